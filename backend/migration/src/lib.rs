@@ -6,6 +6,18 @@ mod m20220101_000001_users;
 mod m20251228_205515_orchestrator_tasks;
 mod m20251228_205522_process_sessions;
 mod m20251228_205527_settings;
+mod m20260105_120000_add_token_usage_to_orchestrator_tasks;
+mod m20260106_120000_add_agent_session_id_to_orchestrator_tasks;
+mod m20260107_120000_add_tags_to_orchestrator_tasks;
+mod m20260108_120000_add_queue_fields_to_orchestrator_tasks;
+mod m20260109_120000_add_queue_position_to_orchestrator_tasks;
+mod m20260110_120000_add_working_subdir_to_orchestrator_tasks;
+mod m20260111_120000_orchestrator_task_logs;
+mod m20260112_120000_add_note_to_orchestrator_tasks;
+mod m20260113_120000_add_agent_type_to_orchestrator_tasks;
+mod m20260114_120000_add_progress_to_orchestrator_tasks;
+mod m20260115_120000_add_env_file_to_orchestrator_tasks;
+mod m20260116_120000_add_restart_count_to_orchestrator_tasks;
 pub struct Migrator;
 
 #[async_trait::async_trait]
@@ -16,6 +28,18 @@ impl MigratorTrait for Migrator {
             Box::new(m20251228_205515_orchestrator_tasks::Migration),
             Box::new(m20251228_205522_process_sessions::Migration),
             Box::new(m20251228_205527_settings::Migration),
+            Box::new(m20260105_120000_add_token_usage_to_orchestrator_tasks::Migration),
+            Box::new(m20260106_120000_add_agent_session_id_to_orchestrator_tasks::Migration),
+            Box::new(m20260107_120000_add_tags_to_orchestrator_tasks::Migration),
+            Box::new(m20260108_120000_add_queue_fields_to_orchestrator_tasks::Migration),
+            Box::new(m20260109_120000_add_queue_position_to_orchestrator_tasks::Migration),
+            Box::new(m20260110_120000_add_working_subdir_to_orchestrator_tasks::Migration),
+            Box::new(m20260111_120000_orchestrator_task_logs::Migration),
+            Box::new(m20260112_120000_add_note_to_orchestrator_tasks::Migration),
+            Box::new(m20260113_120000_add_agent_type_to_orchestrator_tasks::Migration),
+            Box::new(m20260114_120000_add_progress_to_orchestrator_tasks::Migration),
+            Box::new(m20260115_120000_add_env_file_to_orchestrator_tasks::Migration),
+            Box::new(m20260116_120000_add_restart_count_to_orchestrator_tasks::Migration),
             // inject-above (do not remove this comment)
         ]
     }
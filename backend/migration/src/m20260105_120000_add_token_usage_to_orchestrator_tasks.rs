@@ -0,0 +1,22 @@
+use loco_rs::schema::*;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        add_column(m, "orchestrator_tasks", "input_tokens", ColType::IntegerNull).await?;
+        add_column(m, "orchestrator_tasks", "output_tokens", ColType::IntegerNull).await?;
+        add_column(m, "orchestrator_tasks", "estimated_cost", ColType::DoubleNull).await?;
+        Ok(())
+    }
+
+    async fn down(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        remove_column(m, "orchestrator_tasks", "estimated_cost").await?;
+        remove_column(m, "orchestrator_tasks", "output_tokens").await?;
+        remove_column(m, "orchestrator_tasks", "input_tokens").await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,20 @@
+use loco_rs::schema::*;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        add_column(m, "orchestrator_tasks", "depends_on_clickup_id", ColType::StringNull).await?;
+        add_column(m, "orchestrator_tasks", "base_branch", ColType::StringNull).await?;
+        Ok(())
+    }
+
+    async fn down(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        remove_column(m, "orchestrator_tasks", "base_branch").await?;
+        remove_column(m, "orchestrator_tasks", "depends_on_clickup_id").await?;
+        Ok(())
+    }
+}
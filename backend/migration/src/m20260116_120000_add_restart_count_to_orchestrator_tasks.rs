@@ -0,0 +1,30 @@
+use loco_rs::schema::*;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        // Existing rows start with no restarts, so the column gets a default
+        m.alter_table(
+            Table::alter()
+                .table(Alias::new("orchestrator_tasks"))
+                .add_column(
+                    ColumnDef::new(Alias::new("restart_count"))
+                        .integer()
+                        .not_null()
+                        .default(0),
+                )
+                .to_owned(),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        remove_column(m, "orchestrator_tasks", "restart_count").await?;
+        Ok(())
+    }
+}
@@ -12,16 +12,28 @@ use loco_rs::{
 };
 use migration::Migrator;
 use std::path::Path;
+use tower_http::compression::{
+    predicate::{NotForContentType, Predicate, SizeAbove},
+    CompressionLayer,
+};
 
 #[allow(unused_imports)]
 use crate::{
     controllers,
-    initializers::clickup_poller::ClickUpPollerInitializer,
-    models::_entities::users,
+    initializers::{
+        clickup_poller::ClickUpPollerInitializer, process_monitor::ProcessMonitorInitializer,
+        progress_tracker::ProgressTrackerInitializer, repo_fetcher::RepoFetcherInitializer, task_janitor::TaskJanitorInitializer,
+        task_log_listener::TaskLogListenerInitializer,
+    },
+    models::_entities::{settings, users},
+    services::orchestrator,
     tasks,
     workers::downloader::DownloadWorker,
 };
 
+/// Responses smaller than this are sent uncompressed
+const COMPRESSION_MIN_BYTES: u16 = 1024;
+
 pub struct App;
 #[async_trait]
 impl Hooks for App {
@@ -48,7 +60,14 @@ impl Hooks for App {
     }
 
     async fn initializers(_ctx: &AppContext) -> Result<Vec<Box<dyn Initializer>>> {
-        Ok(vec![Box::new(ClickUpPollerInitializer)])
+        Ok(vec![
+            Box::new(ClickUpPollerInitializer),
+            Box::new(ProcessMonitorInitializer),
+            Box::new(TaskJanitorInitializer),
+            Box::new(RepoFetcherInitializer),
+            Box::new(TaskLogListenerInitializer),
+            Box::new(ProgressTrackerInitializer),
+        ])
     }
 
     fn routes(_ctx: &AppContext) -> AppRoutes {
@@ -57,14 +76,46 @@ impl Hooks for App {
             .add_route(controllers::settings::routes())
             .add_route(controllers::clickup::routes())
             .add_route(controllers::git::routes())
+            .add_route(controllers::files::routes())
             .add_route(controllers::tasks::routes())
             .add_route(controllers::setup::routes())
             .add_route(controllers::voice::routes())
+            .add_route(controllers::metrics::routes())
+            .add_route(controllers::sessions::routes())
+            .add_route(controllers::logs::routes())
+            .add_route(controllers::agents::routes())
+            .add_route(controllers::health::routes())
             .add_route(
                 loco_rs::controller::Routes::new()
                     .add("/ws/tasks/{id}/terminal", axum::routing::get(controllers::ws::terminal_handler))
+                    .add("/ws/events", axum::routing::get(controllers::ws::events_handler))
             )
     }
+
+    /// Compress responses for clients that accept gzip or deflate
+    ///
+    /// Disable with the `response_compression` setting set to `false`, e.g.
+    /// when a fronting proxy already compresses. Takes effect on restart.
+    async fn after_routes(router: axum::Router, ctx: &AppContext) -> Result<axum::Router> {
+        let enabled = settings::Entity::get_value(&ctx.db, "response_compression")
+            .await
+            .is_none_or(|v| v != "false");
+        if !enabled {
+            return Ok(router);
+        }
+
+        let predicate = SizeAbove::new(COMPRESSION_MIN_BYTES)
+            .and(NotForContentType::IMAGES)
+            .and(NotForContentType::SSE);
+
+        Ok(router.layer(
+            CompressionLayer::new()
+                .gzip(true)
+                .deflate(true)
+                .compress_when(predicate),
+        ))
+    }
+
     async fn connect_workers(ctx: &AppContext, queue: &Queue) -> Result<()> {
         queue.register(DownloadWorker::build(ctx)).await?;
         Ok(())
@@ -74,6 +125,9 @@ impl Hooks for App {
     fn register_tasks(tasks: &mut Tasks) {
         // tasks-inject (do not remove)
     }
+    async fn on_shutdown(ctx: &AppContext) {
+        orchestrator::shutdown(&ctx.db).await;
+    }
     async fn truncate(ctx: &AppContext) -> Result<()> {
         truncate_table(&ctx.db, users::Entity).await?;
         Ok(())
@@ -0,0 +1,119 @@
+//! Installed CLI agent discovery controller
+
+use crate::services::orchestrator;
+use crate::services::process_manager::{AGENT_TYPES, PROCESS_MANAGER};
+use loco_rs::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long probe results are reused before agents are probed again
+const PROBE_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// How long a single `--version` call may take
+const VERSION_TIMEOUT: Duration = Duration::from_secs(5);
+
+lazy_static::lazy_static! {
+    /// Last probe results and when they were taken
+    static ref PROBE_CACHE: Mutex<Option<(Instant, Vec<AgentInfo>)>> = Mutex::new(None);
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AgentsQuery {
+    /// Ignore cached results and probe the agents again
+    #[serde(default)]
+    pub refresh: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AgentInfo {
+    pub name: String,
+    pub available: bool,
+    /// First line of `<agent> --version`, if it ran
+    pub version: Option<String>,
+    pub path: Option<String>,
+    /// Whether this is the configured `default_agent_type`
+    pub is_default: bool,
+}
+
+/// Run `<path> --version` and return the first line of its output
+async fn agent_version(path: &str) -> Option<String> {
+    let output = tokio::time::timeout(
+        VERSION_TIMEOUT,
+        tokio::process::Command::new(path)
+            .arg("--version")
+            .stdin(std::process::Stdio::null())
+            .kill_on_drop(true)
+            .output(),
+    )
+    .await
+    .ok()?
+    .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .map(str::to_string)
+}
+
+/// Probe every supported agent for its binary and version
+async fn probe_agents(default_agent: &str) -> Vec<AgentInfo> {
+    let mut agents = Vec::with_capacity(AGENT_TYPES.len());
+    for name in AGENT_TYPES {
+        let path = PROCESS_MANAGER.resolve_agent(name).await;
+        let version = match &path {
+            Some(path) => agent_version(path).await,
+            None => None,
+        };
+        agents.push(AgentInfo {
+            name: name.to_string(),
+            available: path.is_some(),
+            version,
+            path,
+            is_default: *name == default_agent,
+        });
+    }
+    agents
+}
+
+/// List the supported agents, whether each is installed and its version
+///
+/// Results are cached for a minute; `?refresh=true` forgets cached binary
+/// paths and probes again, e.g. right after installing an agent.
+#[debug_handler]
+async fn list(State(ctx): State<AppContext>, Query(params): Query<AgentsQuery>) -> Result<Response> {
+    let default_agent = orchestrator::default_agent_type(&ctx.db).await;
+
+    if !params.refresh {
+        let cached = PROBE_CACHE
+            .lock()
+            .ok()
+            .and_then(|cache| cache.clone())
+            .filter(|(probed_at, _)| probed_at.elapsed() < PROBE_CACHE_TTL);
+        if let Some((_, mut agents)) = cached {
+            // The default can change without the installed agents changing
+            for agent in &mut agents {
+                agent.is_default = agent.name == default_agent;
+            }
+            return format::json(agents);
+        }
+    } else {
+        PROCESS_MANAGER.clear_agent_cache();
+    }
+
+    let agents = probe_agents(&default_agent).await;
+    if let Ok(mut cache) = PROBE_CACHE.lock() {
+        *cache = Some((Instant::now(), agents.clone()));
+    }
+
+    format::json(agents)
+}
+
+pub fn routes() -> Routes {
+    Routes::new().prefix("/api/agents").add("/", get(list))
+}
@@ -1,131 +1,238 @@
 //! ClickUp hierarchy browser controller
 
-use crate::services::clickup::ClickUpClient;
+use crate::models::_entities::settings;
+use crate::services::clickup::{self, ClickUpApi, ClickUpClient};
+use axum::http::{HeaderMap, StatusCode};
 use loco_rs::prelude::*;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+
+/// Header carrying a per-request ClickUp API token
+const CLICKUP_TOKEN_HEADER: &str = "X-ClickUp-Token";
 
 #[derive(Debug, Serialize)]
 pub struct ErrorResponse {
     pub error: String,
 }
 
+/// Render a ClickUp error with a status code matching its cause
+///
+/// A missing key is a bad request, validation, auth and lookup failures
+/// reported by ClickUp keep their status, anything else is a bad gateway.
+fn error_response(e: clickup::ClickUpError) -> Result<Response> {
+    let status = match &e {
+        clickup::ClickUpError::NoApiKey | clickup::ClickUpError::UnknownStatus { .. } => {
+            StatusCode::BAD_REQUEST
+        }
+        clickup::ClickUpError::Api { status, .. } => match status.as_u16() {
+            code @ (400 | 401 | 403 | 404 | 429) => {
+                StatusCode::from_u16(code).unwrap_or(StatusCode::BAD_GATEWAY)
+            }
+            _ => StatusCode::BAD_GATEWAY,
+        },
+        clickup::ClickUpError::Request(_) => StatusCode::BAD_GATEWAY,
+    };
+
+    format::render().status(status).json(ErrorResponse {
+        error: error_message(&e),
+    })
+}
+
+/// Describe an error, using just the reason from ClickUp's `{"err": …}` bodies
+fn error_message(e: &clickup::ClickUpError) -> String {
+    if let clickup::ClickUpError::Api { message, .. } = e {
+        let reason = serde_json::from_str::<serde_json::Value>(message)
+            .ok()
+            .and_then(|body| body.get("err")?.as_str().map(str::to_string));
+        if let Some(reason) = reason {
+            return format!("ClickUp rejected the request: {}", reason);
+        }
+    }
+    e.to_string()
+}
+
+/// Build a client from the request's token header, falling back to the configured client
+fn client_for_request(headers: &HeaderMap) -> clickup::Result<Box<dyn ClickUpApi>> {
+    let token = headers
+        .get(CLICKUP_TOKEN_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|t| !t.is_empty());
+
+    match token {
+        Some(token) => Ok(Box::new(ClickUpClient::new(token.to_string()))),
+        None => clickup::client_from_env(),
+    }
+}
+
 /// Get all workspaces (teams) the user has access to
 #[debug_handler]
-async fn get_workspaces() -> Result<Response> {
-    let client = match ClickUpClient::from_env() {
+async fn get_workspaces(headers: HeaderMap) -> Result<Response> {
+    let client = match client_for_request(&headers) {
         Ok(c) => c,
-        Err(e) => {
-            return format::json(ErrorResponse {
-                error: e.to_string(),
-            });
-        }
+        Err(e) => return error_response(e),
     };
 
     match client.get_workspaces().await {
         Ok(teams) => format::json(teams),
-        Err(e) => format::json(ErrorResponse {
-            error: e.to_string(),
-        }),
+        Err(e) => error_response(e),
     }
 }
 
 /// Get all spaces in a workspace
 #[debug_handler]
-async fn get_spaces(Path(team_id): Path<String>) -> Result<Response> {
-    let client = match ClickUpClient::from_env() {
+async fn get_spaces(headers: HeaderMap, Path(team_id): Path<String>) -> Result<Response> {
+    let client = match client_for_request(&headers) {
         Ok(c) => c,
-        Err(e) => {
-            return format::json(ErrorResponse {
-                error: e.to_string(),
-            });
-        }
+        Err(e) => return error_response(e),
     };
 
     match client.get_spaces(&team_id).await {
         Ok(spaces) => format::json(spaces),
-        Err(e) => format::json(ErrorResponse {
-            error: e.to_string(),
-        }),
+        Err(e) => error_response(e),
     }
 }
 
 /// Get all folders in a space
 #[debug_handler]
-async fn get_folders(Path(space_id): Path<String>) -> Result<Response> {
-    let client = match ClickUpClient::from_env() {
+async fn get_folders(headers: HeaderMap, Path(space_id): Path<String>) -> Result<Response> {
+    let client = match client_for_request(&headers) {
         Ok(c) => c,
-        Err(e) => {
-            return format::json(ErrorResponse {
-                error: e.to_string(),
-            });
-        }
+        Err(e) => return error_response(e),
     };
 
     match client.get_folders(&space_id).await {
         Ok(folders) => format::json(folders),
-        Err(e) => format::json(ErrorResponse {
-            error: e.to_string(),
-        }),
+        Err(e) => error_response(e),
     }
 }
 
 /// Get all lists in a folder
 #[debug_handler]
-async fn get_lists_in_folder(Path(folder_id): Path<String>) -> Result<Response> {
-    let client = match ClickUpClient::from_env() {
+async fn get_lists_in_folder(headers: HeaderMap, Path(folder_id): Path<String>) -> Result<Response> {
+    let client = match client_for_request(&headers) {
         Ok(c) => c,
-        Err(e) => {
-            return format::json(ErrorResponse {
-                error: e.to_string(),
-            });
-        }
+        Err(e) => return error_response(e),
     };
 
     match client.get_lists_in_folder(&folder_id).await {
         Ok(lists) => format::json(lists),
-        Err(e) => format::json(ErrorResponse {
-            error: e.to_string(),
-        }),
+        Err(e) => error_response(e),
     }
 }
 
 /// Get folderless lists in a space
 #[debug_handler]
-async fn get_folderless_lists(Path(space_id): Path<String>) -> Result<Response> {
-    let client = match ClickUpClient::from_env() {
+async fn get_folderless_lists(headers: HeaderMap, Path(space_id): Path<String>) -> Result<Response> {
+    let client = match client_for_request(&headers) {
         Ok(c) => c,
-        Err(e) => {
-            return format::json(ErrorResponse {
-                error: e.to_string(),
-            });
-        }
+        Err(e) => return error_response(e),
     };
 
     match client.get_folderless_lists(&space_id).await {
         Ok(lists) => format::json(lists),
-        Err(e) => format::json(ErrorResponse {
-            error: e.to_string(),
-        }),
+        Err(e) => error_response(e),
     }
 }
 
 /// Get statuses for a list
 #[debug_handler]
-async fn get_list_statuses(Path(list_id): Path<String>) -> Result<Response> {
-    let client = match ClickUpClient::from_env() {
+async fn get_list_statuses(headers: HeaderMap, Path(list_id): Path<String>) -> Result<Response> {
+    let client = match client_for_request(&headers) {
         Ok(c) => c,
-        Err(e) => {
-            return format::json(ErrorResponse {
-                error: e.to_string(),
-            });
-        }
+        Err(e) => return error_response(e),
     };
 
     match client.get_list_statuses(&list_id).await {
         Ok(statuses) => format::json(statuses),
-        Err(e) => format::json(ErrorResponse {
-            error: e.to_string(),
-        }),
+        Err(e) => error_response(e),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListTasksQuery {
+    pub status: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TaskPreview {
+    pub id: String,
+    pub name: String,
+    pub status: String,
+    /// Priority as the poller computes it (1=urgent, 4=low)
+    pub priority: Option<i32>,
+}
+
+/// Preview the tasks in a list without queueing them
+///
+/// Priorities are resolved the same way the poller does, including the
+/// configured priority custom field.
+#[debug_handler]
+async fn get_list_tasks(
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+    Path(list_id): Path<String>,
+    Query(query): Query<ListTasksQuery>,
+) -> Result<Response> {
+    let client = match client_for_request(&headers) {
+        Ok(c) => c,
+        Err(e) => return error_response(e),
+    };
+
+    let priority_field_id = settings::Entity::get_value(&ctx.db, "priority_custom_field_id").await;
+
+    let filter = clickup::TaskFilter::default();
+    match client.get_tasks(&list_id, query.status.as_deref(), &filter).await {
+        Ok(tasks) => {
+            let mut previews: Vec<TaskPreview> = tasks
+                .into_iter()
+                .map(|t| TaskPreview {
+                    priority: t.priority_value(priority_field_id.as_deref()),
+                    id: t.id,
+                    name: t.name,
+                    status: t.status.status,
+                })
+                .collect();
+            previews.sort_by_key(|t| t.priority.unwrap_or(99));
+            format::json(previews)
+        }
+        Err(e) => error_response(e),
+    }
+}
+
+/// Create a task in a ClickUp list
+///
+/// Returns the task as ClickUp created it. Validation errors reported by
+/// ClickUp, e.g. an unknown status, are passed on as bad requests.
+#[debug_handler]
+async fn create_list_task(
+    headers: HeaderMap,
+    Path(list_id): Path<String>,
+    Json(mut params): Json<clickup::CreateTaskRequest>,
+) -> Result<Response> {
+    if list_id.is_empty() || !list_id.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(Error::BadRequest(format!("Invalid list id: {}", list_id)));
+    }
+    params.name = params.name.trim().to_string();
+    if params.name.is_empty() {
+        return Err(Error::BadRequest("Task name cannot be empty".to_string()));
+    }
+    if params.priority.is_some_and(|p| !(1..=4).contains(&p)) {
+        return Err(Error::BadRequest(
+            "Priority must be between 1 (urgent) and 4 (low)".to_string(),
+        ));
+    }
+
+    let client = match client_for_request(&headers) {
+        Ok(c) => c,
+        Err(e) => return error_response(e),
+    };
+
+    match client.create_task(&list_id, &params).await {
+        Ok(task) => {
+            tracing::info!("Created ClickUp task {} in list {}", task.id, list_id);
+            format::json(task)
+        }
+        Err(e) => error_response(e),
     }
 }
 
@@ -138,4 +245,8 @@ pub fn routes() -> Routes {
         .add("/folders/{folder_id}/lists", get(get_lists_in_folder))
         .add("/spaces/{space_id}/lists", get(get_folderless_lists))
         .add("/lists/{list_id}/statuses", get(get_list_statuses))
+        .add(
+            "/lists/{list_id}/tasks",
+            get(get_list_tasks).post(create_list_task),
+        )
 }
@@ -0,0 +1,191 @@
+//! Repository file search controller
+
+use crate::models::_entities::settings;
+use crate::services::orchestrator;
+use loco_rs::prelude::*;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use walkdir::{DirEntry, WalkDir};
+
+/// Default cap on the number of matches returned by a search
+const DEFAULT_MAX_RESULTS: usize = 200;
+
+/// Files larger than this are not searched
+const MAX_FILE_BYTES: u64 = 2 * 1024 * 1024;
+
+/// Directories never descended into: VCS metadata, dependencies and build output
+const SKIP_DIRS: &[&str] = &[
+    ".git",
+    "node_modules",
+    "target",
+    ".svelte-kit",
+    "dist",
+    "build",
+    ".next",
+    "__pycache__",
+    ".venv",
+];
+
+#[derive(Debug, Deserialize)]
+pub struct SearchQuery {
+    /// Directory to search, inside the target repository
+    pub path: String,
+    pub query: String,
+    /// Treat `query` as a regular expression instead of a literal string
+    #[serde(default)]
+    pub regex: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SearchMatch {
+    pub path: String,
+    pub line_number: usize,
+    pub line: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SearchResponse {
+    pub matches: Vec<SearchMatch>,
+    /// Whether the search stopped at the result limit
+    pub truncated: bool,
+}
+
+/// Resolve `path` and make sure it lies inside the configured target repository
+async fn confined_path(db: &sea_orm::DatabaseConnection, path: &str) -> Result<(PathBuf, PathBuf)> {
+    let repo_path = settings::Entity::get_value(db, "target_repo_path")
+        .await
+        .ok_or_else(|| Error::BadRequest("Target repo path not configured".to_string()))?;
+
+    let repo = std::fs::canonicalize(&repo_path)
+        .map_err(|e| Error::BadRequest(format!("Invalid target repo path: {}", e)))?;
+    let requested = std::fs::canonicalize(path)
+        .map_err(|e| Error::BadRequest(format!("Invalid path {}: {}", path, e)))?;
+
+    if !requested.starts_with(&repo) {
+        return Err(Error::BadRequest(
+            "Path must be inside the target repository".to_string(),
+        ));
+    }
+    if !requested.is_dir() {
+        return Err(Error::BadRequest("Path is not a directory".to_string()));
+    }
+
+    Ok((repo, requested))
+}
+
+/// Whether a directory entry should be skipped along with its contents
+fn is_skipped(entry: &DirEntry, worktrees_dir: &Path) -> bool {
+    entry.file_type().is_dir()
+        && entry.depth() > 0
+        && (entry
+            .file_name()
+            .to_str()
+            .is_some_and(|name| SKIP_DIRS.contains(&name))
+            || entry.path() == worktrees_dir)
+}
+
+/// Walk `root` and collect matching lines, stopping after `max_results`
+fn search_tree(
+    root: &Path,
+    repo: &Path,
+    worktrees_dir: &Path,
+    pattern: &Regex,
+    max_results: usize,
+) -> SearchResponse {
+    let mut matches = Vec::new();
+
+    let files = WalkDir::new(root)
+        .follow_links(false)
+        .into_iter()
+        .filter_entry(|e| !is_skipped(e, worktrees_dir))
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter(|e| e.metadata().is_ok_and(|m| m.len() <= MAX_FILE_BYTES));
+
+    for entry in files {
+        let Ok(bytes) = std::fs::read(entry.path()) else {
+            continue;
+        };
+
+        // Treat a NUL byte near the start as a binary file, like git does
+        if bytes[..bytes.len().min(8000)].contains(&0) {
+            continue;
+        }
+
+        let relative = entry
+            .path()
+            .strip_prefix(repo)
+            .unwrap_or(entry.path())
+            .to_string_lossy()
+            .to_string();
+
+        for (index, line) in String::from_utf8_lossy(&bytes).lines().enumerate() {
+            if !pattern.is_match(line) {
+                continue;
+            }
+            if matches.len() == max_results {
+                return SearchResponse {
+                    matches,
+                    truncated: true,
+                };
+            }
+            matches.push(SearchMatch {
+                path: relative.clone(),
+                line_number: index + 1,
+                line: line.to_string(),
+            });
+        }
+    }
+
+    SearchResponse {
+        matches,
+        truncated: false,
+    }
+}
+
+/// Search file contents under a directory of the target repository
+///
+/// Paths in the results are relative to the repository root. The number of
+/// matches is capped by the `file_search_max_results` setting.
+#[debug_handler]
+async fn search(State(ctx): State<AppContext>, Query(params): Query<SearchQuery>) -> Result<Response> {
+    if params.query.is_empty() {
+        return Err(Error::BadRequest("query cannot be empty".to_string()));
+    }
+
+    let (repo, root) = confined_path(&ctx.db, &params.path).await?;
+
+    let source = if params.regex {
+        params.query.clone()
+    } else {
+        regex::escape(&params.query)
+    };
+    let pattern = RegexBuilder::new(&source)
+        .size_limit(1 << 20)
+        .build()
+        .map_err(|e| Error::BadRequest(format!("Invalid regex: {}", e)))?;
+
+    let max_results = settings::Entity::get_value(&ctx.db, "file_search_max_results")
+        .await
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_MAX_RESULTS);
+
+    // Task worktrees default to living inside the repo; don't search their copies
+    let worktrees_dir = PathBuf::from(
+        orchestrator::configured_worktrees_dir(&ctx.db, &repo.to_string_lossy()).await,
+    );
+    let worktrees_dir = std::fs::canonicalize(&worktrees_dir).unwrap_or(worktrees_dir);
+
+    let response = tokio::task::spawn_blocking(move || {
+        search_tree(&root, &repo, &worktrees_dir, &pattern, max_results)
+    })
+    .await
+    .map_err(|e| Error::string(&format!("Search failed: {}", e)))?;
+
+    format::json(response)
+}
+
+pub fn routes() -> Routes {
+    Routes::new().prefix("/api/files").add("/search", get(search))
+}
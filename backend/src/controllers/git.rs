@@ -1,6 +1,9 @@
 //! Git repository validation and branch listing controller
 
+use crate::models::_entities::orchestrator_tasks;
+use crate::services::{git, orchestrator};
 use loco_rs::prelude::*;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 use std::process::Command;
@@ -20,11 +23,23 @@ pub struct ValidatePathResponse {
 #[derive(Debug, Deserialize)]
 pub struct BranchesQuery {
     pub path: String,
+    /// Leave out the orchestrator's task branches, recognized by the fixed
+    /// prefix of `branch_template`
+    #[serde(default)]
+    pub exclude_task_branches: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BranchInfo {
+    pub name: String,
+    /// Worktree the branch is checked out in, if any
+    pub worktree_path: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct BranchesResponse {
     pub branches: Vec<String>,
+    pub details: Vec<BranchInfo>,
     pub current: Option<String>,
 }
 
@@ -83,7 +98,10 @@ async fn validate_path(Json(params): Json<ValidatePathRequest>) -> Result<Respon
 
 /// Get all branches in a git repository
 #[debug_handler]
-async fn get_branches(Query(params): Query<BranchesQuery>) -> Result<Response> {
+async fn get_branches(
+    State(ctx): State<AppContext>,
+    Query(params): Query<BranchesQuery>,
+) -> Result<Response> {
     let path = Path::new(&params.path);
 
     // Check if path exists and is a git repo
@@ -109,6 +127,9 @@ async fn get_branches(Query(params): Query<BranchesQuery>) -> Result<Response> {
         }
     });
 
+    let template = orchestrator::branch_template(&ctx.db).await;
+    let task_prefix = orchestrator::branch_prefix(&template);
+
     // Get all local branches
     let branches_output = Command::new("git")
         .args(["branch", "--format=%(refname:short)"])
@@ -122,10 +143,37 @@ async fn get_branches(Query(params): Query<BranchesQuery>) -> Result<Response> {
                     .lines()
                     .map(|s| s.trim().to_string())
                     .filter(|s| !s.is_empty())
+                    .filter(|s| {
+                        !params.exclude_task_branches
+                            || task_prefix.is_empty()
+                            || !s.starts_with(task_prefix)
+                    })
+                    .collect();
+
+                // Note which branches are checked out in a worktree
+                let worktrees = Command::new("git")
+                    .args(["worktree", "list", "--porcelain"])
+                    .current_dir(path)
+                    .output()
+                    .ok()
+                    .filter(|o| o.status.success())
+                    .map(|o| parse_worktree_list(&String::from_utf8_lossy(&o.stdout)))
+                    .unwrap_or_default();
+
+                let details = branches
+                    .iter()
+                    .map(|name| BranchInfo {
+                        name: name.clone(),
+                        worktree_path: worktrees
+                            .iter()
+                            .find(|w| w.branch.as_deref() == Some(name.as_str()))
+                            .map(|w| w.path.clone()),
+                    })
                     .collect();
 
                 format::json(BranchesResponse {
                     branches,
+                    details,
                     current: current_branch,
                 })
             } else {
@@ -142,7 +190,10 @@ async fn get_branches(Query(params): Query<BranchesQuery>) -> Result<Response> {
 
 /// Fetch latest from remote
 #[debug_handler]
-async fn fetch(Json(params): Json<ValidatePathRequest>) -> Result<Response> {
+async fn fetch(
+    State(ctx): State<AppContext>,
+    Json(params): Json<ValidatePathRequest>,
+) -> Result<Response> {
     let path = Path::new(&params.path);
 
     if !path.exists() || !path.is_dir() {
@@ -151,7 +202,11 @@ async fn fetch(Json(params): Json<ValidatePathRequest>) -> Result<Response> {
         });
     }
 
-    let output = Command::new("git")
+    let mut command = Command::new("git");
+    if let Some(ssh_command) = git::ssh_command(&ctx.db).await {
+        command.env("GIT_SSH_COMMAND", ssh_command);
+    }
+    let output = command
         .args(["fetch", "--all", "--prune"])
         .current_dir(path)
         .output();
@@ -264,11 +319,305 @@ async fn detect_path(Json(params): Json<DetectPathRequest>) -> Result<Response>
     })
 }
 
+#[derive(Debug, Deserialize)]
+pub struct WorktreesQuery {
+    pub path: String,
+}
+
+#[derive(Debug, Serialize, Default)]
+pub struct WorktreeEntry {
+    pub path: String,
+    pub branch: Option<String>,
+    pub head: Option<String>,
+    pub locked: bool,
+    pub task_id: Option<i32>,
+    pub task_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RemoveWorktreeRequest {
+    pub path: String,
+}
+
+/// Parse the output of `git worktree list --porcelain`
+fn parse_worktree_list(output: &str) -> Vec<WorktreeEntry> {
+    let mut entries = Vec::new();
+    let mut current: Option<WorktreeEntry> = None;
+
+    for line in output.lines() {
+        if let Some(path) = line.strip_prefix("worktree ") {
+            if let Some(entry) = current.take() {
+                entries.push(entry);
+            }
+            current = Some(WorktreeEntry {
+                path: path.to_string(),
+                ..Default::default()
+            });
+        } else if let Some(entry) = current.as_mut() {
+            if let Some(head) = line.strip_prefix("HEAD ") {
+                entry.head = Some(head.to_string());
+            } else if let Some(branch) = line.strip_prefix("branch ") {
+                entry.branch = Some(branch.trim_start_matches("refs/heads/").to_string());
+            } else if line == "locked" || line.starts_with("locked ") {
+                entry.locked = true;
+            }
+        }
+    }
+
+    if let Some(entry) = current {
+        entries.push(entry);
+    }
+
+    entries
+}
+
+/// Normalize a path for comparison, resolving symlinks when it exists
+fn canonical_path(path: &str) -> String {
+    std::fs::canonicalize(path)
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_else(|_| path.trim_end_matches('/').to_string())
+}
+
+/// List the worktrees of a repository and the task owning each one
+#[debug_handler]
+async fn list_worktrees(
+    State(ctx): State<AppContext>,
+    Query(params): Query<WorktreesQuery>,
+) -> Result<Response> {
+    let path = Path::new(&params.path);
+
+    if !path.exists() || !path.is_dir() {
+        return format::json(ErrorResponse {
+            error: "Invalid path".to_string(),
+        });
+    }
+
+    let output = match Command::new("git")
+        .args(["worktree", "list", "--porcelain"])
+        .current_dir(path)
+        .output()
+    {
+        Ok(output) if output.status.success() => output,
+        Ok(output) => {
+            return format::json(ErrorResponse {
+                error: String::from_utf8_lossy(&output.stderr).to_string(),
+            });
+        }
+        Err(e) => {
+            return format::json(ErrorResponse {
+                error: format!("Failed to list worktrees: {}", e),
+            });
+        }
+    };
+
+    let mut worktrees = parse_worktree_list(&String::from_utf8_lossy(&output.stdout));
+
+    // Cross-reference with the tasks that own a worktree
+    let tasks = orchestrator_tasks::Entity::find()
+        .filter(orchestrator_tasks::Column::WorktreePath.is_not_null())
+        .all(&ctx.db)
+        .await?;
+
+    for worktree in &mut worktrees {
+        let worktree_path = canonical_path(&worktree.path);
+        if let Some(task) = tasks.iter().find(|t| {
+            t.worktree_path
+                .as_deref()
+                .is_some_and(|p| canonical_path(p) == worktree_path)
+        }) {
+            worktree.task_id = Some(task.id);
+            worktree.task_name = Some(task.name.clone());
+        }
+    }
+
+    format::json(worktrees)
+}
+
+/// Remove a specific worktree with `git worktree remove --force`
+#[debug_handler]
+async fn remove_worktree(Json(params): Json<RemoveWorktreeRequest>) -> Result<Response> {
+    let path = Path::new(&params.path);
+
+    if !path.exists() || !path.is_dir() {
+        return format::json(ErrorResponse {
+            error: "Invalid path".to_string(),
+        });
+    }
+
+    // Resolve the main repository from the worktree's common git dir
+    let common_dir = Command::new("git")
+        .args(["rev-parse", "--git-common-dir"])
+        .current_dir(path)
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .map(|o| path.join(String::from_utf8_lossy(&o.stdout).trim()));
+
+    let Some(repo_path) = common_dir.as_deref().and_then(Path::parent) else {
+        return format::json(ErrorResponse {
+            error: "Path is not a git worktree".to_string(),
+        });
+    };
+
+    let output = Command::new("git")
+        .args(["worktree", "remove", "--force", &params.path])
+        .current_dir(repo_path)
+        .output();
+
+    match output {
+        Ok(output) => {
+            if output.status.success() {
+                tracing::info!("Removed worktree {}", params.path);
+                format::json(serde_json::json!({ "success": true }))
+            } else {
+                format::json(ErrorResponse {
+                    error: String::from_utf8_lossy(&output.stderr).to_string(),
+                })
+            }
+        }
+        Err(e) => format::json(ErrorResponse {
+            error: format!("Failed to remove worktree: {}", e),
+        }),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StatusQuery {
+    pub path: String,
+}
+
+#[derive(Debug, Serialize, Default)]
+pub struct StatusResponse {
+    /// Checked out branch, `None` on a detached HEAD
+    pub branch: Option<String>,
+    /// Upstream such as `origin/main`, `None` when the branch doesn't track one
+    pub upstream: Option<String>,
+    pub ahead: u32,
+    pub behind: u32,
+    /// Whether there are uncommitted or untracked changes
+    pub dirty: bool,
+    pub remote_url: Option<String>,
+}
+
+/// Parse the output of `git status --porcelain=v2 --branch`
+fn parse_status(output: &str) -> StatusResponse {
+    let mut status = StatusResponse::default();
+
+    for line in output.lines() {
+        if let Some(head) = line.strip_prefix("# branch.head ") {
+            status.branch = (head != "(detached)").then(|| head.to_string());
+        } else if let Some(upstream) = line.strip_prefix("# branch.upstream ") {
+            status.upstream = Some(upstream.to_string());
+        } else if let Some(ab) = line.strip_prefix("# branch.ab ") {
+            for part in ab.split_whitespace() {
+                if let Some(n) = part.strip_prefix('+') {
+                    status.ahead = n.parse().unwrap_or(0);
+                } else if let Some(n) = part.strip_prefix('-') {
+                    status.behind = n.parse().unwrap_or(0);
+                }
+            }
+        } else if !line.starts_with('#') && !line.is_empty() {
+            status.dirty = true;
+        }
+    }
+
+    status
+}
+
+/// Get a worktree's branch, upstream sync state and remote URL
+///
+/// A branch without an upstream reports `upstream: null` with zero
+/// ahead/behind; `remote_url` then falls back to `origin`.
+#[debug_handler]
+async fn status(Query(params): Query<StatusQuery>) -> Result<Response> {
+    let path = Path::new(&params.path);
+
+    if !path.exists() || !path.is_dir() {
+        return format::json(ErrorResponse {
+            error: "Invalid path".to_string(),
+        });
+    }
+
+    let output = match Command::new("git")
+        .args(["status", "--porcelain=v2", "--branch"])
+        .current_dir(path)
+        .output()
+    {
+        Ok(output) if output.status.success() => output,
+        Ok(output) => {
+            return format::json(ErrorResponse {
+                error: String::from_utf8_lossy(&output.stderr).to_string(),
+            })
+        }
+        Err(e) => {
+            return format::json(ErrorResponse {
+                error: format!("Failed to get status: {}", e),
+            })
+        }
+    };
+
+    let mut status = parse_status(&String::from_utf8_lossy(&output.stdout));
+
+    // The upstream's remote, e.g. `origin` for `origin/main`
+    let remote = status
+        .branch
+        .as_ref()
+        .and_then(|branch| {
+            Command::new("git")
+                .args(["config", &format!("branch.{}.remote", branch)])
+                .current_dir(path)
+                .output()
+                .ok()
+                .filter(|o| o.status.success())
+                .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+        })
+        .filter(|remote| !remote.is_empty() && remote != ".")
+        .unwrap_or_else(|| "origin".to_string());
+
+    status.remote_url = Command::new("git")
+        .args(["remote", "get-url", &remote])
+        .current_dir(path)
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+        .filter(|url| !url.is_empty());
+
+    format::json(status)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DefaultBranchRequest {
+    pub path: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DefaultBranchResponse {
+    /// The remote's default branch, `None` when it can't be determined
+    pub branch: Option<String>,
+}
+
+/// Detect a repository's default branch so setup can pre-fill `dev_branch`
+#[debug_handler]
+async fn default_branch(Json(params): Json<DefaultBranchRequest>) -> Result<Response> {
+    if !Path::new(&params.path).join(".git").exists() {
+        return Err(Error::BadRequest("Not a git repository".to_string()));
+    }
+
+    format::json(DefaultBranchResponse {
+        branch: git::detect_default_branch(&params.path).await,
+    })
+}
+
 pub fn routes() -> Routes {
     Routes::new()
         .prefix("/api/git")
         .add("/validate-path", post(validate_path))
         .add("/branches", get(get_branches))
         .add("/fetch", post(fetch))
+        .add("/status", get(status))
         .add("/detect-path", post(detect_path))
+        .add("/default-branch", post(default_branch))
+        .add("/worktrees", get(list_worktrees))
+        .add("/worktrees", axum::routing::delete(remove_worktree))
 }
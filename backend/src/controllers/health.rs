@@ -0,0 +1,25 @@
+//! Health controller reporting the state of external dependencies
+
+use crate::services::clickup::CLICKUP_BREAKER;
+use crate::services::process_manager::PROCESS_MANAGER;
+use loco_rs::prelude::*;
+
+/// Report orchestrator health
+///
+/// `status` is `degraded` while the ClickUp circuit breaker is open and the
+/// poller is backing off.
+#[debug_handler]
+async fn health() -> Result<Response> {
+    let clickup = CLICKUP_BREAKER.status();
+    let status = if clickup.open { "degraded" } else { "ok" };
+
+    format::json(serde_json::json!({
+        "status": status,
+        "clickup": clickup,
+        "running_processes": PROCESS_MANAGER.running_tasks().len(),
+    }))
+}
+
+pub fn routes() -> Routes {
+    Routes::new().prefix("/api/health").add("/", get(health))
+}
@@ -0,0 +1,108 @@
+//! Global task log controller for auditing activity across tasks
+
+use crate::models::_entities::{orchestrator_task_logs, orchestrator_tasks};
+use crate::services::orchestrator;
+use loco_rs::prelude::*;
+use sea_orm::{ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Default and maximum number of log rows returned per page
+const DEFAULT_LOG_LIMIT: u64 = 100;
+const MAX_LOG_LIMIT: u64 = 1000;
+
+#[derive(Debug, Deserialize)]
+pub struct GlobalLogsQuery {
+    pub event_type: Option<String>,
+    pub task_id: Option<i32>,
+    /// Only return rows created at or after this RFC 3339 timestamp
+    pub since: Option<String>,
+    pub limit: Option<u64>,
+    #[serde(default)]
+    pub offset: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GlobalLogEntry {
+    pub id: i32,
+    pub task_id: i32,
+    /// Name of the task, `None` if it has been deleted
+    pub task_name: Option<String>,
+    pub event_type: String,
+    pub message: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GlobalLogsPage {
+    pub logs: Vec<GlobalLogEntry>,
+    pub total: u64,
+}
+
+/// List task log rows across all tasks, newest first
+///
+/// Filters combine; `limit` is capped at 1000. Timestamps are shown in the
+/// `display_timezone`.
+#[debug_handler]
+async fn list(
+    State(ctx): State<AppContext>,
+    Query(query): Query<GlobalLogsQuery>,
+) -> Result<Response> {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_LOG_LIMIT)
+        .clamp(1, MAX_LOG_LIMIT);
+
+    let mut find = orchestrator_task_logs::Entity::find();
+    if let Some(event_type) = query.event_type.filter(|t| !t.is_empty()) {
+        find = find.filter(orchestrator_task_logs::Column::EventType.eq(event_type));
+    }
+    if let Some(task_id) = query.task_id {
+        find = find.filter(orchestrator_task_logs::Column::TaskId.eq(task_id));
+    }
+    if let Some(since) = query.since.filter(|s| !s.is_empty()) {
+        let since = chrono::DateTime::parse_from_rfc3339(&since)
+            .map_err(|e| Error::BadRequest(format!("Invalid since timestamp: {}", e)))?;
+        find = find.filter(orchestrator_task_logs::Column::CreatedAt.gte(since));
+    }
+
+    let total = find.clone().count(&ctx.db).await?;
+
+    let rows = find
+        .order_by_desc(orchestrator_task_logs::Column::CreatedAt)
+        .order_by_desc(orchestrator_task_logs::Column::Id)
+        .offset(query.offset)
+        .limit(limit)
+        .all(&ctx.db)
+        .await?;
+
+    let mut task_ids: Vec<i32> = rows.iter().map(|log| log.task_id).collect();
+    task_ids.sort_unstable();
+    task_ids.dedup();
+    let names: HashMap<i32, String> = orchestrator_tasks::Entity::find()
+        .filter(orchestrator_tasks::Column::Id.is_in(task_ids))
+        .all(&ctx.db)
+        .await?
+        .into_iter()
+        .map(|task| (task.id, task.name))
+        .collect();
+
+    let timezone = orchestrator::display_timezone(&ctx.db).await;
+    let logs = rows
+        .into_iter()
+        .map(|log| GlobalLogEntry {
+            id: log.id,
+            task_id: log.task_id,
+            task_name: names.get(&log.task_id).cloned(),
+            event_type: log.event_type,
+            message: log.message,
+            created_at: log.created_at.with_timezone(&timezone).to_rfc3339(),
+        })
+        .collect();
+
+    format::json(GlobalLogsPage { logs, total })
+}
+
+pub fn routes() -> Routes {
+    Routes::new().prefix("/api/logs").add("/", get(list))
+}
@@ -0,0 +1,133 @@
+//! Prometheus metrics controller
+
+use crate::models::_entities::orchestrator_tasks;
+use crate::services::metrics::{
+    self, AGENT_EXITS_FAILURE_TOTAL, AGENT_EXITS_SUCCESS_TOTAL, AGENT_SPAWNS_TOTAL,
+    CLICKUP_API_ERRORS_TOTAL, OUTPUT_LINES_DROPPED_TOTAL, POLLER_CYCLES_TOTAL,
+};
+use crate::services::process_manager::PROCESS_MANAGER;
+use loco_rs::prelude::*;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QuerySelect};
+use std::fmt::Write;
+
+/// Append a single metric with its HELP and TYPE lines
+fn write_metric(out: &mut String, name: &str, kind: &str, help: &str, value: impl std::fmt::Display) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+/// Expose metrics in the Prometheus text exposition format
+#[debug_handler]
+async fn render_metrics(State(ctx): State<AppContext>) -> Result<Response> {
+    let mut out = String::new();
+
+    // Tasks by status
+    let counts: Vec<(String, i64)> = orchestrator_tasks::Entity::find()
+        .select_only()
+        .column(orchestrator_tasks::Column::Status)
+        .column_as(orchestrator_tasks::Column::Id.count(), "count")
+        .group_by(orchestrator_tasks::Column::Status)
+        .into_tuple()
+        .all(&ctx.db)
+        .await?;
+
+    let _ = writeln!(out, "# HELP orchestrator_tasks Number of tasks by status");
+    let _ = writeln!(out, "# TYPE orchestrator_tasks gauge");
+    for (status, count) in counts {
+        let _ = writeln!(out, "orchestrator_tasks{{status=\"{}\"}} {}", status, count);
+    }
+
+    write_metric(
+        &mut out,
+        "orchestrator_running_processes",
+        "gauge",
+        "Number of agent processes currently running",
+        PROCESS_MANAGER.running_tasks().len(),
+    );
+    write_metric(
+        &mut out,
+        "orchestrator_poller_cycles_total",
+        "counter",
+        "Number of completed ClickUp poller cycles",
+        metrics::get(&POLLER_CYCLES_TOTAL),
+    );
+    write_metric(
+        &mut out,
+        "orchestrator_clickup_api_errors_total",
+        "counter",
+        "Number of failed ClickUp API requests",
+        metrics::get(&CLICKUP_API_ERRORS_TOTAL),
+    );
+    write_metric(
+        &mut out,
+        "orchestrator_agent_spawns_total",
+        "counter",
+        "Number of agent processes spawned",
+        metrics::get(&AGENT_SPAWNS_TOTAL),
+    );
+
+    let _ = writeln!(out, "# HELP orchestrator_agent_exits_total Number of agent process exits by result");
+    let _ = writeln!(out, "# TYPE orchestrator_agent_exits_total counter");
+    let _ = writeln!(
+        out,
+        "orchestrator_agent_exits_total{{result=\"success\"}} {}",
+        metrics::get(&AGENT_EXITS_SUCCESS_TOTAL)
+    );
+    let _ = writeln!(
+        out,
+        "orchestrator_agent_exits_total{{result=\"failure\"}} {}",
+        metrics::get(&AGENT_EXITS_FAILURE_TOTAL)
+    );
+
+    write_metric(
+        &mut out,
+        "orchestrator_output_buffer_capacity",
+        "gauge",
+        "Number of output lines each subscriber can fall behind before dropping",
+        PROCESS_MANAGER.output_capacity(),
+    );
+    write_metric(
+        &mut out,
+        "orchestrator_output_buffer_lag",
+        "gauge",
+        "Number of output lines not yet seen by the slowest subscriber",
+        PROCESS_MANAGER.output_backlog(),
+    );
+    write_metric(
+        &mut out,
+        "orchestrator_output_lines_dropped_total",
+        "counter",
+        "Number of output lines dropped because a subscriber fell behind",
+        metrics::get(&OUTPUT_LINES_DROPPED_TOTAL),
+    );
+
+    // Average duration of completed tasks
+    let durations: Vec<i32> = orchestrator_tasks::Entity::find()
+        .select_only()
+        .column(orchestrator_tasks::Column::TimeSpentMs)
+        .filter(orchestrator_tasks::Column::Status.eq("completed"))
+        .into_tuple()
+        .all(&ctx.db)
+        .await?;
+
+    let average_secs = if durations.is_empty() {
+        0.0
+    } else {
+        durations.iter().map(|d| f64::from(*d)).sum::<f64>() / durations.len() as f64 / 1000.0
+    };
+
+    write_metric(
+        &mut out,
+        "orchestrator_task_duration_seconds_avg",
+        "gauge",
+        "Average time spent on completed tasks",
+        average_secs,
+    );
+
+    format::text(&out)
+}
+
+pub fn routes() -> Routes {
+    Routes::new().add("/metrics", get(render_metrics))
+}
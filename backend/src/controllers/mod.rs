@@ -1,6 +1,12 @@
+pub mod agents;
 pub mod auth;
 pub mod clickup;
+pub mod files;
 pub mod git;
+pub mod health;
+pub mod logs;
+pub mod metrics;
+pub mod sessions;
 pub mod settings;
 pub mod setup;
 pub mod tasks;
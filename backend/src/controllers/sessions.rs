@@ -0,0 +1,79 @@
+//! Process session history controller
+
+use crate::models::_entities::process_sessions;
+use loco_rs::prelude::*;
+use sea_orm::{EntityTrait, PaginatorTrait, QueryOrder, QuerySelect};
+use serde::{Deserialize, Serialize};
+
+/// Default and maximum number of sessions returned per page
+const DEFAULT_SESSION_LIMIT: u64 = 50;
+const MAX_SESSION_LIMIT: u64 = 500;
+
+#[derive(Debug, Serialize)]
+pub struct SessionResponse {
+    pub id: i32,
+    pub task_id: i32,
+    pub pid: Option<i32>,
+    pub started_at: String,
+    pub ended_at: Option<String>,
+    pub exit_code: Option<i32>,
+    /// Run time in milliseconds, or `None` while the session is still open
+    pub duration_ms: Option<i64>,
+}
+
+impl From<process_sessions::Model> for SessionResponse {
+    fn from(session: process_sessions::Model) -> Self {
+        Self {
+            id: session.id,
+            task_id: session.task_id,
+            pid: session.pid,
+            started_at: session.started_at.to_rfc3339(),
+            ended_at: session.ended_at.map(|t| t.to_rfc3339()),
+            exit_code: session.exit_code,
+            duration_ms: session
+                .ended_at
+                .map(|ended| (ended - session.started_at).num_milliseconds()),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SessionsQuery {
+    pub limit: Option<u64>,
+    #[serde(default)]
+    pub offset: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionsPage {
+    pub sessions: Vec<SessionResponse>,
+    pub total: u64,
+}
+
+/// List sessions across all tasks, newest first
+#[debug_handler]
+async fn list(State(ctx): State<AppContext>, Query(query): Query<SessionsQuery>) -> Result<Response> {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_SESSION_LIMIT)
+        .clamp(1, MAX_SESSION_LIMIT);
+
+    let total = process_sessions::Entity::find().count(&ctx.db).await?;
+
+    let sessions = process_sessions::Entity::find()
+        .order_by_desc(process_sessions::Column::StartedAt)
+        .order_by_desc(process_sessions::Column::Id)
+        .offset(query.offset)
+        .limit(limit)
+        .all(&ctx.db)
+        .await?
+        .into_iter()
+        .map(SessionResponse::from)
+        .collect();
+
+    format::json(SessionsPage { sessions, total })
+}
+
+pub fn routes() -> Routes {
+    Routes::new().prefix("/api/sessions").add("/", get(list))
+}
@@ -1,16 +1,23 @@
 //! Settings controller for managing application configuration
 
 use crate::models::_entities::settings;
+use crate::services::process_manager::PROCESS_MANAGER;
 use loco_rs::prelude::*;
-use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, TransactionTrait};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 #[derive(Debug, Serialize)]
 pub struct SettingsResponse {
     pub settings: HashMap<String, String>,
 }
 
+#[derive(Debug, Serialize)]
+pub struct UpdateSettingsResponse {
+    pub settings: HashMap<String, String>,
+    pub previous: HashMap<String, Option<String>>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct UpdateSettingsRequest {
     pub settings: HashMap<String, String>,
@@ -46,25 +53,37 @@ async fn get_one(State(ctx): State<AppContext>, Path(key): Path<String>) -> Resu
 }
 
 /// Update multiple settings at once
+///
+/// Keys are applied in sorted order inside a single transaction, so either
+/// every setting is updated or none is. The response includes the previous
+/// value of each changed key (`null` for keys that did not exist).
 #[debug_handler]
 async fn update_all(
     State(ctx): State<AppContext>,
     Json(params): Json<UpdateSettingsRequest>,
 ) -> Result<Response> {
-    for (key, value) in params.settings {
+    let updates: BTreeMap<String, String> = params.settings.into_iter().collect();
+    let mut previous: HashMap<String, Option<String>> = HashMap::new();
+
+    let txn = ctx.db.begin().await?;
+
+    for (key, value) in updates {
         let existing = settings::Entity::find()
             .filter(settings::Column::Key.eq(&key))
-            .one(&ctx.db)
+            .one(&txn)
             .await?;
 
         match existing {
+            Some(setting) if setting.value == value => {}
             Some(setting) => {
+                previous.insert(key, Some(setting.value.clone()));
                 let mut active: settings::ActiveModel = setting.into();
                 active.value = sea_orm::ActiveValue::Set(value);
                 active.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
-                active.update(&ctx.db).await?;
+                active.update(&txn).await?;
             }
             None => {
+                previous.insert(key.clone(), None);
                 let new_setting = settings::ActiveModel {
                     key: sea_orm::ActiveValue::Set(key),
                     value: sea_orm::ActiveValue::Set(value),
@@ -72,13 +91,35 @@ async fn update_all(
                     updated_at: sea_orm::ActiveValue::Set(chrono::Utc::now().into()),
                     ..Default::default()
                 };
-                new_setting.insert(&ctx.db).await?;
+                new_setting.insert(&txn).await?;
             }
         }
     }
 
-    // Return updated settings
-    get_all(State(ctx)).await
+    txn.commit().await?;
+
+    settings::Entity::notify_changed(previous.keys().cloned().collect());
+
+    // Return updated settings along with the previous values
+    let all_settings = settings::Entity::find().all(&ctx.db).await?;
+
+    format::json(UpdateSettingsResponse {
+        settings: all_settings.into_iter().map(|s| (s.key, s.value)).collect(),
+        previous,
+    })
+}
+
+/// Clear the cached agent binary paths and re-resolve them
+#[debug_handler]
+async fn recheck_agents() -> Result<Response> {
+    PROCESS_MANAGER.clear_agent_cache();
+
+    let mut agents = HashMap::new();
+    for agent in ["claude", "codex", "gemini"] {
+        agents.insert(agent, PROCESS_MANAGER.resolve_agent(agent).await);
+    }
+
+    format::json(serde_json::json!({ "success": true, "agents": agents }))
 }
 
 pub fn routes() -> Routes {
@@ -86,5 +127,6 @@ pub fn routes() -> Routes {
         .prefix("/api/settings")
         .add("/", get(get_all))
         .add("/", put(update_all))
+        .add("/recheck-agents", post(recheck_agents))
         .add("/{key}", get(get_one))
 }
@@ -1,7 +1,10 @@
 //! Setup controller for first-time configuration
 
 use crate::models::_entities::settings;
-use crate::services::clickup::ClickUpClient;
+use crate::services::clickup::{self, ClickUpApi, ClickUpClient};
+use crate::services::orchestrator;
+use crate::services::process_manager::PROCESS_MANAGER;
+use crate::services::redaction;
 use loco_rs::prelude::*;
 use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
 use serde::{Deserialize, Serialize};
@@ -29,6 +32,37 @@ pub struct SaveApiKeyResponse {
     pub error: Option<String>,
 }
 
+#[derive(Debug, Serialize)]
+pub struct PreflightCheck {
+    pub name: String,
+    pub passed: bool,
+    pub message: String,
+}
+
+impl PreflightCheck {
+    fn pass(name: &str, message: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            passed: true,
+            message: message.into(),
+        }
+    }
+
+    fn fail(name: &str, message: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            passed: false,
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PreflightResponse {
+    pub passed: bool,
+    pub checks: Vec<PreflightCheck>,
+}
+
 /// Get setup status
 #[debug_handler]
 async fn get_status(State(ctx): State<AppContext>) -> Result<Response> {
@@ -80,7 +114,10 @@ async fn get_status(State(ctx): State<AppContext>) -> Result<Response> {
 
 /// Save API key to .env file
 #[debug_handler]
-async fn save_api_key(Json(params): Json<SaveApiKeyRequest>) -> Result<Response> {
+async fn save_api_key(
+    State(ctx): State<AppContext>,
+    Json(params): Json<SaveApiKeyRequest>,
+) -> Result<Response> {
     let api_key = params.api_key.trim();
 
     if api_key.is_empty() {
@@ -155,6 +192,9 @@ async fn save_api_key(Json(params): Json<SaveApiKeyRequest>) -> Result<Response>
     // Also set the environment variable for this process
     std::env::set_var("CLICKUP_API_KEY", api_key);
 
+    // Make sure the new key is scrubbed from agent output
+    redaction::reload(&ctx.db).await;
+
     format::json(SaveApiKeyResponse {
         success: true,
         valid: true,
@@ -191,10 +231,120 @@ async fn complete_setup(State(ctx): State<AppContext>) -> Result<Response> {
     }))
 }
 
+/// Run every check the poller relies on and report each result
+#[debug_handler]
+async fn preflight(State(ctx): State<AppContext>) -> Result<Response> {
+    let mut checks = Vec::new();
+
+    let repo_path = settings::Entity::get_value(&ctx.db, "target_repo_path").await;
+    let dev_branch = orchestrator::dev_branch(&ctx.db).await;
+
+    // Repository
+    let repo_ok = match &repo_path {
+        None => {
+            checks.push(PreflightCheck::fail("repo", "Target repo path is not configured"));
+            false
+        }
+        Some(path) => {
+            let output = tokio::process::Command::new("git")
+                .args(["-C", path, "rev-parse", "--git-dir"])
+                .output()
+                .await;
+            match output {
+                Ok(o) if o.status.success() => {
+                    checks.push(PreflightCheck::pass("repo", format!("{} is a git repository", path)));
+                    true
+                }
+                Ok(_) => {
+                    checks.push(PreflightCheck::fail("repo", format!("{} is not a git repository", path)));
+                    false
+                }
+                Err(e) => {
+                    checks.push(PreflightCheck::fail("repo", format!("Failed to run git: {}", e)));
+                    false
+                }
+            }
+        }
+    };
+
+    // Base branch and worktrees directory only make sense for a valid repo
+    if let (true, Some(path)) = (repo_ok, &repo_path) {
+        let branch_exists = tokio::process::Command::new("git")
+            .args(["-C", path, "rev-parse", "--verify", "--quiet", &dev_branch])
+            .output()
+            .await
+            .map(|o| o.status.success())
+            .unwrap_or(false);
+
+        checks.push(if branch_exists {
+            PreflightCheck::pass("dev_branch", format!("Branch '{}' exists", dev_branch))
+        } else {
+            PreflightCheck::fail("dev_branch", format!("Branch '{}' does not exist", dev_branch))
+        });
+
+        let worktrees_dir = orchestrator::configured_worktrees_dir(&ctx.db, path).await;
+        let probe = format!("{}/.preflight", worktrees_dir);
+        let writable = match tokio::fs::create_dir_all(&worktrees_dir).await {
+            Ok(()) => tokio::fs::write(&probe, b"").await,
+            Err(e) => Err(e),
+        };
+        let _ = tokio::fs::remove_file(&probe).await;
+
+        checks.push(match writable {
+            Ok(()) => PreflightCheck::pass("worktrees_dir", format!("{} is writable", worktrees_dir)),
+            Err(e) => PreflightCheck::fail(
+                "worktrees_dir",
+                format!("{} is not writable: {}", worktrees_dir, e),
+            ),
+        });
+    } else {
+        checks.push(PreflightCheck::fail("dev_branch", "Skipped: repository is not valid"));
+        checks.push(PreflightCheck::fail("worktrees_dir", "Skipped: repository is not valid"));
+    }
+
+    // Agent binary
+    checks.push(match PROCESS_MANAGER.resolve_agent("claude").await {
+        Some(path) => PreflightCheck::pass("agent", format!("claude found at {}", path)),
+        None => PreflightCheck::fail("agent", "The 'claude' command is not found in PATH"),
+    });
+
+    // ClickUp list
+    let list = match settings::Entity::get_value(&ctx.db, "clickup_list_id").await {
+        None => Err("No ClickUp list selected".to_string()),
+        Some(list_id) => match clickup::client_from_env() {
+            Err(e) => Err(e.to_string()),
+            Ok(client) => client.get_list(&list_id).await.map_err(|e| e.to_string()),
+        },
+    };
+    checks.push(match &list {
+        Ok(list) => PreflightCheck::pass("clickup_list", format!("List '{}' is reachable", list.name)),
+        Err(e) => PreflightCheck::fail("clickup_list", e.clone()),
+    });
+
+    // Trigger and target statuses must exist on the list
+    for (key, default) in [("trigger_status", "Ready for Dev"), ("target_status", "In Development")] {
+        let name = settings::Entity::get_value(&ctx.db, key)
+            .await
+            .unwrap_or_else(|| default.to_string());
+        checks.push(match &list {
+            Ok(list) => match clickup::resolve_status(&list.statuses, &name) {
+                Ok(status) => PreflightCheck::pass(key, format!("Status '{}' exists", status)),
+                Err(e) => PreflightCheck::fail(key, e.to_string()),
+            },
+            Err(_) => PreflightCheck::fail(key, "Skipped: ClickUp list is not reachable"),
+        });
+    }
+
+    let passed = checks.iter().all(|c| c.passed);
+
+    format::json(PreflightResponse { passed, checks })
+}
+
 pub fn routes() -> Routes {
     Routes::new()
         .prefix("/api/setup")
         .add("/status", get(get_status))
         .add("/api-key", post(save_api_key))
         .add("/complete", post(complete_setup))
+        .add("/preflight", post(preflight))
 }
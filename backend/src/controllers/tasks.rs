@@ -1,15 +1,26 @@
 //! Tasks controller for managing orchestrator tasks
 
-use crate::models::_entities::{orchestrator_tasks, process_sessions, settings};
-use crate::services::process_manager::PROCESS_MANAGER;
+use crate::controllers::sessions::SessionResponse;
+use crate::models::_entities::{orchestrator_task_logs, orchestrator_tasks, process_sessions, settings};
+use crate::services::clickup;
+use crate::services::git;
+use crate::services::orchestrator::{self, TaskEventKind};
+use crate::services::process_manager::{ProcessError, AGENT_TYPES, PROCESS_MANAGER};
+use loco_rs::controller::ErrorDetail;
+use axum::http::{header, StatusCode};
+use axum::response::IntoResponse;
 use loco_rs::prelude::*;
-use sea_orm::{ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder, Set};
+use sea_orm::{
+    ColumnTrait, Condition, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, Set,
+    TransactionTrait,
+};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize)]
 pub struct TaskResponse {
     pub id: i32,
     pub clickup_task_id: String,
+    pub clickup_url: Option<String>,
     pub name: String,
     pub description: Option<String>,
     pub priority: Option<i32>,
@@ -19,6 +30,20 @@ pub struct TaskResponse {
     pub started_at: Option<String>,
     pub completed_at: Option<String>,
     pub is_running: bool,
+    pub input_tokens: Option<i32>,
+    pub output_tokens: Option<i32>,
+    pub estimated_cost: Option<f64>,
+    pub tags: Vec<String>,
+    pub depends_on_clickup_id: Option<String>,
+    pub queue_position: Option<i32>,
+    pub working_subdir: Option<String>,
+    pub note: Option<String>,
+    pub agent_type: Option<String>,
+    /// Percent complete as last reported by the agent, see `progress_marker`
+    pub progress: Option<i32>,
+    pub env_file: Option<String>,
+    /// Automatic restarts after failure, see `auto_restart_failed`
+    pub restart_count: i32,
 }
 
 impl From<orchestrator_tasks::Model> for TaskResponse {
@@ -26,6 +51,7 @@ impl From<orchestrator_tasks::Model> for TaskResponse {
         let is_running = PROCESS_MANAGER.is_running(task.id);
         Self {
             id: task.id,
+            clickup_url: clickup::task_url(&task.clickup_task_id),
             clickup_task_id: task.clickup_task_id,
             name: task.name,
             description: task.description,
@@ -36,33 +62,127 @@ impl From<orchestrator_tasks::Model> for TaskResponse {
             started_at: task.started_at.map(|t| t.to_rfc3339()),
             completed_at: task.completed_at.map(|t| t.to_rfc3339()),
             is_running,
+            input_tokens: task.input_tokens,
+            output_tokens: task.output_tokens,
+            estimated_cost: task.estimated_cost,
+            tags: split_tags(task.tags.as_deref()),
+            depends_on_clickup_id: task.depends_on_clickup_id,
+            queue_position: task.queue_position,
+            working_subdir: task.working_subdir,
+            note: task.note,
+            agent_type: task.agent_type,
+            progress: task.progress,
+            env_file: task.env_file,
+            restart_count: task.restart_count,
         }
     }
 }
 
-#[derive(Debug, Deserialize)]
+/// Split a comma-joined tags column into tag names
+fn split_tags(tags: Option<&str>) -> Vec<String> {
+    tags.map(|t| {
+        t.split(',')
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .map(String::from)
+            .collect()
+    })
+    .unwrap_or_default()
+}
+
+#[derive(Debug, Deserialize, Serialize)]
 pub struct ListQuery {
     pub status: Option<String>,
+    pub tag: Option<String>,
+    /// Exact priority; ClickUp numbers them 1 (urgent) to 4 (low)
+    pub priority: Option<i32>,
+    pub priority_min: Option<i32>,
+    pub priority_max: Option<i32>,
+    /// RFC 3339 timestamp or `YYYY-MM-DD` (midnight UTC), inclusive
+    pub created_after: Option<String>,
+    /// RFC 3339 timestamp or `YYYY-MM-DD` (midnight UTC), exclusive
+    pub created_before: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TaskListResponse {
+    pub tasks: Vec<TaskResponse>,
+    /// The filters that were applied, as given in the query
+    pub filters: ListQuery,
+}
+
+/// Parse a date query parameter given as RFC 3339 or as a plain `YYYY-MM-DD` date
+fn parse_date_param(name: &str, value: &str) -> Result<chrono::DateTime<chrono::Utc>> {
+    if let Ok(timestamp) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Ok(timestamp.with_timezone(&chrono::Utc));
+    }
+    chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map(|date| date.and_time(chrono::NaiveTime::MIN).and_utc())
+        .map_err(|_| {
+            Error::BadRequest(format!(
+                "Invalid {}: expected an RFC 3339 timestamp or YYYY-MM-DD, got '{}'",
+                name, value
+            ))
+        })
 }
 
 /// List all tasks
+///
+/// Filters by status, tag, priority (exact or a `priority_min`..`priority_max`
+/// range) and creation time; all given filters must match.
 #[debug_handler]
 async fn list(State(ctx): State<AppContext>, Query(query): Query<ListQuery>) -> Result<Response> {
-    let mut find = orchestrator_tasks::Entity::find()
-        .order_by_desc(orchestrator_tasks::Column::CreatedAt);
+    let mut condition = Condition::all();
 
     if let Some(status) = &query.status {
-        find = find.filter(orchestrator_tasks::Column::Status.eq(status));
+        condition = condition.add(orchestrator_tasks::Column::Status.eq(status));
+    }
+
+    if let Some(tag) = &query.tag {
+        condition = condition.add(orchestrator_tasks::Column::Tags.contains(tag));
     }
 
+    if let Some(priority) = query.priority {
+        condition = condition.add(orchestrator_tasks::Column::Priority.eq(priority));
+    }
+    if let Some(min) = query.priority_min {
+        condition = condition.add(orchestrator_tasks::Column::Priority.gte(min));
+    }
+    if let Some(max) = query.priority_max {
+        condition = condition.add(orchestrator_tasks::Column::Priority.lte(max));
+    }
+
+    if let Some(after) = query.created_after.as_deref().filter(|s| !s.is_empty()) {
+        let after = parse_date_param("created_after", after)?;
+        condition = condition.add(orchestrator_tasks::Column::CreatedAt.gte(after));
+    }
+    if let Some(before) = query.created_before.as_deref().filter(|s| !s.is_empty()) {
+        let before = parse_date_param("created_before", before)?;
+        condition = condition.add(orchestrator_tasks::Column::CreatedAt.lt(before));
+    }
+
+    let find = orchestrator_tasks::Entity::find()
+        .filter(condition)
+        .order_by_desc(orchestrator_tasks::Column::CreatedAt);
+
+    // The LIKE filter above is a coarse match, keep only exact tag names
     let tasks: Vec<TaskResponse> = find
         .all(&ctx.db)
         .await?
         .into_iter()
         .map(TaskResponse::from)
+        .filter(|t| {
+            query
+                .tag
+                .as_ref()
+                .is_none_or(|tag| t.tags.iter().any(|name| name.eq_ignore_ascii_case(tag)))
+        })
         .collect();
 
-    format::json(tasks)
+    format::json(TaskListResponse {
+        tasks,
+        filters: query,
+    })
 }
 
 /// Get a single task by ID
@@ -98,6 +218,7 @@ async fn stop(State(ctx): State<AppContext>, Path(id): Path<i32>) -> Result<Resp
     active.status = Set("stopped".to_string());
     active.updated_at = Set(chrono::Utc::now().into());
     let updated = active.update(&ctx.db).await?;
+    orchestrator::publish_task_event(TaskEventKind::StatusChanged, id, &updated.status);
 
     // Update process session
     let _ = process_sessions::Entity::update_many()
@@ -113,23 +234,319 @@ async fn stop(State(ctx): State<AppContext>, Path(id): Path<i32>) -> Result<Resp
     format::json(TaskResponse::from(updated))
 }
 
-/// Restart a stopped task
+/// How long kill-all waits for task processes to exit
+const KILL_ALL_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+#[derive(Debug, Serialize)]
+pub struct KillAllResponse {
+    pub task_ids: Vec<i32>,
+    pub session_ids: Vec<String>,
+}
+
+/// Kill every running agent and UI session process
+///
+/// Goes by the process manager rather than the database, so processes are
+/// found even when task statuses have drifted. Their tasks are marked stopped.
 #[debug_handler]
-async fn restart(State(ctx): State<AppContext>, Path(id): Path<i32>) -> Result<Response> {
+async fn kill_all(State(ctx): State<AppContext>) -> Result<Response> {
+    let task_ids = orchestrator::stop_all_agents(&ctx.db, KILL_ALL_TIMEOUT).await;
+    let session_ids = PROCESS_MANAGER.kill_all_sessions().await;
+
+    for task_id in &task_ids {
+        orchestrator_task_logs::Entity::log_task_event(
+            &ctx.db,
+            *task_id,
+            "system",
+            "Process killed by kill-all",
+        )
+        .await;
+    }
+
+    tracing::warn!(
+        "Kill-all stopped tasks {:?} and sessions {:?}",
+        task_ids,
+        session_ids
+    );
+
+    format::json(KillAllResponse {
+        task_ids,
+        session_ids,
+    })
+}
+
+/// Default and maximum number of log rows returned per request
+const DEFAULT_LOG_LIMIT: u64 = 200;
+const MAX_LOG_LIMIT: u64 = 1000;
+
+/// Rows returned by the log tail when `lines` is not given
+const DEFAULT_TAIL_LINES: u64 = 50;
+
+#[derive(Debug, Deserialize)]
+pub struct LogsQuery {
+    /// Only return rows with an id greater than this cursor
+    pub after_id: Option<i32>,
+    pub limit: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LogEntry {
+    pub id: i32,
+    pub event_type: String,
+    pub message: String,
+    pub created_at: String,
+}
+
+impl LogEntry {
+    /// Build a log entry with `created_at` shown in `timezone`
+    fn new(log: orchestrator_task_logs::Model, timezone: chrono_tz::Tz) -> Self {
+        Self {
+            id: log.id,
+            event_type: log.event_type,
+            message: log.message,
+            created_at: log.created_at.with_timezone(&timezone).to_rfc3339(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct LogsResponse {
+    pub logs: Vec<LogEntry>,
+    /// Cursor to pass as `after_id` to fetch newer rows
+    pub last_id: Option<i32>,
+}
+
+/// Get a task's event log, oldest first
+///
+/// Pass the returned `last_id` as `after_id` to poll for new rows only.
+/// Timestamps are RFC 3339 in the `display_timezone` setting's zone.
+#[debug_handler]
+async fn get_logs(
+    State(ctx): State<AppContext>,
+    Path(id): Path<i32>,
+    Query(query): Query<LogsQuery>,
+) -> Result<Response> {
+    orchestrator_tasks::Entity::find_by_id(id)
+        .one(&ctx.db)
+        .await?
+        .ok_or(Error::NotFound)?;
+
+    let limit = query.limit.unwrap_or(DEFAULT_LOG_LIMIT).clamp(1, MAX_LOG_LIMIT);
+
+    let mut find = orchestrator_task_logs::Entity::find()
+        .filter(orchestrator_task_logs::Column::TaskId.eq(id))
+        .order_by_asc(orchestrator_task_logs::Column::Id)
+        .limit(limit);
+
+    if let Some(after_id) = query.after_id {
+        find = find.filter(orchestrator_task_logs::Column::Id.gt(after_id));
+    }
+
+    let timezone = orchestrator::display_timezone(&ctx.db).await;
+    let logs: Vec<LogEntry> = find
+        .all(&ctx.db)
+        .await?
+        .into_iter()
+        .map(|log| LogEntry::new(log, timezone))
+        .collect();
+
+    // Keep the caller's cursor when nothing new arrived
+    let last_id = logs.last().map(|l| l.id).or(query.after_id);
+
+    format::json(LogsResponse { logs, last_id })
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TailQuery {
+    /// Number of rows to return, capped at 1000
+    pub lines: Option<u64>,
+}
+
+/// Get the last rows of a task's event log, oldest first
+///
+/// A cheap way to see how a task ended. `last_id` can be passed to the full
+/// logs endpoint as `after_id` to follow along from there.
+#[debug_handler]
+async fn tail_logs(
+    State(ctx): State<AppContext>,
+    Path(id): Path<i32>,
+    Query(query): Query<TailQuery>,
+) -> Result<Response> {
+    orchestrator_tasks::Entity::find_by_id(id)
+        .one(&ctx.db)
+        .await?
+        .ok_or(Error::NotFound)?;
+
+    let lines = query.lines.unwrap_or(DEFAULT_TAIL_LINES).clamp(1, MAX_LOG_LIMIT);
+
+    let mut rows = orchestrator_task_logs::Entity::find()
+        .filter(orchestrator_task_logs::Column::TaskId.eq(id))
+        .order_by_desc(orchestrator_task_logs::Column::Id)
+        .limit(lines)
+        .all(&ctx.db)
+        .await?;
+    rows.reverse();
+
+    let timezone = orchestrator::display_timezone(&ctx.db).await;
+    let logs: Vec<LogEntry> = rows
+        .into_iter()
+        .map(|log| LogEntry::new(log, timezone))
+        .collect();
+    let last_id = logs.last().map(|l| l.id);
+
+    format::json(LogsResponse { logs, last_id })
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NoteRequest {
+    /// The new note, an empty or missing note clears it
+    pub note: Option<String>,
+}
+
+/// Set the human note on a task
+#[debug_handler]
+async fn update_note(
+    State(ctx): State<AppContext>,
+    Path(id): Path<i32>,
+    Json(params): Json<NoteRequest>,
+) -> Result<Response> {
     let task = orchestrator_tasks::Entity::find_by_id(id)
         .one(&ctx.db)
         .await?
         .ok_or(Error::NotFound)?;
 
-    if task.status != "stopped" && task.status != "failed" {
-        return Err(Error::BadRequest(
-            "Task must be stopped or failed to restart".to_string(),
-        ));
+    let note = params.note.filter(|n| !n.trim().is_empty());
+
+    let mut active: orchestrator_tasks::ActiveModel = task.into();
+    active.note = Set(note);
+    active.updated_at = Set(chrono::Utc::now().into());
+    let updated = active.update(&ctx.db).await?;
+
+    format::json(TaskResponse::from(updated))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DescriptionRequest {
+    /// The new description, an empty or missing description clears it
+    pub description: Option<String>,
+}
+
+/// Replace the description of a queued task before its agent starts
+///
+/// Only queued tasks can be edited, since the description is baked into the
+/// prompt when the agent is spawned.
+#[debug_handler]
+async fn update_description(
+    State(ctx): State<AppContext>,
+    Path(id): Path<i32>,
+    Json(params): Json<DescriptionRequest>,
+) -> Result<Response> {
+    let task = orchestrator_tasks::Entity::find_by_id(id)
+        .one(&ctx.db)
+        .await?
+        .ok_or(Error::NotFound)?;
+
+    if task.status != "queued" {
+        return Err(Error::BadRequest(format!(
+            "Only queued tasks can be edited, task is {}",
+            task.status
+        )));
     }
 
-    let worktree_path = task.worktree_path.clone().ok_or(Error::BadRequest(
+    let description = params.description.filter(|d| !d.trim().is_empty());
+    let length = description.as_deref().map_or(0, |d| d.chars().count());
+
+    let mut active: orchestrator_tasks::ActiveModel = task.into();
+    active.description = Set(description);
+    active.updated_at = Set(chrono::Utc::now().into());
+    let updated = active.update(&ctx.db).await?;
+    orchestrator::publish_task_event(TaskEventKind::StatusChanged, id, &updated.status);
+
+    orchestrator_task_logs::Entity::log_task_event(
+        &ctx.db,
+        id,
+        "system",
+        &format!("Description edited before start ({} characters)", length),
+    )
+    .await;
+
+    format::json(TaskResponse::from(updated))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateTaskRequest {
+    /// Agent to run the task with; an empty string clears it back to the default
+    pub agent_type: Option<String>,
+    /// `.env`-style file relative to the worktree whose variables are passed
+    /// to the agent; an empty string clears it
+    pub env_file: Option<String>,
+}
+
+/// Update editable task fields, leaving fields missing from the body as they are
+#[debug_handler]
+async fn update(
+    State(ctx): State<AppContext>,
+    Path(id): Path<i32>,
+    Json(params): Json<UpdateTaskRequest>,
+) -> Result<Response> {
+    let task = orchestrator_tasks::Entity::find_by_id(id)
+        .one(&ctx.db)
+        .await?
+        .ok_or(Error::NotFound)?;
+
+    let mut active: orchestrator_tasks::ActiveModel = task.clone().into();
+
+    if let Some(agent_type) = params.agent_type {
+        let agent_type = Some(agent_type.trim().to_lowercase()).filter(|a| !a.is_empty());
+        if let Some(agent) = &agent_type {
+            if !AGENT_TYPES.contains(&agent.as_str()) {
+                return Err(Error::BadRequest(format!(
+                    "Unknown agent type '{}', expected one of: {}",
+                    agent,
+                    AGENT_TYPES.join(", ")
+                )));
+            }
+        }
+        // A conversation id from one agent can't be resumed by another
+        if agent_type != task.agent_type {
+            active.agent_session_id = Set(None);
+        }
+        active.agent_type = Set(agent_type);
+    }
+
+    if let Some(env_file) = params.env_file {
+        let env_file = Some(env_file.trim().to_string()).filter(|f| !f.is_empty());
+        if let Some(file) = &env_file {
+            if !orchestrator::is_contained_relative(std::path::Path::new(file)) {
+                return Err(Error::BadRequest(format!(
+                    "Env file must be a path relative to the worktree: {}",
+                    file
+                )));
+            }
+        }
+        active.env_file = Set(env_file);
+    }
+
+    active.updated_at = Set(chrono::Utc::now().into());
+    let updated = active.update(&ctx.db).await?;
+
+    format::json(TaskResponse::from(updated))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RerunRequest {
+    pub prompt_override: String,
+}
+
+/// Get the task's recorded worktree path
+fn task_worktree_path(task: &orchestrator_tasks::Model) -> Result<String> {
+    task.worktree_path.clone().ok_or(Error::BadRequest(
         "Task has no worktree path".to_string(),
-    ))?;
+    ))
+}
+
+/// Get the task's worktree path, failing when it no longer exists on disk
+fn existing_worktree(task: &orchestrator_tasks::Model) -> Result<String> {
+    let worktree_path = task_worktree_path(task)?;
 
     // Check if worktree exists, if not try to recreate it
     if !std::path::Path::new(&worktree_path).exists() {
@@ -140,67 +557,471 @@ async fn restart(State(ctx): State<AppContext>, Path(id): Path<i32>) -> Result<R
         )));
     }
 
-    // Get agent prompt from settings
-    let agent_prompt = settings::Entity::find()
-        .filter(settings::Column::Key.eq("agent_prompt"))
+    Ok(worktree_path)
+}
+
+/// Map a process manager error to an HTTP error matching its cause
+///
+/// A second process for the same task is a conflict, missing agents and
+/// directories are bad requests, failing to launch the process is a server error
+/// and hitting the session limit asks the client to retry later.
+pub(crate) fn process_error(e: ProcessError) -> Error {
+    let (status, kind) = match &e {
+        ProcessError::NotFound(_) => return Error::NotFound,
+        ProcessError::AlreadyRunning(_) | ProcessError::InputFull(_) => {
+            (StatusCode::CONFLICT, "conflict")
+        }
+        ProcessError::WorkingDirMissing(_)
+        | ProcessError::InvalidWorkingDir(_)
+        | ProcessError::UnknownAgent(_)
+        | ProcessError::AgentNotFound(_) => return Error::BadRequest(e.to_string()),
+        ProcessError::SpawnFailed(_) => (StatusCode::INTERNAL_SERVER_ERROR, "spawn_failed"),
+        ProcessError::Database(_) => (StatusCode::INTERNAL_SERVER_ERROR, "database"),
+        ProcessError::TooManySessions { .. } => {
+            (StatusCode::TOO_MANY_REQUESTS, "too_many_sessions")
+        }
+    };
+    Error::CustomError(status, ErrorDetail::new(kind.to_string(), e.to_string()))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReorderRequest {
+    /// Queued task ids in the order they should start
+    pub task_ids: Vec<i32>,
+}
+
+/// Manually set the start order of queued tasks
+///
+/// Listed tasks get consecutive queue positions and start before any
+/// unlisted queued task, which keep their priority order.
+#[debug_handler]
+async fn reorder(
+    State(ctx): State<AppContext>,
+    Json(params): Json<ReorderRequest>,
+) -> Result<Response> {
+    let unique: std::collections::HashSet<_> = params.task_ids.iter().collect();
+    if unique.len() != params.task_ids.len() {
+        return Err(Error::BadRequest("Task ids must be unique".to_string()));
+    }
+
+    let queued = orchestrator_tasks::Entity::find()
+        .filter(orchestrator_tasks::Column::Id.is_in(params.task_ids.clone()))
+        .filter(orchestrator_tasks::Column::Status.eq("queued"))
+        .count(&ctx.db)
+        .await?;
+    if queued as usize != params.task_ids.len() {
+        return Err(Error::BadRequest("Only queued tasks can be reordered".to_string()));
+    }
+
+    let txn = ctx.db.begin().await?;
+
+    // Clear previous manual positions so unlisted tasks fall back to priority order
+    orchestrator_tasks::Entity::update_many()
+        .filter(orchestrator_tasks::Column::Status.eq("queued"))
+        .col_expr(
+            orchestrator_tasks::Column::QueuePosition,
+            sea_orm::sea_query::Expr::value(Option::<i32>::None),
+        )
+        .exec(&txn)
+        .await?;
+
+    for (position, id) in params.task_ids.iter().enumerate() {
+        orchestrator_tasks::Entity::update_many()
+            .filter(orchestrator_tasks::Column::Id.eq(*id))
+            .col_expr(
+                orchestrator_tasks::Column::QueuePosition,
+                sea_orm::sea_query::Expr::value(position as i32),
+            )
+            .exec(&txn)
+            .await?;
+    }
+
+    txn.commit().await?;
+
+    let tasks: Vec<TaskResponse> = orchestrator::queued_tasks(&ctx.db)
+        .await?
+        .into_iter()
+        .map(TaskResponse::from)
+        .collect();
+
+    format::json(tasks)
+}
+
+/// Restart a stopped task
+#[debug_handler]
+async fn restart(State(ctx): State<AppContext>, Path(id): Path<i32>) -> Result<Response> {
+    let task = orchestrator_tasks::Entity::find_by_id(id)
         .one(&ctx.db)
-        .await
-        .ok()
-        .flatten()
-        .map(|s| s.value)
-        .filter(|v| !v.is_empty());
+        .await?
+        .ok_or(Error::NotFound)?;
+
+    if !matches!(task.status.as_str(), "stopped" | "failed" | "needs_review") {
+        return Err(Error::BadRequest(
+            "Task must be stopped, failed or need review to restart".to_string(),
+        ));
+    }
+
+    let worktree_path = existing_worktree(&task)?;
 
     // Build prompt from task description combined with agent prompt
-    let task_description = task
-        .description
-        .clone()
-        .unwrap_or_else(|| format!("Complete task: {}", task.name));
-
-    let prompt = match &agent_prompt {
-        Some(global_prompt) if !global_prompt.is_empty() => {
-            format!(
-                "## Task\n{}\n\n## Instructions\n{}",
-                task_description, global_prompt
-            )
-        }
-        _ => task_description,
+    let prompt = orchestrator::compose_task_prompt(&ctx.db, &task).await;
+
+    // Spawn new process; a manual restart gives automatic restarts a fresh budget
+    let updated = orchestrator::spawn_in_worktree(&ctx.db, task, &prompt, &worktree_path, 0)
+        .await
+        .map_err(process_error)?;
+
+    format::json(TaskResponse::from(updated))
+}
+
+/// Re-run a task in its existing worktree with a different prompt
+///
+/// The stored description is left untouched; the override is only used for this run.
+#[debug_handler]
+async fn rerun(
+    State(ctx): State<AppContext>,
+    Path(id): Path<i32>,
+    Json(params): Json<RerunRequest>,
+) -> Result<Response> {
+    let task = orchestrator_tasks::Entity::find_by_id(id)
+        .one(&ctx.db)
+        .await?
+        .ok_or(Error::NotFound)?;
+
+    if task.status == "in_progress" || PROCESS_MANAGER.is_running(id) {
+        return Err(Error::BadRequest("Task is already running".to_string()));
+    }
+
+    let prompt = params.prompt_override.trim();
+    if prompt.is_empty() {
+        return Err(Error::BadRequest("prompt_override cannot be empty".to_string()));
+    }
+
+    let worktree_path = existing_worktree(&task)?;
+
+    let updated = orchestrator::spawn_in_worktree(&ctx.db, task, prompt, &worktree_path, 0)
+        .await
+        .map_err(process_error)?;
+
+    format::json(TaskResponse::from(updated))
+}
+
+/// Preview the prompt a run of this task would send to the agent
+#[debug_handler]
+async fn prompt(State(ctx): State<AppContext>, Path(id): Path<i32>) -> Result<Response> {
+    let task = orchestrator_tasks::Entity::find_by_id(id)
+        .one(&ctx.db)
+        .await?
+        .ok_or(Error::NotFound)?;
+
+    format::text(&orchestrator::compose_task_prompt(&ctx.db, &task).await)
+}
+
+/// List a task's process sessions, oldest first
+#[debug_handler]
+async fn list_sessions(State(ctx): State<AppContext>, Path(id): Path<i32>) -> Result<Response> {
+    orchestrator_tasks::Entity::find_by_id(id)
+        .one(&ctx.db)
+        .await?
+        .ok_or(Error::NotFound)?;
+
+    let sessions: Vec<SessionResponse> = process_sessions::Entity::find()
+        .filter(process_sessions::Column::TaskId.eq(id))
+        .order_by_asc(process_sessions::Column::StartedAt)
+        .all(&ctx.db)
+        .await?
+        .into_iter()
+        .map(SessionResponse::from)
+        .collect();
+
+    format::json(sessions)
+}
+
+/// Export the task's changes as a patch file
+///
+/// Diffs the worktree against its merge-base with the task's base branch, so
+/// both committed and uncommitted changes to tracked files are included.
+#[debug_handler]
+async fn export_patch(State(ctx): State<AppContext>, Path(id): Path<i32>) -> Result<Response> {
+    let task = orchestrator_tasks::Entity::find_by_id(id)
+        .one(&ctx.db)
+        .await?
+        .ok_or(Error::NotFound)?;
+
+    let worktree_path = task_worktree_path(&task)?;
+    if !std::path::Path::new(&worktree_path).exists() {
+        return format::render().status(StatusCode::GONE).json(serde_json::json!({
+            "error": format!("Worktree no longer exists: {}", worktree_path)
+        }));
+    }
+
+    let base_branch = match task.base_branch.clone() {
+        Some(branch) => branch,
+        None => orchestrator::dev_branch(&ctx.db).await,
     };
+    if !orchestrator::is_valid_branch_name(&base_branch) {
+        return Err(Error::BadRequest(format!(
+            "Invalid base branch name: '{}'",
+            base_branch
+        )));
+    }
 
-    // Spawn new process
-    match PROCESS_MANAGER
-        .spawn_agent(id, &prompt, &worktree_path)
+    let merge_base = tokio::process::Command::new("git")
+        .args(["-C", &worktree_path, "merge-base", &base_branch, "HEAD"])
+        .output()
         .await
-    {
-        Ok(pid) => {
-            tracing::info!("Restarted task {} with PID {}", id, pid);
-
-            // Update task status
-            let mut active: orchestrator_tasks::ActiveModel = task.into();
-            active.status = Set("in_progress".to_string());
-            active.started_at = Set(Some(chrono::Utc::now().into()));
-            active.updated_at = Set(chrono::Utc::now().into());
-            let updated = active.update(&ctx.db).await?;
-
-            // Create new process session
-            let session = process_sessions::ActiveModel {
-                task_id: Set(id),
-                pid: Set(Some(pid as i32)),
-                started_at: Set(chrono::Utc::now().into()),
-                ended_at: Set(None),
-                exit_code: Set(None),
-                created_at: Set(chrono::Utc::now().into()),
-                updated_at: Set(chrono::Utc::now().into()),
-                ..Default::default()
-            };
-            let _ = process_sessions::Entity::insert(session).exec(&ctx.db).await;
-
-            format::json(TaskResponse::from(updated))
+        .map_err(|e| Error::string(&format!("Failed to run git: {}", e)))?;
+    if !merge_base.status.success() {
+        return Err(Error::BadRequest(format!(
+            "Could not find merge-base with '{}': {}",
+            base_branch,
+            String::from_utf8_lossy(&merge_base.stderr).trim()
+        )));
+    }
+    let merge_base = String::from_utf8_lossy(&merge_base.stdout).trim().to_string();
+
+    let diff = tokio::process::Command::new("git")
+        .args(["-C", &worktree_path, "diff", "--binary", &merge_base])
+        .output()
+        .await
+        .map_err(|e| Error::string(&format!("Failed to run git: {}", e)))?;
+    if !diff.status.success() {
+        return Err(Error::string(&format!(
+            "git diff failed: {}",
+            String::from_utf8_lossy(&diff.stderr).trim()
+        )));
+    }
+
+    let body = if diff.stdout.is_empty() {
+        format!("# No changes in task {} relative to {}\n", id, base_branch).into_bytes()
+    } else {
+        diff.stdout
+    };
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/x-patch".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"task-{}.patch\"", id),
+            ),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+/// Remove the task's worktree, ignoring failures
+async fn remove_worktree(ctx: &AppContext, task: &orchestrator_tasks::Model) {
+    if let Some(worktree_path) = &task.worktree_path {
+        if std::path::Path::new(worktree_path).exists() {
+            // Run from the repo so worktrees outside of it are found too
+            let repo_path = settings::Entity::get_value(&ctx.db, "target_repo_path")
+                .await
+                .unwrap_or_else(|| worktree_path.clone());
+
+            // Try to remove the worktree
+            let _ = git::remove_worktree(&repo_path, worktree_path).await;
         }
-        Err(e) => {
-            tracing::error!("Failed to restart task {}: {}", id, e);
-            Err(Error::BadRequest(format!("Failed to restart: {}", e)))
+    }
+}
+
+/// Abandon a task: stop it, remove its worktree and hand the card back in ClickUp
+///
+/// The local record is kept with status `abandoned`, and the ClickUp card is
+/// moved back to the trigger status so it can be re-triaged.
+#[debug_handler]
+async fn abandon(State(ctx): State<AppContext>, Path(id): Path<i32>) -> Result<Response> {
+    let task = orchestrator_tasks::Entity::find_by_id(id)
+        .one(&ctx.db)
+        .await?
+        .ok_or(Error::NotFound)?;
+
+    if task.status == "abandoned" {
+        return Err(Error::BadRequest("Task is already abandoned".to_string()));
+    }
+
+    let previous_status = task.status.clone();
+
+    // Update the status first so the exit handler doesn't mark the task failed
+    let mut active: orchestrator_tasks::ActiveModel = task.clone().into();
+    active.status = Set("abandoned".to_string());
+    active.updated_at = Set(chrono::Utc::now().into());
+    let updated = active.update(&ctx.db).await?;
+    orchestrator::publish_task_event(TaskEventKind::StatusChanged, id, &updated.status);
+
+    if PROCESS_MANAGER.is_running(id) {
+        if let Err(e) = PROCESS_MANAGER.kill_process(id).await {
+            tracing::warn!("Failed to kill process for task {}: {}", id, e);
         }
     }
+
+    // Close any open process session
+    let _ = process_sessions::Entity::update_many()
+        .filter(process_sessions::Column::TaskId.eq(id))
+        .filter(process_sessions::Column::EndedAt.is_null())
+        .col_expr(
+            process_sessions::Column::EndedAt,
+            sea_orm::sea_query::Expr::value(chrono::Utc::now()),
+        )
+        .exec(&ctx.db)
+        .await;
+
+    remove_worktree(&ctx, &task).await;
+
+    // Move the card back so a human can re-triage it
+    let trigger_status = settings::Entity::get_value(&ctx.db, "trigger_status")
+        .await
+        .unwrap_or_else(|| "Ready for Dev".to_string());
+
+    match clickup::client_from_env() {
+        Ok(client) => {
+            match client
+                .update_task_status(&task.clickup_task_id, &trigger_status)
+                .await
+            {
+                Ok(_) => tracing::info!(
+                    "Reverted ClickUp task {} to '{}'",
+                    task.clickup_task_id,
+                    trigger_status
+                ),
+                Err(e) => tracing::error!(
+                    "Failed to revert ClickUp task {} to '{}': {}",
+                    task.clickup_task_id,
+                    trigger_status,
+                    e
+                ),
+            }
+        }
+        Err(e) => tracing::error!("Failed to create ClickUp client: {}", e),
+    }
+
+    tracing::info!("Abandoned task {} ({}), was {}", id, task.name, previous_status);
+    orchestrator_task_logs::Entity::log_task_event(
+        &ctx.db,
+        id,
+        "system",
+        &format!("Task abandoned (was {})", previous_status),
+    )
+    .await;
+
+    format::json(TaskResponse::from(updated))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RebaseWorktreeRequest {
+    /// Branch to base the new worktree on, defaults to the task's base branch
+    pub base_branch: Option<String>,
+}
+
+/// Give a task a fresh worktree and branch off `base_branch`
+///
+/// A repair for corrupted or lost worktrees: the old worktree is removed if
+/// present, but its branch is kept so earlier work isn't lost. The new branch
+/// gets a timestamp suffix to stay clear of it. The agent conversation is
+/// forgotten since it belonged to the old checkout.
+#[debug_handler]
+async fn rebase_worktree(
+    State(ctx): State<AppContext>,
+    Path(id): Path<i32>,
+    Json(params): Json<RebaseWorktreeRequest>,
+) -> Result<Response> {
+    let task = orchestrator_tasks::Entity::find_by_id(id)
+        .one(&ctx.db)
+        .await?
+        .ok_or(Error::NotFound)?;
+
+    if task.status == "in_progress" || PROCESS_MANAGER.is_running(id) {
+        return Err(Error::BadRequest(
+            "Stop the task before replacing its worktree".to_string(),
+        ));
+    }
+
+    let repo_path = settings::Entity::get_value(&ctx.db, "target_repo_path")
+        .await
+        .ok_or_else(|| Error::BadRequest("Target repo path not configured".to_string()))?;
+
+    let base_branch = match params.base_branch.filter(|b| !b.trim().is_empty()) {
+        Some(branch) => branch.trim().to_string(),
+        None => match task.base_branch.clone() {
+            Some(branch) => branch,
+            None => orchestrator::dev_branch(&ctx.db).await,
+        },
+    };
+
+    remove_worktree(&ctx, &task).await;
+
+    // Drop worktree entries whose directories were already deleted by hand
+    let _ = tokio::process::Command::new("git")
+        .args(["-C", &repo_path, "worktree", "prune"])
+        .output()
+        .await;
+
+    let worktrees_dir = orchestrator::configured_worktrees_dir(&ctx.db, &repo_path).await;
+    tokio::fs::create_dir_all(&worktrees_dir)
+        .await
+        .map_err(|e| Error::string(&format!("Failed to create worktrees directory: {}", e)))?;
+
+    if let Some(min_mb) = orchestrator::min_free_disk_mb(&ctx.db).await {
+        orchestrator::ensure_free_disk(&worktrees_dir, min_mb)
+            .await
+            .map_err(|e| {
+                Error::CustomError(
+                    StatusCode::INSUFFICIENT_STORAGE,
+                    ErrorDetail::new("insufficient_disk".to_string(), e),
+                )
+            })?;
+    }
+
+    let worktree_name = orchestrator::worktree_name(&task.name);
+    let suffix = chrono::Utc::now().format("%Y%m%d%H%M%S");
+    let template = orchestrator::branch_template(&ctx.db).await;
+    let task_branch = format!(
+        "{}-{}",
+        orchestrator::render_branch_name(&template, &task).map_err(Error::BadRequest)?,
+        suffix
+    );
+    let mut worktree_path = format!("{}/{}", worktrees_dir, worktree_name);
+    if std::path::Path::new(&worktree_path).exists() {
+        worktree_path = format!("{}-{}", worktree_path, suffix);
+    }
+
+    let ssh_command = git::ssh_command(&ctx.db).await;
+    let output = git::add_worktree(
+        &repo_path,
+        &task_branch,
+        &worktree_path,
+        &base_branch,
+        ssh_command.as_deref(),
+    )
+    .await
+    .map_err(|e| Error::string(&format!("Failed to run git worktree command: {}", e)))?;
+
+    if !output.status.success() {
+        return Err(Error::BadRequest(format!(
+            "Git worktree failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    let message = format!(
+        "Worktree replaced: {} -> {} on branch {} from {}",
+        task.worktree_path.as_deref().unwrap_or("(none)"),
+        worktree_path,
+        task_branch,
+        base_branch
+    );
+    tracing::info!("Task {}: {}", id, message);
+    orchestrator_task_logs::Entity::log_task_event(&ctx.db, id, "system", &message).await;
+
+    let mut active: orchestrator_tasks::ActiveModel = task.into();
+    active.worktree_path = Set(Some(worktree_path));
+    active.base_branch = Set(Some(base_branch));
+    active.agent_session_id = Set(None);
+    active.updated_at = Set(chrono::Utc::now().into());
+    let updated = active.update(&ctx.db).await?;
+
+    format::json(TaskResponse::from(updated))
 }
 
 /// Delete a task
@@ -218,27 +1039,21 @@ async fn delete(State(ctx): State<AppContext>, Path(id): Path<i32>) -> Result<Re
         }
     }
 
-    // Delete associated process sessions
+    // Delete the task along with its sessions and logs
+    let txn = ctx.db.begin().await?;
     process_sessions::Entity::delete_many()
         .filter(process_sessions::Column::TaskId.eq(id))
-        .exec(&ctx.db)
+        .exec(&txn)
         .await?;
-
-    // Delete the task
-    orchestrator_tasks::Entity::delete_by_id(id)
-        .exec(&ctx.db)
+    orchestrator_task_logs::Entity::delete_many()
+        .filter(orchestrator_task_logs::Column::TaskId.eq(id))
+        .exec(&txn)
         .await?;
+    orchestrator_tasks::Entity::delete_by_id(id).exec(&txn).await?;
+    txn.commit().await?;
 
     // Optionally clean up worktree (don't fail if it doesn't work)
-    if let Some(worktree_path) = &task.worktree_path {
-        if std::path::Path::new(worktree_path).exists() {
-            // Try to remove the worktree
-            let _ = tokio::process::Command::new("git")
-                .args(["worktree", "remove", "--force", worktree_path])
-                .output()
-                .await;
-        }
-    }
+    remove_worktree(&ctx, &task).await;
 
     tracing::info!("Deleted task {} ({})", id, task.name);
 
@@ -248,6 +1063,162 @@ async fn delete(State(ctx): State<AppContext>, Path(id): Path<i32>) -> Result<Re
     }))
 }
 
+#[derive(Debug, Deserialize)]
+pub struct CleanupRequest {
+    /// Statuses to remove, defaults to `completed`
+    #[serde(default)]
+    pub statuses: Vec<String>,
+    /// Only remove tasks last updated more than this many days ago
+    pub older_than_days: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CleanupResponse {
+    pub deleted_tasks: u64,
+    pub deleted_sessions: u64,
+    pub deleted_logs: u64,
+    pub removed_worktrees: u64,
+}
+
+/// Delete finished tasks in bulk along with their sessions, logs and worktrees
+#[debug_handler]
+async fn cleanup(
+    State(ctx): State<AppContext>,
+    Json(params): Json<CleanupRequest>,
+) -> Result<Response> {
+    let statuses = if params.statuses.is_empty() {
+        vec!["completed".to_string()]
+    } else {
+        params.statuses
+    };
+    if statuses.iter().any(|s| s == "in_progress") {
+        return Err(Error::BadRequest(
+            "Cannot clean up in_progress tasks, stop them first".to_string(),
+        ));
+    }
+
+    let mut find = orchestrator_tasks::Entity::find()
+        .filter(orchestrator_tasks::Column::Status.is_in(statuses));
+    if let Some(days) = params.older_than_days {
+        let cutoff = chrono::Utc::now() - chrono::Duration::days(days.max(0));
+        find = find.filter(orchestrator_tasks::Column::UpdatedAt.lt(cutoff));
+    }
+
+    // Never pull a task out from under a running agent
+    let tasks: Vec<orchestrator_tasks::Model> = find
+        .all(&ctx.db)
+        .await?
+        .into_iter()
+        .filter(|t| !PROCESS_MANAGER.is_running(t.id))
+        .collect();
+    let ids: Vec<i32> = tasks.iter().map(|t| t.id).collect();
+
+    let txn = ctx.db.begin().await?;
+    let deleted_sessions = process_sessions::Entity::delete_many()
+        .filter(process_sessions::Column::TaskId.is_in(ids.clone()))
+        .exec(&txn)
+        .await?
+        .rows_affected;
+    let deleted_logs = orchestrator_task_logs::Entity::delete_many()
+        .filter(orchestrator_task_logs::Column::TaskId.is_in(ids.clone()))
+        .exec(&txn)
+        .await?
+        .rows_affected;
+    let deleted_tasks = orchestrator_tasks::Entity::delete_many()
+        .filter(orchestrator_tasks::Column::Id.is_in(ids))
+        .exec(&txn)
+        .await?
+        .rows_affected;
+    txn.commit().await?;
+
+    let mut removed_worktrees = 0;
+    for task in &tasks {
+        if task
+            .worktree_path
+            .as_deref()
+            .is_some_and(|p| std::path::Path::new(p).exists())
+        {
+            remove_worktree(&ctx, task).await;
+            removed_worktrees += 1;
+        }
+    }
+
+    tracing::info!(
+        "Cleanup removed {} task(s), {} session(s), {} log(s) and {} worktree(s)",
+        deleted_tasks,
+        deleted_sessions,
+        deleted_logs,
+        removed_worktrees
+    );
+
+    format::json(CleanupResponse {
+        deleted_tasks,
+        deleted_sessions,
+        deleted_logs,
+        removed_worktrees,
+    })
+}
+
+/// Time allowed for each task's `git diff --shortstat`
+const DIFFSTAT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+#[derive(Debug, Serialize)]
+pub struct TaskDiffStat {
+    pub task_id: i32,
+    #[serde(flatten)]
+    pub stat: git::DiffStat,
+}
+
+/// Diff statistics of every active task's worktree against its base branch
+///
+/// Covers `in_progress` and `stopped` tasks. Tasks whose worktree is gone,
+/// or whose diff fails or times out, are left out.
+#[debug_handler]
+async fn diffstat(State(ctx): State<AppContext>) -> Result<Response> {
+    let tasks = orchestrator_tasks::Entity::find()
+        .filter(orchestrator_tasks::Column::Status.is_in(["in_progress", "stopped"]))
+        .order_by_asc(orchestrator_tasks::Column::Id)
+        .all(&ctx.db)
+        .await?;
+
+    let dev_branch = orchestrator::dev_branch(&ctx.db).await;
+
+    let diffs = tasks.into_iter().filter_map(|task| {
+        let worktree_path = task.worktree_path.clone()?;
+        if !std::path::Path::new(&worktree_path).exists() {
+            return None;
+        }
+        let base_branch = task.base_branch.clone().unwrap_or_else(|| dev_branch.clone());
+
+        Some(async move {
+            match tokio::time::timeout(
+                DIFFSTAT_TIMEOUT,
+                git::diff_shortstat(&worktree_path, &base_branch),
+            )
+            .await
+            {
+                Ok(Ok(stat)) => Some(TaskDiffStat { task_id: task.id, stat }),
+                Ok(Err(e)) => {
+                    tracing::warn!("git diff failed for task {}: {}", task.id, e);
+                    None
+                }
+                Err(_) => {
+                    tracing::warn!("git diff timed out for task {}", task.id);
+                    None
+                }
+            }
+        })
+    });
+
+    let stats: Vec<TaskDiffStat> = futures::future::join_all(diffs)
+        .await
+        .into_iter()
+        .flatten()
+        .collect();
+
+    format::json(stats)
+}
+
 /// Get task stats
 #[debug_handler]
 async fn stats(State(ctx): State<AppContext>) -> Result<Response> {
@@ -276,13 +1247,77 @@ async fn stats(State(ctx): State<AppContext>) -> Result<Response> {
         .count(&ctx.db)
         .await?;
 
+    let needs_review = orchestrator_tasks::Entity::find()
+        .filter(orchestrator_tasks::Column::Status.eq("needs_review"))
+        .count(&ctx.db)
+        .await?;
+
+    // Aggregate token usage and spend across all tasks
+    let (input_tokens, output_tokens, estimated_cost) = orchestrator_tasks::Entity::find()
+        .select_only()
+        .column_as(orchestrator_tasks::Column::InputTokens.sum(), "input_tokens")
+        .column_as(orchestrator_tasks::Column::OutputTokens.sum(), "output_tokens")
+        .column_as(orchestrator_tasks::Column::EstimatedCost.sum(), "estimated_cost")
+        .into_tuple::<(Option<i64>, Option<i64>, Option<f64>)>()
+        .one(&ctx.db)
+        .await?
+        .unwrap_or_default();
+
+    // Queued tasks in the order they will start
+    let queue: Vec<serde_json::Value> = orchestrator::queued_tasks(&ctx.db)
+        .await?
+        .into_iter()
+        .map(|t| {
+            serde_json::json!({
+                "id": t.id,
+                "name": t.name,
+                "priority": t.priority,
+                "queue_position": t.queue_position,
+            })
+        })
+        .collect();
+
+    // Capacity: running agents against the configured limit
+    let parallel_limit: usize = settings::Entity::get_value(&ctx.db, "parallel_limit")
+        .await
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(1);
+    let running_ids = PROCESS_MANAGER.running_tasks();
+    let now = chrono::Utc::now();
+    let running: Vec<serde_json::Value> = orchestrator_tasks::Entity::find()
+        .filter(orchestrator_tasks::Column::Id.is_in(running_ids.clone()))
+        .order_by_asc(orchestrator_tasks::Column::StartedAt)
+        .all(&ctx.db)
+        .await?
+        .into_iter()
+        .map(|t| {
+            let elapsed_ms = t
+                .started_at
+                .map(|started| (now - started.with_timezone(&chrono::Utc)).num_milliseconds().max(0))
+                .unwrap_or(0);
+            serde_json::json!({
+                "id": t.id,
+                "name": t.name,
+                "elapsed_ms": elapsed_ms,
+            })
+        })
+        .collect();
+
     format::json(serde_json::json!({
         "queued": queued,
+        "queue": queue,
+        "parallel_limit": parallel_limit,
+        "available_slots": parallel_limit.saturating_sub(running_ids.len()),
+        "running": running,
         "in_progress": in_progress,
         "stopped": stopped,
         "completed": completed,
         "failed": failed,
-        "running_processes": PROCESS_MANAGER.running_tasks().len()
+        "needs_review": needs_review,
+        "running_processes": running_ids.len(),
+        "total_input_tokens": input_tokens.unwrap_or(0),
+        "total_output_tokens": output_tokens.unwrap_or(0),
+        "total_estimated_cost": estimated_cost.unwrap_or(0.0)
     }))
 }
 
@@ -291,8 +1326,23 @@ pub fn routes() -> Routes {
         .prefix("/api/tasks")
         .add("/", get(list))
         .add("/stats", get(stats))
+        .add("/diffstat", get(diffstat))
+        .add("/reorder", post(reorder))
+        .add("/cleanup", post(cleanup))
+        .add("/kill-all", post(kill_all))
         .add("/{id}", get(get_one))
         .add("/{id}", axum::routing::delete(delete))
+        .add("/{id}", patch(update))
         .add("/{id}/stop", post(stop))
         .add("/{id}/restart", post(restart))
+        .add("/{id}/rerun", post(rerun))
+        .add("/{id}/abandon", post(abandon))
+        .add("/{id}/rebase-worktree", post(rebase_worktree))
+        .add("/{id}/note", put(update_note))
+        .add("/{id}/description", put(update_description))
+        .add("/{id}/logs", get(get_logs))
+        .add("/{id}/logs/tail", get(tail_logs))
+        .add("/{id}/patch", get(export_patch))
+        .add("/{id}/sessions", get(list_sessions))
+        .add("/{id}/prompt", get(prompt))
 }
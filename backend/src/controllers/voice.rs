@@ -1,12 +1,44 @@
 //! Voice Assistant controller for saving screenshots and spawning BA agent
 
+use crate::controllers::tasks::process_error;
 use crate::models::_entities::settings;
+use crate::services::orchestrator;
+use crate::services::process_manager::{push_agent_args, sandboxed_command, PROCESS_MANAGER};
 use loco_rs::prelude::*;
 use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use tokio::process::Command;
+use axum::extract::DefaultBodyLimit;
 use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use dashmap::DashMap;
+use std::time::{Duration, Instant};
+
+/// How long an idempotency key keeps pointing at the session it started
+const IDEMPOTENCY_WINDOW: Duration = Duration::from_secs(60);
+
+/// Default number of BA agent sessions that may run at once
+const DEFAULT_MAX_UI_SESSIONS: usize = 3;
+
+/// Default cap on screenshot request bodies: 20MB
+///
+/// Images arrive base64 encoded, which inflates them by about a third, so this
+/// admits screenshots of roughly 15MB. Override with `SCREENSHOT_BODY_LIMIT_BYTES`.
+const DEFAULT_SCREENSHOT_BODY_LIMIT: usize = 20 * 1024 * 1024;
+
+/// Maximum screenshot request body size; larger requests get 413 Payload Too Large
+fn screenshot_body_limit() -> usize {
+    std::env::var("SCREENSHOT_BODY_LIMIT_BYTES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|limit| *limit > 0)
+        .unwrap_or(DEFAULT_SCREENSHOT_BODY_LIMIT)
+}
+
+lazy_static::lazy_static! {
+    /// Idempotency keys of recent generate requests, with their session and start time
+    static ref RECENT_GENERATIONS: DashMap<String, (String, Instant)> = DashMap::new();
+}
 
 #[derive(Debug, Deserialize)]
 pub struct SaveScreenshotRequest {
@@ -45,6 +77,12 @@ pub struct GenerateTasksRequest {
     /// Which agent to use (claude, codex, gemini)
     #[serde(default)]
     pub agent: AgentType,
+    /// A previous session to continue, using the agent's own resume support
+    #[serde(default)]
+    pub continue_session: Option<String>,
+    /// Client-generated key; repeats within a short window reuse the first session
+    #[serde(default)]
+    pub idempotency_key: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -66,6 +104,17 @@ async fn get_setting(db: &sea_orm::DatabaseConnection, key: &str) -> Option<Stri
         .filter(|v| !v.is_empty())
 }
 
+/// Maximum number of concurrent BA agent sessions, 0 for no limit
+///
+/// Read from the `max_ui_sessions` setting. This is separate from
+/// `parallel_limit`, which only governs task agents.
+async fn max_ui_sessions(db: &sea_orm::DatabaseConnection) -> usize {
+    get_setting(db, "max_ui_sessions")
+        .await
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_MAX_UI_SESSIONS)
+}
+
 /// Save a screenshot to the temp_imgs folder in the target repo
 #[debug_handler]
 async fn save_screenshot(
@@ -164,106 +213,176 @@ async fn generate_tasks(
         AgentType::Gemini => ("gemini", "gemini"),
     };
 
-    // Check if agent command is available
-    let agent_check = Command::new("which")
-        .arg(agent_cmd)
-        .output()
-        .await;
-
-    if agent_check.is_err() || !agent_check.unwrap().status.success() {
-        return Err(Error::BadRequest(
-            format!("The '{}' command is not found in PATH. Please install it first.", agent_cmd)
-        ));
-    }
+    // Inside a sandbox the agent is looked up by the sandbox itself
+    let sandbox = orchestrator::sandbox_command(&ctx.db).await;
+    let agent_path = match &sandbox {
+        Some(_) => None,
+        // Check if agent command is available
+        None => Some(PROCESS_MANAGER.resolve_agent(agent_cmd).await.ok_or_else(|| {
+            Error::BadRequest(format!(
+                "The '{}' command is not found in PATH. Please install it first.",
+                agent_cmd
+            ))
+        })?),
+    };
 
-    // Spawn the agent using script for PTY
-    // Claude: script -q /dev/null claude -p "prompt" --dangerously-skip-permissions
-    // Codex: script -q /dev/null codex exec "prompt" --full-auto
-    // Gemini: script -q /dev/null gemini "prompt" -y
-    let child = match params.agent {
-        AgentType::Claude => {
-            Command::new("script")
-                .arg("-q")
-                .arg("/dev/null")
-                .arg("claude")
-                .arg("-p")
-                .arg(&full_prompt)
-                .arg("--dangerously-skip-permissions")
-                .current_dir(&repo_path)
-                .stdin(std::process::Stdio::null())
-                .stdout(std::process::Stdio::piped())
-                .stderr(std::process::Stdio::piped())
-                .spawn()
-        }
-        AgentType::Codex => {
-            Command::new("script")
-                .arg("-q")
-                .arg("/dev/null")
-                .arg("codex")
-                .arg("exec")
-                .arg(&full_prompt)
-                .arg("--full-auto")
-                .current_dir(&repo_path)
-                .stdin(std::process::Stdio::null())
-                .stdout(std::process::Stdio::piped())
-                .stderr(std::process::Stdio::piped())
-                .spawn()
+    // Conversation id captured from the session being continued
+    let resume_id = match &params.continue_session {
+        Some(previous) => {
+            let output = PROCESS_MANAGER
+                .session_output(previous, 0)
+                .ok_or_else(|| Error::BadRequest(format!("Session {} not found or expired", previous)))?;
+            if output.agent_session_id.is_none() {
+                tracing::warn!("Session {} reported no conversation id, starting fresh", previous);
+            }
+            output.agent_session_id
         }
-        AgentType::Gemini => {
-            Command::new("script")
+        None => None,
+    };
+
+    // Spawn the agent in the sandbox, or using script for PTY
+    let skip_permissions = orchestrator::claude_skip_permissions(&ctx.db).await;
+    let mut command = match (&sandbox, agent_path) {
+        (Some(template), _) => sandboxed_command(template, &repo_path, &full_prompt, agent_cmd),
+        (None, agent_path) => {
+            let mut command = Command::new("script");
+            command
                 .arg("-q")
                 .arg("/dev/null")
-                .arg("gemini")
-                .arg(&full_prompt)
-                .arg("-y")
-                .current_dir(&repo_path)
-                .stdin(std::process::Stdio::null())
-                .stdout(std::process::Stdio::piped())
-                .stderr(std::process::Stdio::piped())
-                .spawn()
+                .arg(agent_path.as_deref().unwrap_or(agent_cmd));
+            command
         }
     };
+    command.current_dir(&repo_path);
+    // Session stdin is closed, so without skipping permissions claude can only deny tools
+    push_agent_args(
+        &mut command,
+        agent_name,
+        &full_prompt,
+        resume_id.as_deref(),
+        skip_permissions,
+    );
+
+    let session_id = uuid::Uuid::new_v4().to_string();
+
+    // Reserve the idempotency key before spawning so a concurrent duplicate sees it
+    let idempotency_key = params.idempotency_key.filter(|k| !k.is_empty());
+    if let Some(key) = &idempotency_key {
+        RECENT_GENERATIONS.retain(|_, (_, at)| at.elapsed() < IDEMPOTENCY_WINDOW);
+        match RECENT_GENERATIONS.entry(key.clone()) {
+            dashmap::mapref::entry::Entry::Occupied(existing) => {
+                let existing_session = existing.get().0.clone();
+                tracing::info!(
+                    "Duplicate generate request for key {}, reusing session {}",
+                    key,
+                    existing_session
+                );
+                return format::json(GenerateTasksResponse {
+                    success: true,
+                    message: format!("Request already started as session {}", existing_session),
+                    session_id: Some(existing_session),
+                });
+            }
+            dashmap::mapref::entry::Entry::Vacant(slot) => {
+                slot.insert((session_id.clone(), Instant::now()));
+            }
+        }
+    }
 
-    match child {
-        Ok(process) => {
-            let pid = process.id();
-            let agent_name_owned = agent_name.to_string();
-
-            // Spawn a task to wait for completion and log output
-            tokio::spawn(async move {
-                match process.wait_with_output().await {
-                    Ok(output) => {
-                        let stdout = String::from_utf8_lossy(&output.stdout);
-                        let stderr = String::from_utf8_lossy(&output.stderr);
-                        tracing::info!("{} agent completed. Exit code: {:?}", agent_name_owned, output.status.code());
-                        if !stdout.is_empty() {
-                            tracing::info!("{} agent stdout: {}", agent_name_owned, stdout);
-                        }
-                        if !stderr.is_empty() {
-                            tracing::warn!("{} agent stderr: {}", agent_name_owned, stderr);
-                        }
-                    }
-                    Err(e) => {
-                        tracing::error!("Failed to wait for {} agent: {}", agent_name_owned, e);
-                    }
-                }
-            });
-
-            tracing::info!("Spawned {} agent with PID {:?}", agent_name, pid);
+    let limit = max_ui_sessions(&ctx.db).await;
+    match PROCESS_MANAGER.spawn_session_process(&session_id, command, limit).await {
+        Ok(pid) => {
+            tracing::info!("Spawned {} agent with PID {} for session {}", agent_name, pid, session_id);
 
             format::json(GenerateTasksResponse {
                 success: true,
-                message: format!("{} agent spawned successfully (PID: {:?})", agent_name, pid),
-                session_id: pid.map(|p| p.to_string()),
+                message: format!("{} agent spawned successfully (PID: {})", agent_name, pid),
+                session_id: Some(session_id),
             })
         }
         Err(e) => {
             tracing::error!("Failed to spawn {} agent: {}", agent_name, e);
-            Err(Error::BadRequest(format!("Failed to spawn {} agent: {}", agent_name, e)))
+            // Let the client retry with the same key
+            if let Some(key) = &idempotency_key {
+                RECENT_GENERATIONS.remove(key);
+            }
+            Err(process_error(e))
         }
     }
 }
 
+#[derive(Debug, Deserialize)]
+pub struct SessionOutputQuery {
+    /// Number of lines the client has already seen
+    #[serde(default)]
+    pub since: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionOutputResponse {
+    pub lines: Vec<String>,
+    /// Value to pass as `since` on the next poll
+    pub next: usize,
+    pub done: bool,
+    pub exit_code: Option<i32>,
+    pub agent_session_id: Option<String>,
+    /// Lines dropped because the session exceeded the output cap
+    pub dropped_lines: usize,
+}
+
+/// Poll the output of a BA agent session
+#[debug_handler]
+async fn session_output(
+    Path(session_id): Path<String>,
+    Query(query): Query<SessionOutputQuery>,
+) -> Result<Response> {
+    let output = PROCESS_MANAGER
+        .session_output(&session_id, query.since)
+        .ok_or(Error::NotFound)?;
+
+    format::json(SessionOutputResponse {
+        next: query.since + output.lines.len(),
+        lines: output.lines,
+        done: output.done,
+        exit_code: output.exit_code,
+        agent_session_id: output.agent_session_id,
+        dropped_lines: output.dropped_lines,
+    })
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionStatusResponse {
+    /// Number of BA agent sessions currently running
+    pub active: usize,
+    /// Maximum number of concurrent sessions, 0 when unlimited
+    pub limit: usize,
+}
+
+/// Report how many BA agent sessions are running against the configured limit
+#[debug_handler]
+async fn session_status(State(ctx): State<AppContext>) -> Result<Response> {
+    format::json(SessionStatusResponse {
+        active: PROCESS_MANAGER.running_sessions(),
+        limit: max_ui_sessions(&ctx.db).await,
+    })
+}
+
+/// Cancel a running BA agent session
+#[debug_handler]
+async fn cancel_session(Path(session_id): Path<String>) -> Result<Response> {
+    PROCESS_MANAGER
+        .kill_session_process(&session_id)
+        .await
+        .map_err(process_error)?;
+
+    tracing::info!("Cancelled BA agent session {}", session_id);
+
+    format::json(serde_json::json!({
+        "success": true,
+        "message": format!("Session {} cancelled", session_id)
+    }))
+}
+
 /// Clear all screenshots from temp_imgs folder
 #[debug_handler]
 async fn clear_screenshots(State(ctx): State<AppContext>) -> Result<Response> {
@@ -307,7 +426,13 @@ async fn clear_screenshots(State(ctx): State<AppContext>) -> Result<Response> {
 pub fn routes() -> Routes {
     Routes::new()
         .prefix("/api/voice")
-        .add("/screenshot", post(save_screenshot))
+        .add(
+            "/screenshot",
+            post(save_screenshot).layer(DefaultBodyLimit::max(screenshot_body_limit())),
+        )
         .add("/generate-tasks", post(generate_tasks))
         .add("/screenshots", axum::routing::delete(clear_screenshots))
+        .add("/sessions", get(session_status))
+        .add("/cancel/{session_id}", post(cancel_session))
+        .add("/session/{session_id}/output", get(session_output))
 }
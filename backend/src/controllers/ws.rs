@@ -1,40 +1,196 @@
 //! WebSocket controller for terminal streaming
 
+use crate::models::_entities::{orchestrator_tasks, process_sessions, settings};
+use crate::services::metrics::{self, OUTPUT_LINES_DROPPED_TOTAL};
+use crate::services::orchestrator;
 use crate::services::process_manager::{OutputLine, PROCESS_MANAGER};
 use axum::{
     extract::{
         ws::{Message, WebSocket, WebSocketUpgrade},
-        Path,
+        Path, State,
     },
     response::Response,
 };
-use futures::{sink::SinkExt, stream::StreamExt};
+use futures::{
+    sink::SinkExt,
+    stream::{SplitSink, StreamExt},
+};
+use loco_rs::app::AppContext;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder};
 use serde::{Deserialize, Serialize};
-use tokio::sync::broadcast;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+
+/// How often buffered output lines are flushed to the client
+const FLUSH_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Maximum number of lines coalesced into a single frame
+const MAX_BATCH_LINES: usize = 100;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputEntry {
+    pub line: String,
+    pub is_stderr: bool,
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum WsMessage {
     #[serde(rename = "output")]
     Output { line: String, is_stderr: bool },
+    #[serde(rename = "output_batch")]
+    OutputBatch { lines: Vec<OutputEntry> },
+    /// Raw input written to the process's stdin. Data is sent as-is, which
+    /// suits keystroke streams; set `append_newline` to submit it as a line
+    /// (a newline is only added when the data doesn't already end with one).
     #[serde(rename = "input")]
-    Input { data: String },
+    Input {
+        data: String,
+        #[serde(default)]
+        append_newline: bool,
+    },
     #[serde(rename = "kill")]
     Kill,
     #[serde(rename = "error")]
     Error { message: String },
     #[serde(rename = "connected")]
     Connected { task_id: i32, is_running: bool },
+    /// Sent by the client to ask for a `task_snapshot`
+    #[serde(rename = "snapshot")]
+    Snapshot,
+    #[serde(rename = "task_snapshot")]
+    TaskSnapshot {
+        task_id: i32,
+        status: String,
+        time_spent_ms: i32,
+        pid: Option<u32>,
+        /// Exit code of the most recent finished run
+        last_exit_code: Option<i32>,
+    },
+    /// The agent reported progress, in percent complete
+    #[serde(rename = "progress")]
+    Progress { task_id: i32, progress: i32 },
+}
+
+/// Prefix added to streamed output lines, chosen by the `stream_timestamps` setting
+///
+/// Applied only to the live stream; persisted logs keep their own `created_at`.
+enum LinePrefix {
+    None,
+    /// Wall clock time in the display timezone (`iso` or `true`)
+    Timestamp(chrono_tz::Tz),
+    /// Time since the task started (`elapsed`)
+    Elapsed(chrono::DateTime<chrono::Utc>),
+}
+
+impl LinePrefix {
+    async fn load(ctx: &AppContext, task_id: i32) -> Self {
+        let mode = settings::Entity::get_value(&ctx.db, "stream_timestamps").await;
+        match mode.as_deref().map(str::trim) {
+            Some("iso" | "true") => Self::Timestamp(orchestrator::display_timezone(&ctx.db).await),
+            Some("elapsed") => {
+                let started_at = orchestrator_tasks::Entity::find_by_id(task_id)
+                    .one(&ctx.db)
+                    .await
+                    .ok()
+                    .flatten()
+                    .and_then(|t| t.started_at)
+                    .map(|t| t.with_timezone(&chrono::Utc))
+                    .unwrap_or_else(chrono::Utc::now);
+                Self::Elapsed(started_at)
+            }
+            _ => Self::None,
+        }
+    }
+
+    fn apply(&self, line: String) -> String {
+        let now = chrono::Utc::now();
+        match self {
+            Self::None => line,
+            Self::Timestamp(tz) => format!(
+                "[{}] {}",
+                now.with_timezone(tz)
+                    .to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+                line
+            ),
+            Self::Elapsed(started_at) => {
+                let elapsed = (now - *started_at).num_milliseconds().max(0);
+                format!("[+{}.{:03}s] {}", elapsed / 1000, elapsed % 1000, line)
+            }
+        }
+    }
 }
 
 pub async fn terminal_handler(
     ws: WebSocketUpgrade,
+    State(ctx): State<AppContext>,
     Path(task_id): Path<i32>,
 ) -> Response {
-    ws.on_upgrade(move |socket| handle_socket(socket, task_id))
+    ws.on_upgrade(move |socket| handle_socket(socket, ctx, task_id))
+}
+
+/// Build the task's current status snapshot from the database
+async fn task_snapshot(ctx: &AppContext, task_id: i32) -> WsMessage {
+    let task = match orchestrator_tasks::Entity::find_by_id(task_id).one(&ctx.db).await {
+        Ok(Some(task)) => task,
+        Ok(None) => {
+            return WsMessage::Error {
+                message: format!("Task {} not found", task_id),
+            }
+        }
+        Err(e) => {
+            return WsMessage::Error {
+                message: format!("Failed to load task: {}", e),
+            }
+        }
+    };
+
+    let last_exit_code = process_sessions::Entity::find()
+        .filter(process_sessions::Column::TaskId.eq(task_id))
+        .filter(process_sessions::Column::EndedAt.is_not_null())
+        .order_by_desc(process_sessions::Column::EndedAt)
+        .one(&ctx.db)
+        .await
+        .ok()
+        .flatten()
+        .and_then(|s| s.exit_code);
+
+    WsMessage::TaskSnapshot {
+        task_id,
+        status: task.status,
+        time_spent_ms: task.time_spent_ms,
+        pid: PROCESS_MANAGER.get_pid(task_id),
+        last_exit_code,
+    }
+}
+
+/// Serialize and send a message, returning false once the client is gone
+async fn send_message(sender: &mut SplitSink<WebSocket, Message>, msg: &WsMessage) -> bool {
+    match serde_json::to_string(msg) {
+        Ok(json) => sender.send(Message::Text(json.into())).await.is_ok(),
+        Err(_) => true,
+    }
 }
 
-async fn handle_socket(socket: WebSocket, task_id: i32) {
+/// Send buffered output as a single frame and clear the buffer
+async fn flush_batch(sender: &mut SplitSink<WebSocket, Message>, batch: &mut Vec<OutputEntry>) -> bool {
+    let msg = match batch.len() {
+        0 => return true,
+        1 => {
+            let entry = batch.remove(0);
+            WsMessage::Output {
+                line: entry.line,
+                is_stderr: entry.is_stderr,
+            }
+        }
+        _ => WsMessage::OutputBatch {
+            lines: std::mem::take(batch),
+        },
+    };
+    send_message(sender, &msg).await
+}
+
+async fn handle_socket(socket: WebSocket, ctx: AppContext, task_id: i32) {
     let (mut sender, mut receiver) = socket.split();
 
     // Check if process is running
@@ -49,30 +205,79 @@ async fn handle_socket(socket: WebSocket, task_id: i32) {
 
     // Subscribe to process output
     let mut output_rx: broadcast::Receiver<OutputLine> = PROCESS_MANAGER.subscribe_output();
+    let mut progress_rx = orchestrator::subscribe_progress();
+    let prefix = LinePrefix::load(&ctx, task_id).await;
 
-    // Spawn task to forward output to WebSocket
+    // Replies to client requests, sent by the forwarding task so frames stay ordered
+    let (reply_tx, mut reply_rx) = mpsc::channel::<WsMessage>(8);
+
+    // Spawn task to forward output to WebSocket, coalescing lines into frames
     let send_task = tokio::spawn(async move {
+        let mut batch: Vec<OutputEntry> = Vec::new();
+        let mut flush = tokio::time::interval(FLUSH_INTERVAL);
+        flush.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
         loop {
-            match output_rx.recv().await {
-                Ok(output) => {
-                    if output.task_id == task_id {
-                        let msg = WsMessage::Output {
-                            line: output.line,
-                            is_stderr: output.is_stderr,
-                        };
-                        if let Ok(json) = serde_json::to_string(&msg) {
-                            if sender.send(Message::Text(json.into())).await.is_err() {
+            tokio::select! {
+                result = output_rx.recv() => match result {
+                    Ok(output) => {
+                        if output.task_id == task_id {
+                            batch.push(OutputEntry {
+                                line: prefix.apply(output.line),
+                                is_stderr: output.is_stderr,
+                            });
+                            if batch.len() >= MAX_BATCH_LINES
+                                && !flush_batch(&mut sender, &mut batch).await
+                            {
                                 break;
                             }
                         }
                     }
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        metrics::add(&OUTPUT_LINES_DROPPED_TOTAL, n);
+
+                        // Tell the client output was lost instead of leaving a silent gap
+                        if !flush_batch(&mut sender, &mut batch).await {
+                            break;
+                        }
+                        let msg = WsMessage::Error {
+                            message: format!("Output stream lagged: dropped {} lines", n),
+                        };
+                        if !send_message(&mut sender, &msg).await {
+                            break;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Closed) => {
+                        let _ = flush_batch(&mut sender, &mut batch).await;
+                        break;
+                    }
+                },
+                _ = flush.tick() => {
+                    if !flush_batch(&mut sender, &mut batch).await {
+                        break;
+                    }
                 }
-                Err(broadcast::error::RecvError::Lagged(_)) => {
-                    // Skip lagged messages
-                    continue;
+                Ok(progress) = progress_rx.recv() => {
+                    if progress.task_id != task_id {
+                        continue;
+                    }
+                    // Flush first so the progress frame follows the line that reported it
+                    let msg = WsMessage::Progress {
+                        task_id,
+                        progress: progress.percent,
+                    };
+                    if !flush_batch(&mut sender, &mut batch).await
+                        || !send_message(&mut sender, &msg).await
+                    {
+                        break;
+                    }
                 }
-                Err(broadcast::error::RecvError::Closed) => {
-                    break;
+                Some(reply) = reply_rx.recv() => {
+                    if !flush_batch(&mut sender, &mut batch).await
+                        || !send_message(&mut sender, &reply).await
+                    {
+                        break;
+                    }
                 }
             }
         }
@@ -85,14 +290,27 @@ async fn handle_socket(socket: WebSocket, task_id: i32) {
                 Ok(Message::Text(text)) => {
                     if let Ok(msg) = serde_json::from_str::<WsMessage>(&text) {
                         match msg {
-                            WsMessage::Input { data } => {
-                                if let Err(e) = PROCESS_MANAGER.send_input(task_id, &data).await {
+                            WsMessage::Input {
+                                data,
+                                append_newline,
+                            } => {
+                                if let Err(e) = PROCESS_MANAGER
+                                    .send_input(task_id, &data, append_newline)
+                                    .await
+                                {
                                     tracing::error!("Failed to send input: {}", e);
                                 }
                             }
                             WsMessage::Kill => {
-                                if let Err(e) = PROCESS_MANAGER.kill_process(task_id).await {
-                                    tracing::error!("Failed to kill process: {}", e);
+                                match PROCESS_MANAGER.kill_process(task_id).await {
+                                    Ok(()) => orchestrator::mark_stopped(&ctx.db, &[task_id]).await,
+                                    Err(e) => tracing::error!("Failed to kill process: {}", e),
+                                }
+                            }
+                            WsMessage::Snapshot => {
+                                let snapshot = task_snapshot(&ctx, task_id).await;
+                                if reply_tx.send(snapshot).await.is_err() {
+                                    break;
                                 }
                             }
                             _ => {}
@@ -112,3 +330,39 @@ async fn handle_socket(socket: WebSocket, task_id: i32) {
         _ = recv_task => {}
     }
 }
+
+/// Stream task lifecycle events to the dashboard
+///
+/// Read-only: messages from the client are ignored. Each frame is a
+/// `{ type, task_id, status }` event; a `lagged` frame means events were
+/// missed and the client should refetch its task list.
+pub async fn events_handler(ws: WebSocketUpgrade) -> Response {
+    ws.on_upgrade(handle_events_socket)
+}
+
+async fn handle_events_socket(socket: WebSocket) {
+    let (mut sender, mut receiver) = socket.split();
+    let mut events_rx = orchestrator::subscribe_task_events();
+
+    loop {
+        tokio::select! {
+            result = events_rx.recv() => {
+                let json = match result {
+                    Ok(event) => serde_json::to_string(&event).unwrap_or_default(),
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        serde_json::json!({ "type": "lagged", "missed": n }).to_string()
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                if sender.send(Message::Text(json.into())).await.is_err() {
+                    break;
+                }
+            }
+            message = receiver.next() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
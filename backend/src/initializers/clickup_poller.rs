@@ -8,80 +8,259 @@ use loco_rs::{
     app::{AppContext, Initializer},
     Result,
 };
-use sea_orm::{ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter, Set};
+use regex::Regex;
+use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter, Set};
+use std::sync::Arc;
 use std::time::Duration;
+use tokio::task::JoinSet;
 use tokio::time::interval;
 
-use crate::models::_entities::{orchestrator_tasks, settings};
-use crate::services::clickup::{priority_to_int, ClickUpClient};
-use crate::services::process_manager::PROCESS_MANAGER;
+use crate::models::_entities::{orchestrator_task_logs, orchestrator_tasks, settings};
+use crate::services::clickup::{self, ClickUpApi, Task, TaskFilter, CLICKUP_BREAKER};
+use crate::services::git;
+use crate::services::metrics::{self, POLLER_CYCLES_TOTAL};
+use crate::services::orchestrator::{self, DependencyState, TaskEventKind};
+use crate::services::process_manager::{ProcessError, SpawnOptions, AGENT_TYPES, PROCESS_MANAGER};
+
+/// Consecutive ClickUp outages that open the circuit breaker
+const DEFAULT_BREAKER_THRESHOLD: u32 = 5;
+
+/// Seconds the poller leaves ClickUp alone once the breaker has opened
+const DEFAULT_BREAKER_COOLDOWN_SECS: u64 = 300;
+
+lazy_static::lazy_static! {
+    /// Matches a `depends:<clickup id>` marker in a task description
+    static ref DEPENDS_ON_RE: Regex =
+        Regex::new(r"(?i)\bdepends:\s*([A-Za-z0-9_-]+)").expect("depends pattern is valid");
+
+    /// Matches a `workdir: <path>` line in a task description
+    static ref WORKDIR_RE: Regex =
+        Regex::new(r"(?im)^\s*workdir:\s*(\S+)\s*$").expect("workdir pattern is valid");
+}
 
 pub struct ClickUpPollerInitializer;
 
-impl ClickUpPollerInitializer {
-    async fn get_setting(db: &sea_orm::DatabaseConnection, key: &str) -> Option<String> {
-        settings::Entity::find()
-            .filter(settings::Column::Key.eq(key))
-            .one(db)
-            .await
-            .ok()
-            .flatten()
-            .map(|s| s.value)
-            .filter(|v| !v.is_empty())
-    }
+/// Settings used by the poller, cached between polls and reloaded on change
+#[derive(Debug, Clone)]
+struct PollerConfig {
+    list_id: Option<String>,
+    trigger_status: String,
+    target_status: String,
+    parallel_limit: usize,
+    target_repo_path: Option<String>,
+    worktrees_root: Option<String>,
+    dev_branch: String,
+    branch_field_id: Option<String>,
+    priority_field_id: Option<String>,
+    depends_field_id: Option<String>,
+    workdir_field_id: Option<String>,
+    agent_field_id: Option<String>,
+    branch_template: String,
+    reuse_existing_branch: bool,
+    breaker_threshold: u32,
+    breaker_cooldown: Duration,
+    git_ssh_command: Option<String>,
+    git_author: git::GitAuthor,
+    claude_skip_permissions: bool,
+    spawn_retries: u32,
+    idle_timeout: Option<Duration>,
+    sandbox_command: Option<String>,
+    min_free_disk_mb: Option<u64>,
+    output_file_path: Option<String>,
+    task_filter: TaskFilter,
+}
 
-    async fn poll_and_process(ctx: AppContext) {
-        let db = &ctx.db;
+impl PollerConfig {
+    async fn load(db: &sea_orm::DatabaseConnection) -> Self {
+        Self {
+            list_id: settings::Entity::get_value(db, "clickup_list_id").await,
+            trigger_status: settings::Entity::get_value(db, "trigger_status")
+                .await
+                .unwrap_or_else(|| "Ready for Dev".to_string()),
+            target_status: settings::Entity::get_value(db, "target_status")
+                .await
+                .unwrap_or_else(|| "In Development".to_string()),
+            parallel_limit: settings::Entity::get_value(db, "parallel_limit")
+                .await
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(1),
+            target_repo_path: settings::Entity::get_value(db, "target_repo_path").await,
+            worktrees_root: settings::Entity::get_value(db, "worktrees_root").await,
+            dev_branch: orchestrator::dev_branch(db).await,
+            branch_field_id: settings::Entity::get_value(db, "branch_custom_field_id").await,
+            priority_field_id: settings::Entity::get_value(db, "priority_custom_field_id").await,
+            depends_field_id: settings::Entity::get_value(db, "depends_custom_field_id").await,
+            workdir_field_id: settings::Entity::get_value(db, "workdir_custom_field_id").await,
+            agent_field_id: settings::Entity::get_value(db, "agent_custom_field_id").await,
+            branch_template: orchestrator::branch_template(db).await,
+            reuse_existing_branch: settings::Entity::get_value(db, "reuse_existing_branch")
+                .await
+                .is_some_and(|v| v == "true"),
+            breaker_threshold: settings::Entity::get_value(db, "clickup_breaker_threshold")
+                .await
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_BREAKER_THRESHOLD),
+            breaker_cooldown: Duration::from_secs(
+                settings::Entity::get_value(db, "clickup_breaker_cooldown_secs")
+                    .await
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(DEFAULT_BREAKER_COOLDOWN_SECS),
+            ),
+            git_ssh_command: git::ssh_command(db).await,
+            git_author: git::author(db).await,
+            claude_skip_permissions: orchestrator::claude_skip_permissions(db).await,
+            spawn_retries: orchestrator::spawn_retry_count(db).await,
+            idle_timeout: orchestrator::idle_timeout(db).await,
+            sandbox_command: orchestrator::sandbox_command(db).await,
+            min_free_disk_mb: orchestrator::min_free_disk_mb(db).await,
+            output_file_path: orchestrator::output_file_path(db).await,
+            task_filter: TaskFilter {
+                include_closed: settings::Entity::get_value(db, "poll_include_closed")
+                    .await
+                    .is_some_and(|v| v == "true"),
+                assignees: settings::Entity::get_value(db, "poll_assignee_id")
+                    .await
+                    .map(|ids| {
+                        ids.split(',')
+                            .map(|id| id.trim().to_string())
+                            .filter(|id| !id.is_empty())
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+            },
+        }
+    }
+}
 
-        // Get settings
-        let Some(list_id) = Self::get_setting(db, "clickup_list_id").await else {
-            tracing::debug!("No ClickUp list configured, skipping poll");
-            return;
-        };
+impl ClickUpPollerInitializer {
+    /// Resolve the ClickUp id of the task this one depends on
+    ///
+    /// Uses the configured custom field when set, otherwise a `depends:<id>`
+    /// marker in the description.
+    fn task_dependency(task: &Task, depends_field_id: Option<&str>) -> Option<String> {
+        depends_field_id
+            .and_then(|field| task.custom_field_value(field))
+            .or_else(|| {
+                task.description
+                    .as_deref()
+                    .and_then(|d| DEPENDS_ON_RE.captures(d))
+                    .map(|caps| caps[1].to_string())
+            })
+            .filter(|id| !id.is_empty())
+    }
 
-        let trigger_status = Self::get_setting(db, "trigger_status")
-            .await
-            .unwrap_or_else(|| "Ready for Dev".to_string());
+    /// Resolve the subdirectory of the worktree the agent should run in
+    ///
+    /// Uses the configured custom field when set, otherwise a `workdir: <path>`
+    /// line in the description.
+    fn task_working_subdir(task: &Task, workdir_field_id: Option<&str>) -> Option<String> {
+        workdir_field_id
+            .and_then(|field| task.custom_field_value(field))
+            .or_else(|| {
+                task.description
+                    .as_deref()
+                    .and_then(|d| WORKDIR_RE.captures(d))
+                    .map(|caps| caps[1].to_string())
+            })
+            .filter(|dir| !dir.is_empty())
+    }
 
-        let target_status = Self::get_setting(db, "target_status")
-            .await
-            .unwrap_or_else(|| "In Development".to_string());
+    /// Resolve the agent type a task asked for
+    ///
+    /// Uses the configured custom field when set, otherwise an `agent:<type>`
+    /// tag. Unknown agent types are ignored so the default applies.
+    fn task_agent_type(task: &Task, agent_field_id: Option<&str>) -> Option<String> {
+        let requested = agent_field_id
+            .and_then(|field| task.custom_field_value(field))
+            .or_else(|| {
+                task.tags
+                    .iter()
+                    .find_map(|tag| tag.name.strip_prefix("agent:").map(str::to_string))
+            })?
+            .trim()
+            .to_lowercase();
+
+        if AGENT_TYPES.contains(&requested.as_str()) {
+            Some(requested)
+        } else {
+            tracing::warn!("Task {} requested unknown agent type '{}'", task.id, requested);
+            None
+        }
+    }
 
-        let parallel_limit: usize = Self::get_setting(db, "parallel_limit")
-            .await
-            .and_then(|s| s.parse().ok())
-            .unwrap_or(1);
+    /// First of `branch-2`, `branch-3`, … that doesn't exist yet
+    ///
+    /// Used unless `reuse_existing_branch` is "true", so a leftover branch from
+    /// an earlier run doesn't make `git worktree add -b` fail.
+    async fn free_branch_name(
+        repo_path: &str,
+        branch: &str,
+    ) -> std::result::Result<String, String> {
+        for n in 2..100 {
+            let candidate = format!("{}-{}", branch, n);
+            if !git::branch_exists(repo_path, &candidate).await {
+                return Ok(candidate);
+            }
+        }
+        Err(format!("No free branch name found for {}", branch))
+    }
 
-        let target_repo_path = match Self::get_setting(db, "target_repo_path").await {
-            Some(p) if !p.is_empty() => p,
-            _ => {
-                tracing::debug!("No target repo path configured, skipping poll");
-                return;
+    /// Record the outcome of a ClickUp call on the circuit breaker
+    ///
+    /// Only outages count as failures; a rejected request says nothing about
+    /// whether ClickUp is up.
+    fn track<T>(config: &PollerConfig, result: &clickup::Result<T>) {
+        match result {
+            Ok(_) => CLICKUP_BREAKER.record_success(),
+            Err(e) if e.is_outage() => {
+                CLICKUP_BREAKER.record_failure(config.breaker_threshold, config.breaker_cooldown)
             }
-        };
+            Err(_) => {}
+        }
+    }
 
-        let dev_branch = Self::get_setting(db, "dev_branch")
-            .await
-            .unwrap_or_else(|| "dev".to_string());
+    /// Whether this instance may orchestrate the configured repo
+    ///
+    /// False while another instance holds the repo lock. Without a repo there
+    /// is nothing to lock; starting tasks fails on its own in that case.
+    fn holds_repo_lock(config: &PollerConfig) -> bool {
+        match &config.target_repo_path {
+            Some(repo) => orchestrator::acquire_repo_lock(&orchestrator::worktrees_dir(
+                repo,
+                config.worktrees_root.as_deref(),
+            )),
+            None => true,
+        }
+    }
 
-        // Get agent prompt (global instructions to combine with task description)
-        let agent_prompt = Self::get_setting(db, "agent_prompt").await;
+    /// Fetch new tasks from ClickUp and queue them, then start queued tasks
+    async fn poll_and_process(ctx: AppContext, config: &PollerConfig) {
+        let db = &ctx.db;
 
-        // Check how many tasks are currently in progress
-        let in_progress_count = orchestrator_tasks::Entity::find()
-            .filter(orchestrator_tasks::Column::Status.eq("in_progress"))
-            .count(db)
-            .await
-            .unwrap_or(0) as usize;
+        if !Self::holds_repo_lock(config) {
+            tracing::debug!("Another instance holds the repo lock, skipping poll");
+            return;
+        }
 
-        let available_slots = parallel_limit.saturating_sub(in_progress_count);
-        if available_slots == 0 {
-            tracing::debug!("No available slots for new tasks (limit: {}, in_progress: {})", parallel_limit, in_progress_count);
+        if CLICKUP_BREAKER.is_open() {
+            tracing::debug!("ClickUp circuit breaker is open, skipping poll");
             return;
         }
 
+        // Get settings
+        let Some(list_id) = config.list_id.clone() else {
+            tracing::debug!("No ClickUp list configured, skipping poll");
+            return;
+        };
+
+        // Optional custom fields overriding the base branch, priority and dependency per task
+        let branch_field_id = config.branch_field_id.clone();
+        let priority_field_id = config.priority_field_id.clone();
+        let depends_field_id = config.depends_field_id.clone();
+        let workdir_field_id = config.workdir_field_id.clone();
+
         // Fetch tasks from ClickUp
-        let client = match ClickUpClient::from_env() {
+        let client = match clickup::client_from_env() {
             Ok(c) => c,
             Err(e) => {
                 tracing::error!("Failed to create ClickUp client: {}", e);
@@ -89,7 +268,30 @@ impl ClickUpPollerInitializer {
             }
         };
 
-        let tasks = match client.get_tasks(&list_id, Some(&trigger_status)).await {
+        // Catch misconfigured statuses before claiming anything
+        let resolved = client
+            .resolve_statuses(&list_id, &[&config.trigger_status, &config.target_status])
+            .await;
+        Self::track(config, &resolved);
+        let resolved = match resolved {
+            Ok(names) => PollerConfig {
+                trigger_status: names[0].clone(),
+                target_status: names[1].clone(),
+                ..config.clone()
+            },
+            Err(e) => {
+                tracing::error!("Invalid ClickUp status configuration: {}", e);
+                return;
+            }
+        };
+        let config = &resolved;
+        let trigger_status = config.trigger_status.clone();
+
+        let tasks = client
+            .get_tasks(&list_id, Some(&trigger_status), &config.task_filter)
+            .await;
+        Self::track(config, &tasks);
+        let tasks = match tasks {
             Ok(t) => t,
             Err(e) => {
                 tracing::error!("Failed to fetch tasks from ClickUp: {}", e);
@@ -99,15 +301,10 @@ impl ClickUpPollerInitializer {
 
         if tasks.is_empty() {
             tracing::debug!("No tasks found with status '{}'", trigger_status);
-            return;
         }
 
-        // Sort by priority (1=urgent first)
-        let mut tasks = tasks;
-        tasks.sort_by_key(|t| priority_to_int(&t.priority).unwrap_or(99));
-
-        // Process up to available_slots tasks
-        for task in tasks.into_iter().take(available_slots) {
+        // Queue every task we haven't seen yet
+        for task in tasks {
             // Check if task already exists in database
             let existing = orchestrator_tasks::Entity::find()
                 .filter(orchestrator_tasks::Column::ClickupTaskId.eq(&task.id))
@@ -126,203 +323,508 @@ impl ClickUpPollerInitializer {
                 Ok(None) => {}
             }
 
-            tracing::info!("Processing new task: {} ({})", task.name, task.id);
-
-            // Create worktree name from task name (sanitize)
-            let worktree_name: String = task
-                .name
-                .chars()
-                .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '-' })
-                .collect::<String>()
-                .to_lowercase();
-
-            // Create a unique branch name for this task
-            let task_branch = format!("task/{}-{}", task.id, worktree_name);
-            let worktree_path = format!("{}/worktrees/{}", target_repo_path, worktree_name);
+            // Base branch comes from the task's custom field when configured
+            let base_branch = branch_field_id
+                .as_deref()
+                .and_then(|field| task.custom_field_value(field))
+                .filter(|branch| {
+                    let valid = orchestrator::is_valid_branch_name(branch);
+                    if !valid {
+                        tracing::warn!(
+                            "Task {} has invalid base branch {:?}, using the dev branch",
+                            task.id,
+                            branch
+                        );
+                    }
+                    valid
+                });
+
+            let depends_on = Self::task_dependency(&task, depends_field_id.as_deref());
+
+            // A task that (transitively) depends on itself could never start
+            let status = match &depends_on {
+                Some(dep) if orchestrator::has_dependency_cycle(db, &task.id, dep).await => {
+                    tracing::error!(
+                        "Task {} has a dependency cycle through {}, marking it failed",
+                        task.id,
+                        dep
+                    );
+                    "failed"
+                }
+                _ => "queued",
+            };
 
-            // Update task status in ClickUp
-            if let Err(e) = client.update_task_status(&task.id, &target_status).await {
-                tracing::error!("Failed to update task status in ClickUp: {}", e);
-                continue;
-            }
+            tracing::info!("Queueing new task: {} ({})", task.name, task.id);
 
-            // Insert task into database
             let now = chrono::Utc::now();
             let new_task = orchestrator_tasks::ActiveModel {
                 clickup_task_id: Set(task.id.clone()),
                 clickup_list_id: Set(task.list.id.clone()),
                 name: Set(task.name.clone()),
                 description: Set(task.description.clone()),
-                priority: Set(priority_to_int(&task.priority)),
-                status: Set("in_progress".to_string()),
-                worktree_path: Set(Some(worktree_path.clone())),
+                priority: Set(task.priority_value(priority_field_id.as_deref())),
+                status: Set(status.to_string()),
+                worktree_path: Set(None),
                 time_spent_ms: Set(0),
-                started_at: Set(Some(now.into())),
+                started_at: Set(None),
                 completed_at: Set(None),
+                tags: Set(task.joined_tags()),
+                depends_on_clickup_id: Set(depends_on),
+                base_branch: Set(base_branch),
+                working_subdir: Set(Self::task_working_subdir(&task, workdir_field_id.as_deref())),
+                agent_type: Set(Self::task_agent_type(&task, config.agent_field_id.as_deref())),
                 created_at: Set(now.into()),
                 updated_at: Set(now.into()),
                 ..Default::default()
             };
 
-            let inserted = match orchestrator_tasks::Entity::insert(new_task)
-                .exec(db)
-                .await
-            {
-                Ok(r) => r,
-                Err(e) => {
-                    tracing::error!("Failed to insert task: {}", e);
-                    continue;
-                }
-            };
-
-            let task_id = inserted.last_insert_id;
-
-            // Ensure worktrees directory exists
-            let worktrees_dir = format!("{}/worktrees", target_repo_path);
-            if let Err(e) = tokio::fs::create_dir_all(&worktrees_dir).await {
-                tracing::error!("Failed to create worktrees directory: {}", e);
-                let _ = orchestrator_tasks::Entity::update_many()
-                    .filter(orchestrator_tasks::Column::Id.eq(task_id))
-                    .col_expr(
-                        orchestrator_tasks::Column::Status,
-                        sea_orm::sea_query::Expr::value("failed"),
-                    )
-                    .exec(db)
-                    .await;
-                continue;
+            match orchestrator_tasks::Entity::insert(new_task).exec(db).await {
+                Ok(inserted) => orchestrator::publish_task_event(
+                    TaskEventKind::Created,
+                    inserted.last_insert_id,
+                    status,
+                ),
+                Err(e) => tracing::error!("Failed to insert task: {}", e),
             }
+        }
 
-            // Fetch latest from remote before creating worktree
-            let fetch_result = tokio::process::Command::new("git")
-                .args(["-C", &target_repo_path, "fetch", "--all"])
-                .output()
-                .await;
+        Self::start_queued_tasks(db, client.into(), config).await;
+    }
+
+    /// Start queued tasks while there are free slots
+    ///
+    /// Tasks are taken in queue order, skipping those whose dependency has not
+    /// completed yet. Up to one start per free slot runs concurrently; a start
+    /// that fails hands its slot to the next queued task. Worktree creation
+    /// stays serialized by the lock in [`git`], so only the ClickUp claim and
+    /// agent spawn overlap.
+    async fn start_queued_tasks(
+        db: &sea_orm::DatabaseConnection,
+        client: Arc<dyn ClickUpApi>,
+        config: &PollerConfig,
+    ) {
+        let parallel_limit = config.parallel_limit;
+
+        // Check how many tasks are currently in progress
+        let in_progress_count = orchestrator_tasks::Entity::find()
+            .filter(orchestrator_tasks::Column::Status.eq("in_progress"))
+            .count(db)
+            .await
+            .unwrap_or(0) as usize;
 
-            if let Err(e) = fetch_result {
-                tracing::warn!("Failed to fetch from remote: {}", e);
-                // Continue anyway, not fatal
+        let mut available_slots = parallel_limit.saturating_sub(in_progress_count);
+        if available_slots == 0 {
+            tracing::debug!("No available slots for new tasks (limit: {}, in_progress: {})", parallel_limit, in_progress_count);
+            return;
+        }
+
+        let queued = match orchestrator::queued_tasks(db).await {
+            Ok(tasks) => tasks,
+            Err(e) => {
+                tracing::error!("Failed to load queued tasks: {}", e);
+                return;
             }
+        };
 
-            // Create git worktree with a new branch based on dev_branch
-            let worktree_result = tokio::process::Command::new("git")
-                .args([
-                    "-C",
-                    &target_repo_path,
-                    "worktree",
-                    "add",
-                    "-b",
-                    &task_branch,
-                    &worktree_path,
-                    &dev_branch,
-                ])
-                .output()
-                .await;
+        let config = Arc::new(config.clone());
+        let mut queued = queued.into_iter();
+        let mut starts = JoinSet::new();
+
+        loop {
+            while starts.len() < available_slots && !CLICKUP_BREAKER.is_open() {
+                let Some(task) = Self::next_ready_task(db, &mut queued).await else {
+                    break;
+                };
+                let db = db.clone();
+                let client = client.clone();
+                let config = config.clone();
+                starts.spawn(async move {
+                    Self::start_task(&db, client.as_ref(), &config, task).await
+                });
+            }
 
-            match worktree_result {
-                Err(e) => {
-                    tracing::error!("Failed to run git worktree command: {}", e);
-                    let _ = orchestrator_tasks::Entity::update_many()
-                        .filter(orchestrator_tasks::Column::Id.eq(task_id))
-                        .col_expr(
-                            orchestrator_tasks::Column::Status,
-                            sea_orm::sea_query::Expr::value("failed"),
-                        )
-                        .exec(db)
-                        .await;
-                    continue;
-                }
-                Ok(output) if !output.status.success() => {
-                    let stderr = String::from_utf8_lossy(&output.stderr);
-                    tracing::error!("Git worktree failed: {}", stderr);
-                    let _ = orchestrator_tasks::Entity::update_many()
-                        .filter(orchestrator_tasks::Column::Id.eq(task_id))
-                        .col_expr(
-                            orchestrator_tasks::Column::Status,
-                            sea_orm::sea_query::Expr::value("failed"),
-                        )
-                        .exec(db)
-                        .await;
-                    continue;
+            match starts.join_next().await {
+                Some(Ok(true)) => available_slots -= 1,
+                Some(Ok(false)) => {}
+                Some(Err(e)) => {
+                    // The task's own failure handling didn't run, so keep its slot taken
+                    tracing::error!("Task start panicked: {}", e);
+                    available_slots -= 1;
                 }
-                Ok(output) => {
-                    let stdout = String::from_utf8_lossy(&output.stdout);
-                    tracing::info!("Created worktree at {} on branch {}: {}", worktree_path, task_branch, stdout.trim());
+                None => break,
+            }
+        }
+    }
+
+    /// Take the next queued task whose dependency, if any, has completed
+    ///
+    /// Tasks whose dependency failed or was abandoned are marked failed, since
+    /// they would otherwise wait forever.
+    async fn next_ready_task(
+        db: &sea_orm::DatabaseConnection,
+        queued: &mut impl Iterator<Item = orchestrator_tasks::Model>,
+    ) -> Option<orchestrator_tasks::Model> {
+        for task in queued.by_ref() {
+            if let Some(dep) = &task.depends_on_clickup_id {
+                match orchestrator::dependency_state(db, dep).await {
+                    DependencyState::Completed => {}
+                    DependencyState::Pending => {
+                        tracing::debug!("Task {} is waiting for dependency {}", task.id, dep);
+                        continue;
+                    }
+                    DependencyState::Blocked(status) => {
+                        Self::fail_blocked_task(db, &task, dep, &status).await;
+                        continue;
+                    }
                 }
             }
+            return Some(task);
+        }
+        None
+    }
+
+    /// Mark a queued task failed because its dependency ended up `status`
+    async fn fail_blocked_task(
+        db: &sea_orm::DatabaseConnection,
+        task: &orchestrator_tasks::Model,
+        dependency: &str,
+        status: &str,
+    ) {
+        let updated = orchestrator_tasks::Entity::update_many()
+            .filter(orchestrator_tasks::Column::Id.eq(task.id))
+            .filter(orchestrator_tasks::Column::Status.eq("queued"))
+            .col_expr(
+                orchestrator_tasks::Column::Status,
+                sea_orm::sea_query::Expr::value("failed"),
+            )
+            .col_expr(
+                orchestrator_tasks::Column::UpdatedAt,
+                sea_orm::sea_query::Expr::value(chrono::Utc::now()),
+            )
+            .exec(db)
+            .await;
+        if !updated.is_ok_and(|result| result.rows_affected > 0) {
+            return;
+        }
+
+        tracing::warn!(
+            "Task {} is blocked by {} dependency {}, marked it failed",
+            task.id,
+            status,
+            dependency
+        );
+        orchestrator::publish_task_event(TaskEventKind::StatusChanged, task.id, "failed");
+        orchestrator_task_logs::Entity::log_task_event(
+            db,
+            task.id,
+            "system",
+            &format!(
+                "Dependency {} is {}, so this task can't start; rerun it once the dependency has completed",
+                dependency, status
+            ),
+        )
+        .await;
+    }
+
+    /// Claim a queued task in ClickUp, create its worktree and spawn the agent
+    ///
+    /// Returns whether the agent was started. Once the card has been claimed,
+    /// any failure marks the task failed and moves the card back to the
+    /// trigger status so it doesn't sit in the target status unattended.
+    async fn start_task(
+        db: &sea_orm::DatabaseConnection,
+        client: &dyn ClickUpApi,
+        config: &PollerConfig,
+        task: orchestrator_tasks::Model,
+    ) -> bool {
+        if config.target_repo_path.is_none() {
+            tracing::debug!("No target repo path configured, not starting tasks");
+            return false;
+        }
+
+        let task_id = task.id;
+        if PROCESS_MANAGER.is_running(task_id) {
+            tracing::warn!("Task {} already has a running agent, not starting it", task_id);
+            return false;
+        }
+        tracing::info!("Starting task: {} ({})", task.name, task.clickup_task_id);
+
+        // Update task status in ClickUp
+        let claimed = client
+            .update_task_status(&task.clickup_task_id, &config.target_status)
+            .await;
+        Self::track(config, &claimed);
+        if let Err(e) = claimed {
+            tracing::error!("Failed to update task status in ClickUp: {}", e);
+            return false;
+        }
 
-            // Verify the worktree directory exists before spawning
-            if !std::path::Path::new(&worktree_path).exists() {
-                tracing::error!("Worktree directory does not exist after creation: {}", worktree_path);
-                let _ = orchestrator_tasks::Entity::update_many()
-                    .filter(orchestrator_tasks::Column::Id.eq(task_id))
-                    .col_expr(
-                        orchestrator_tasks::Column::Status,
-                        sea_orm::sea_query::Expr::value("failed"),
-                    )
-                    .exec(db)
-                    .await;
-                continue;
+        match Self::spawn_claimed_task(db, config, task.clone()).await {
+            Ok(pid) => {
+                tracing::info!("Spawned CLI agent for task {} (PID: {})", task_id, pid);
+                true
             }
+            Err(reason) => {
+                Self::fail_claimed_task(db, client, config, &task, &reason).await;
+                false
+            }
+        }
+    }
 
-            // Build prompt from task description combined with agent prompt
-            let task_description = task
-                .description
-                .clone()
-                .unwrap_or_else(|| format!("Complete task: {}", task.name));
-
-            // Combine task description with global agent prompt if configured
-            let prompt = match &agent_prompt {
-                Some(global_prompt) if !global_prompt.is_empty() => {
-                    format!(
-                        "## Task\n{}\n\n## Instructions\n{}",
-                        task_description, global_prompt
-                    )
-                }
-                _ => task_description,
-            };
+    /// Mark a claimed task failed and revert its ClickUp card to the trigger status
+    async fn fail_claimed_task(
+        db: &sea_orm::DatabaseConnection,
+        client: &dyn ClickUpApi,
+        config: &PollerConfig,
+        task: &orchestrator_tasks::Model,
+        reason: &str,
+    ) {
+        tracing::error!("Failed to start task {}: {}", task.id, reason);
+
+        let _ = orchestrator_tasks::Entity::update_many()
+            .filter(orchestrator_tasks::Column::Id.eq(task.id))
+            .col_expr(
+                orchestrator_tasks::Column::Status,
+                sea_orm::sea_query::Expr::value("failed"),
+            )
+            .col_expr(
+                orchestrator_tasks::Column::UpdatedAt,
+                sea_orm::sea_query::Expr::value(chrono::Utc::now()),
+            )
+            .exec(db)
+            .await;
+        orchestrator::publish_task_event(TaskEventKind::StatusChanged, task.id, "failed");
+
+        orchestrator_task_logs::Entity::log_task_event(
+            db,
+            task.id,
+            "system",
+            &format!("Failed to start: {}", reason),
+        )
+        .await;
+
+        let reverted = client
+            .update_task_status(&task.clickup_task_id, &config.trigger_status)
+            .await;
+        Self::track(config, &reverted);
+        let revert_message = match reverted {
+            Ok(_) => {
+                tracing::info!(
+                    "Reverted ClickUp task {} to '{}'",
+                    task.clickup_task_id,
+                    config.trigger_status
+                );
+                format!("Reverted ClickUp status to '{}'", config.trigger_status)
+            }
+            Err(e) => {
+                tracing::error!(
+                    "Failed to revert ClickUp task {} to '{}': {}",
+                    task.clickup_task_id,
+                    config.trigger_status,
+                    e
+                );
+                format!(
+                    "Failed to revert ClickUp status to '{}': {}",
+                    config.trigger_status, e
+                )
+            }
+        };
 
-            // Spawn CLI agent
-            match PROCESS_MANAGER
-                .spawn_agent(task_id, &prompt, &worktree_path)
-                .await
-            {
-                Ok(pid) => {
-                    tracing::info!(
-                        "Spawned CLI agent for task {} (PID: {})",
-                        task_id,
-                        pid
-                    );
+        orchestrator_task_logs::Entity::log_task_event(db, task.id, "system", &revert_message)
+            .await;
+    }
 
-                    // Insert process session record
-                    let session = crate::models::_entities::process_sessions::ActiveModel {
-                        task_id: Set(task_id),
-                        pid: Set(Some(pid as i32)),
-                        started_at: Set(chrono::Utc::now().into()),
-                        ended_at: Set(None),
-                        exit_code: Set(None),
-                        created_at: Set(chrono::Utc::now().into()),
-                        updated_at: Set(chrono::Utc::now().into()),
-                        ..Default::default()
-                    };
-
-                    let _ = crate::models::_entities::process_sessions::Entity::insert(session)
-                        .exec(db)
-                        .await;
-                }
-                Err(e) => {
-                    tracing::error!("Failed to spawn CLI agent: {}", e);
-                    // Update task status to failed
-                    let _ = orchestrator_tasks::Entity::update_many()
-                        .filter(orchestrator_tasks::Column::Id.eq(task_id))
-                        .col_expr(
-                            orchestrator_tasks::Column::Status,
-                            sea_orm::sea_query::Expr::value("failed"),
-                        )
-                        .exec(db)
-                        .await;
-                }
+    /// Create the worktree for a claimed task and spawn its agent
+    ///
+    /// Returns the agent's PID, or the reason the task could not be started.
+    async fn spawn_claimed_task(
+        db: &sea_orm::DatabaseConnection,
+        config: &PollerConfig,
+        task: orchestrator_tasks::Model,
+    ) -> std::result::Result<u32, String> {
+        let target_repo_path = config
+            .target_repo_path
+            .clone()
+            .ok_or("No target repo path configured")?;
+
+        let worktrees_dir = orchestrator::worktrees_dir(&target_repo_path, config.worktrees_root.as_deref());
+        let git_ssh_command = config.git_ssh_command.clone();
+
+        // Agent that runs this task
+        let agent = orchestrator::task_agent_type(db, &task).await;
+
+        let task_id = task.id;
+
+        // Create worktree name from task name (sanitize)
+        let worktree_name = orchestrator::worktree_name(&task.name);
+
+        // Create a unique branch name for this task from `branch_template`
+        let mut task_branch = orchestrator::render_branch_name(&config.branch_template, &task)?;
+        let worktree_path = format!("{}/{}", worktrees_dir, worktree_name);
+
+        // Base branch was taken from the task's custom field when it was queued
+        let base_branch = task
+            .base_branch
+            .clone()
+            .unwrap_or_else(|| config.dev_branch.clone());
+
+        let now = chrono::Utc::now();
+        let mut active: orchestrator_tasks::ActiveModel = task.clone().into();
+        active.status = Set("in_progress".to_string());
+        active.worktree_path = Set(Some(worktree_path.clone()));
+        active.started_at = Set(Some(now.into()));
+        active.progress = Set(None);
+        active.updated_at = Set(now.into());
+        active
+            .update(db)
+            .await
+            .map_err(|e| format!("Failed to mark task in progress: {}", e))?;
+        orchestrator::publish_task_event(TaskEventKind::StatusChanged, task.id, "in_progress");
+
+        // Ensure worktrees directory exists
+        tokio::fs::create_dir_all(&worktrees_dir)
+            .await
+            .map_err(|e| format!("Failed to create worktrees directory: {}", e))?;
+
+        if let Some(min_mb) = config.min_free_disk_mb {
+            orchestrator::ensure_free_disk(&worktrees_dir, min_mb).await?;
+        }
+
+        // Fetch latest from remote before creating worktree
+        let fetch_result = git::command(git_ssh_command.as_deref())
+            .args(["-C", &target_repo_path, "fetch", "--all"])
+            .output()
+            .await;
+
+        if let Err(e) = fetch_result {
+            tracing::warn!("Failed to fetch from remote: {}", e);
+            // Continue anyway, not fatal
+        }
+
+        // A re-opened task may find its branch left over from an earlier run
+        let branch_exists = git::branch_exists(&target_repo_path, &task_branch).await;
+        let reuse_branch = branch_exists && config.reuse_existing_branch;
+        if branch_exists && !reuse_branch {
+            task_branch = Self::free_branch_name(&target_repo_path, &task_branch).await?;
+        }
+
+        // Create git worktree with a new branch based on the base branch, or
+        // check out the existing one when reusing it
+        let output = if reuse_branch {
+            orchestrator_task_logs::Entity::log_task_event(
+                db,
+                task_id,
+                "system",
+                &format!("Reusing existing branch {}", task_branch),
+            )
+            .await;
+            git::checkout_worktree(
+                &target_repo_path,
+                &task_branch,
+                &worktree_path,
+                git_ssh_command.as_deref(),
+            )
+            .await
+        } else {
+            git::add_worktree(
+                &target_repo_path,
+                &task_branch,
+                &worktree_path,
+                &base_branch,
+                git_ssh_command.as_deref(),
+            )
+            .await
+        }
+        .map_err(|e| format!("Failed to run git worktree command: {}", e))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("Git worktree failed: {}", stderr.trim()));
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        tracing::info!("Created worktree at {} on branch {}: {}", worktree_path, task_branch, stdout.trim());
+
+        // Verify the worktree directory exists before spawning
+        if !std::path::Path::new(&worktree_path).exists() {
+            return Err(format!(
+                "Worktree directory does not exist after creation: {}",
+                worktree_path
+            ));
+        }
+
+        // Scope the agent to the task's subdirectory in monorepos
+        let working_dir =
+            orchestrator::agent_working_dir(&worktree_path, task.working_subdir.as_deref())?;
+
+        // Build prompt from task description combined with agent prompt
+        let prompt = orchestrator::compose_task_prompt(db, &task).await;
+        let env_file = orchestrator::task_env_file(&worktree_path, task.env_file.as_deref());
+        let output_file =
+            orchestrator::task_output_file(&worktree_path, config.output_file_path.as_deref());
+
+        // Spawn CLI agent
+        let spawned = PROCESS_MANAGER
+            .spawn_agent(
+                task_id,
+                &agent,
+                &prompt,
+                &working_dir,
+                SpawnOptions {
+                    resume_session: None,
+                    skip_permissions: config.claude_skip_permissions,
+                    retries: config.spawn_retries,
+                    idle_timeout: config.idle_timeout,
+                    sandbox: config.sandbox_command.as_deref(),
+                    env_file: env_file.as_deref(),
+                    git_author: Some(&config.git_author),
+                    output_file: output_file.as_deref(),
+                },
+            )
+            .await;
+        let pid = match spawned {
+            Ok(pid) => pid,
+            // Checked before claiming, so another agent was started meanwhile
+            Err(e @ ProcessError::AlreadyRunning(_)) => {
+                return Err(format!("Another agent was started for the task meanwhile: {}", e));
             }
+            Err(e) => return Err(format!("Failed to spawn CLI agent: {}", e)),
+        };
+
+        // Insert process session record
+        let session = crate::models::_entities::process_sessions::ActiveModel {
+            task_id: Set(task_id),
+            pid: Set(Some(pid as i32)),
+            started_at: Set(chrono::Utc::now().into()),
+            ended_at: Set(None),
+            exit_code: Set(None),
+            created_at: Set(chrono::Utc::now().into()),
+            updated_at: Set(chrono::Utc::now().into()),
+            ..Default::default()
+        };
+
+        let _ = crate::models::_entities::process_sessions::Entity::insert(session)
+            .exec(db)
+            .await;
+
+        Ok(pid)
+    }
+
+    /// Start queued tasks without polling ClickUp, e.g. after a task finished
+    async fn schedule_queued(ctx: AppContext, config: &PollerConfig) {
+        if !Self::holds_repo_lock(config) {
+            return;
         }
+
+        let client = match clickup::client_from_env() {
+            Ok(c) => c,
+            Err(e) => {
+                tracing::error!("Failed to create ClickUp client: {}", e);
+                return;
+            }
+        };
+
+        Self::start_queued_tasks(&ctx.db, client.into(), config).await;
     }
 }
 
@@ -337,10 +839,25 @@ impl Initializer for ClickUpPollerInitializer {
         let ctx_clone = ctx.clone();
         tokio::spawn(async move {
             let mut interval = interval(Duration::from_secs(30));
+            let mut settings_rx = settings::Entity::subscribe_changes();
+            let mut config = PollerConfig::load(&ctx_clone.db).await;
 
             loop {
-                interval.tick().await;
-                Self::poll_and_process(ctx_clone.clone()).await;
+                tokio::select! {
+                    _ = interval.tick() => {
+                        Self::poll_and_process(ctx_clone.clone(), &config).await;
+                        metrics::inc(&POLLER_CYCLES_TOTAL);
+                    }
+                    _ = orchestrator::schedule_requested() => {
+                        Self::schedule_queued(ctx_clone.clone(), &config).await;
+                    }
+                    changed = settings_rx.recv() => {
+                        if let Ok(keys) = changed {
+                            tracing::debug!("Settings changed ({:?}), reloading poller config", keys);
+                        }
+                        config = PollerConfig::load(&ctx_clone.db).await;
+                    }
+                }
             }
         });
 
@@ -1 +1,6 @@
 pub mod clickup_poller;
+pub mod process_monitor;
+pub mod progress_tracker;
+pub mod repo_fetcher;
+pub mod task_janitor;
+pub mod task_log_listener;
@@ -0,0 +1,582 @@
+//! Process Monitor Initializer
+//!
+//! Listens for agent process exits and records the outcome on the task.
+
+use async_trait::async_trait;
+use axum::Router;
+use loco_rs::{
+    app::{AppContext, Initializer},
+    Result,
+};
+use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, Set};
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+use crate::models::_entities::{orchestrator_task_logs, orchestrator_tasks, process_sessions, settings};
+use crate::services::git;
+use crate::services::metrics::{self, AGENT_EXITS_FAILURE_TOTAL, AGENT_EXITS_SUCCESS_TOTAL};
+use crate::services::orchestrator::{self, TaskEventKind};
+use crate::services::process_manager::{
+    parse_agent_session_id, ExitReason, ProcessExit, PROCESS_MANAGER,
+};
+use crate::services::redaction;
+use crate::services::usage::UsagePatterns;
+
+/// Default cap on automatic restarts of a failed task
+const DEFAULT_AUTO_RESTART_MAX_ATTEMPTS: i32 = 3;
+
+/// Default wait before the first automatic restart
+const DEFAULT_AUTO_RESTART_BACKOFF: Duration = Duration::from_secs(30);
+
+pub struct ProcessMonitorInitializer;
+
+/// When a finished task's worktree is removed (`cleanup_worktree_on`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CleanupPolicy {
+    /// Keep worktrees until the task is deleted (default)
+    Never,
+    /// Remove after a successful run
+    Complete,
+    /// Remove after a successful run whose branch is pushed to its upstream
+    CompleteAfterPush,
+}
+
+impl ProcessMonitorInitializer {
+    /// Decide the outcome from the `failure_marker` / `success_marker` settings
+    ///
+    /// Both are regexes matched against the output tail. The failure marker wins
+    /// when both match. Returns the outcome and the setting that decided it, or
+    /// `None` when neither is set or matches, leaving the exit code to decide.
+    async fn marker_outcome(
+        db: &sea_orm::DatabaseConnection,
+        output_tail: &[String],
+    ) -> Option<(bool, &'static str)> {
+        for (key, outcome) in [("failure_marker", false), ("success_marker", true)] {
+            let Some(pattern) = settings::Entity::get_value(db, key).await else {
+                continue;
+            };
+            match regex::Regex::new(&pattern) {
+                Ok(re) if output_tail.iter().any(|line| re.is_match(line)) => {
+                    return Some((outcome, key));
+                }
+                Ok(_) => {}
+                Err(e) => tracing::warn!("Invalid {} pattern, ignoring: {}", key, e),
+            }
+        }
+        None
+    }
+
+    /// Whether the output tail matches the `partial_marker` setting
+    ///
+    /// Agents print it when they finished only part of the work; the task then
+    /// ends as `needs_review` instead of `completed`.
+    async fn partial_marker_matched(db: &sea_orm::DatabaseConnection, output_tail: &[String]) -> bool {
+        let Some(pattern) = settings::Entity::get_value(db, "partial_marker").await else {
+            return false;
+        };
+        match regex::Regex::new(&pattern) {
+            Ok(re) => output_tail.iter().any(|line| re.is_match(line)),
+            Err(e) => {
+                tracing::warn!("Invalid partial_marker pattern, ignoring: {}", e);
+                false
+            }
+        }
+    }
+
+    /// Run the `post_task_command` setting in the worktree, if set
+    ///
+    /// Output is stored as `posthook` task log events. Returns false when the
+    /// command fails, exits non-zero or exceeds `post_task_timeout_secs`.
+    async fn run_post_task_command(
+        db: &sea_orm::DatabaseConnection,
+        task_id: i32,
+        worktree_path: &str,
+    ) -> bool {
+        let Some(command) = settings::Entity::get_value(db, "post_task_command").await else {
+            return true;
+        };
+
+        let timeout = orchestrator::post_task_timeout(db).await;
+
+        // Keep the janitor from treating the task as abandoned while the hook runs
+        let _ = orchestrator_tasks::Entity::update_many()
+            .filter(orchestrator_tasks::Column::Id.eq(task_id))
+            .col_expr(
+                orchestrator_tasks::Column::UpdatedAt,
+                sea_orm::sea_query::Expr::value(chrono::Utc::now()),
+            )
+            .exec(db)
+            .await;
+
+        orchestrator_task_logs::Entity::log_task_event(
+            db,
+            task_id,
+            "posthook",
+            &format!("Running post-task command: {}", command),
+        )
+        .await;
+
+        let output = tokio::time::timeout(
+            timeout,
+            tokio::process::Command::new("sh")
+                .arg("-c")
+                .arg(&command)
+                .current_dir(worktree_path)
+                .kill_on_drop(true)
+                .output(),
+        )
+        .await;
+
+        let (passed, summary) = match output {
+            Ok(Ok(output)) => {
+                let redactor = redaction::Redactor::load(db).await;
+                for line in String::from_utf8_lossy(&output.stdout)
+                    .lines()
+                    .chain(String::from_utf8_lossy(&output.stderr).lines())
+                {
+                    orchestrator_task_logs::Entity::log_task_event(
+                        db,
+                        task_id,
+                        "posthook",
+                        &redactor.redact(line),
+                    )
+                    .await;
+                }
+                let code = output.status.code().unwrap_or(-1);
+                (
+                    output.status.success(),
+                    format!("Post-task command exited with code {}", code),
+                )
+            }
+            Ok(Err(e)) => (false, format!("Failed to run post-task command: {}", e)),
+            Err(_) => (
+                false,
+                format!("Post-task command timed out after {}s", timeout.as_secs()),
+            ),
+        };
+
+        tracing::info!("Task {}: {}", task_id, summary);
+        orchestrator_task_logs::Entity::log_task_event(db, task_id, "posthook", &summary).await;
+
+        passed
+    }
+
+    async fn cleanup_policy(db: &sea_orm::DatabaseConnection) -> CleanupPolicy {
+        match settings::Entity::get_value(db, "cleanup_worktree_on").await.as_deref() {
+            None | Some("never") => CleanupPolicy::Never,
+            Some("complete") => CleanupPolicy::Complete,
+            Some("complete_after_push") => CleanupPolicy::CompleteAfterPush,
+            Some(other) => {
+                tracing::warn!("Unknown cleanup_worktree_on '{}', keeping worktrees", other);
+                CleanupPolicy::Never
+            }
+        }
+    }
+
+    /// Remove a completed task's worktree according to `cleanup_worktree_on`
+    ///
+    /// Failed tasks never get here, their worktree is kept for inspection.
+    /// Removal failures are logged on the task and otherwise ignored.
+    async fn cleanup_worktree(db: &sea_orm::DatabaseConnection, task: &orchestrator_tasks::Model) {
+        let Some(worktree_path) = &task.worktree_path else {
+            return;
+        };
+        let policy = Self::cleanup_policy(db).await;
+        if policy == CleanupPolicy::Never || !std::path::Path::new(worktree_path).exists() {
+            return;
+        }
+
+        if policy == CleanupPolicy::CompleteAfterPush && !git::is_pushed(worktree_path).await {
+            orchestrator_task_logs::Entity::log_task_event(
+                db,
+                task.id,
+                "system",
+                "Branch is not pushed to its upstream, keeping the worktree",
+            )
+            .await;
+            return;
+        }
+
+        let repo_path = settings::Entity::get_value(db, "target_repo_path")
+            .await
+            .unwrap_or_else(|| worktree_path.clone());
+        let message = match git::remove_worktree(&repo_path, worktree_path).await {
+            Ok(()) => format!("Removed worktree {}", worktree_path),
+            Err(e) => {
+                tracing::warn!("Failed to remove worktree of task {}: {}", task.id, e);
+                format!("Failed to remove worktree {}: {}", worktree_path, e)
+            }
+        };
+        orchestrator_task_logs::Entity::log_task_event(db, task.id, "system", &message).await;
+    }
+
+    /// Schedule an automatic restart of a task that just failed
+    ///
+    /// Enabled by `auto_restart_failed` = "true" and capped by
+    /// `auto_restart_max_attempts`. The first restart waits
+    /// `auto_restart_backoff_secs`, each further one twice as long. Failures
+    /// matched by `failure_marker` are taken as permanent and not retried.
+    async fn schedule_auto_restart(
+        db: &sea_orm::DatabaseConnection,
+        task: &orchestrator_tasks::Model,
+        permanent: bool,
+    ) {
+        let enabled = settings::Entity::get_value(db, "auto_restart_failed")
+            .await
+            .is_some_and(|v| v == "true");
+        if !enabled {
+            return;
+        }
+
+        if permanent {
+            orchestrator_task_logs::Entity::log_task_event(
+                db,
+                task.id,
+                "system",
+                "Not restarting automatically: output matched failure_marker",
+            )
+            .await;
+            return;
+        }
+
+        let max_attempts = settings::Entity::get_value(db, "auto_restart_max_attempts")
+            .await
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_AUTO_RESTART_MAX_ATTEMPTS);
+        let backoff = settings::Entity::get_value(db, "auto_restart_backoff_secs")
+            .await
+            .and_then(|v| v.parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_AUTO_RESTART_BACKOFF);
+        let attempts = task.restart_count;
+
+        let Some(delay) = orchestrator::auto_restart_delay(attempts, max_attempts, backoff) else {
+            orchestrator_task_logs::Entity::log_task_event(
+                db,
+                task.id,
+                "system",
+                &format!(
+                    "Not restarting automatically: reached auto_restart_max_attempts ({})",
+                    max_attempts
+                ),
+            )
+            .await;
+            return;
+        };
+
+        orchestrator_task_logs::Entity::log_task_event(
+            db,
+            task.id,
+            "system",
+            &format!(
+                "Restarting automatically in {}s (attempt {}/{})",
+                delay.as_secs(),
+                attempts + 1,
+                max_attempts
+            ),
+        )
+        .await;
+
+        let db = db.clone();
+        let task_id = task.id;
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            Self::auto_restart(&db, task_id).await;
+        });
+    }
+
+    /// Respawn a failed task in its existing worktree, unless it changed meanwhile
+    async fn auto_restart(db: &sea_orm::DatabaseConnection, task_id: i32) {
+        let task = match orchestrator_tasks::Entity::find_by_id(task_id).one(db).await {
+            Ok(Some(task)) => task,
+            Ok(None) => return,
+            Err(e) => {
+                tracing::error!("Failed to load task {} for restart: {}", task_id, e);
+                return;
+            }
+        };
+
+        let skipped = if task.status != "failed" || PROCESS_MANAGER.is_running(task_id) {
+            Some(format!("task is now {}", task.status))
+        } else if !task
+            .worktree_path
+            .as_deref()
+            .is_some_and(|p| std::path::Path::new(p).exists())
+        {
+            Some("its worktree no longer exists".to_string())
+        } else {
+            None
+        };
+        if let Some(reason) = skipped {
+            orchestrator_task_logs::Entity::log_task_event(
+                db,
+                task_id,
+                "system",
+                &format!("Automatic restart skipped: {}", reason),
+            )
+            .await;
+            return;
+        }
+        let worktree_path = task.worktree_path.clone().unwrap_or_default();
+        let attempt = task.restart_count + 1;
+
+        let prompt = orchestrator::compose_task_prompt(db, &task).await;
+        let spawned =
+            orchestrator::spawn_in_worktree(db, task, &prompt, &worktree_path, attempt).await;
+        let message = match spawned {
+            Ok(_) => format!("Restarted automatically (attempt {})", attempt),
+            Err(e) => format!("Automatic restart failed: {}", e),
+        };
+        tracing::info!("Task {}: {}", task_id, message);
+        orchestrator_task_logs::Entity::log_task_event(db, task_id, "system", &message).await;
+    }
+
+    /// Record a process exit: close the session and finalize the task
+    pub async fn handle_process_exit(db: &sea_orm::DatabaseConnection, exit: ProcessExit) {
+        let now = chrono::Utc::now();
+
+        metrics::inc(if exit.exit_code == 0 {
+            &AGENT_EXITS_SUCCESS_TOTAL
+        } else {
+            &AGENT_EXITS_FAILURE_TOTAL
+        });
+
+        // Close the open process session
+        let _ = process_sessions::Entity::update_many()
+            .filter(process_sessions::Column::TaskId.eq(exit.task_id))
+            .filter(process_sessions::Column::EndedAt.is_null())
+            .col_expr(
+                process_sessions::Column::EndedAt,
+                sea_orm::sea_query::Expr::value(now),
+            )
+            .col_expr(
+                process_sessions::Column::ExitCode,
+                sea_orm::sea_query::Expr::value(exit.exit_code),
+            )
+            .exec(db)
+            .await;
+
+        let task = match orchestrator_tasks::Entity::find_by_id(exit.task_id).one(db).await {
+            Ok(Some(task)) => task,
+            Ok(None) => {
+                tracing::warn!("Process exited for unknown task {}", exit.task_id);
+                return;
+            }
+            Err(e) => {
+                tracing::error!("Failed to load task {}: {}", exit.task_id, e);
+                return;
+            }
+        };
+
+        let elapsed_ms = task
+            .started_at
+            .map(|started| (now - started.with_timezone(&chrono::Utc)).num_milliseconds())
+            .unwrap_or(0)
+            .clamp(0, i32::MAX as i64) as i32;
+
+        let usage = UsagePatterns::load(db).await.parse(&exit.output_tail);
+
+        // Processes killed on request are finished by whoever killed them
+        let finishing = task.status == "in_progress" && exit.reason != ExitReason::Killed;
+
+        let idle_killed = exit.reason == ExitReason::IdleTimeout;
+        if idle_killed {
+            orchestrator_task_logs::Entity::log_task_event(
+                db,
+                task.id,
+                "system",
+                "Agent produced no output within idle_timeout_secs and was killed",
+            )
+            .await;
+        }
+
+        // Output markers, when configured and matched, override the exit code
+        let mut succeeded = exit.exit_code == 0 && !idle_killed;
+        let mut failure_marked = false;
+        if finishing && !idle_killed {
+            if let Some((marker_succeeded, key)) = Self::marker_outcome(db, &exit.output_tail).await {
+                if marker_succeeded != succeeded {
+                    orchestrator_task_logs::Entity::log_task_event(
+                        db,
+                        task.id,
+                        "system",
+                        &format!(
+                            "Output matched {}, overriding exit code {}",
+                            key, exit.exit_code
+                        ),
+                    )
+                    .await;
+                }
+                succeeded = marker_succeeded;
+                failure_marked = !marker_succeeded;
+            }
+        }
+
+        // A successful run must also pass the post-task command, if configured
+        if succeeded && finishing {
+            if let Some(worktree_path) = &task.worktree_path {
+                succeeded = Self::run_post_task_command(db, task.id, worktree_path).await;
+            }
+        }
+
+        // Partial work still needs a human; the ClickUp card stays in target_status
+        let needs_review = succeeded
+            && finishing
+            && Self::partial_marker_matched(db, &exit.output_tail).await;
+        if needs_review {
+            orchestrator_task_logs::Entity::log_task_event(
+                db,
+                task.id,
+                "system",
+                "Output matched partial_marker, marking task for review",
+            )
+            .await;
+        }
+
+        let mut active: orchestrator_tasks::ActiveModel = task.clone().into();
+        if task.status == "in_progress" {
+            active.time_spent_ms = Set(task.time_spent_ms.saturating_add(elapsed_ms));
+        }
+        if usage.input_tokens.is_some() {
+            active.input_tokens = Set(usage.input_tokens);
+        }
+        if usage.output_tokens.is_some() {
+            active.output_tokens = Set(usage.output_tokens);
+        }
+        if usage.estimated_cost.is_some() {
+            active.estimated_cost = Set(usage.estimated_cost);
+        }
+        if let Some(session_id) = parse_agent_session_id(&exit.output_tail) {
+            active.agent_session_id = Set(Some(session_id));
+        }
+        active.updated_at = Set(now.into());
+
+        let task = match active.update(db).await {
+            Ok(updated) => updated,
+            Err(e) => {
+                tracing::error!("Failed to update task {}: {}", exit.task_id, e);
+                task
+            }
+        };
+
+        if finishing {
+            let status = if needs_review {
+                "needs_review"
+            } else if succeeded {
+                "completed"
+            } else {
+                "failed"
+            };
+
+            // Only move tasks that are still in progress, so a stop that lands
+            // while this exit is being handled is not overwritten
+            let mut transition = orchestrator_tasks::Entity::update_many()
+                .filter(orchestrator_tasks::Column::Id.eq(task.id))
+                .filter(orchestrator_tasks::Column::Status.eq("in_progress"))
+                .col_expr(
+                    orchestrator_tasks::Column::Status,
+                    sea_orm::sea_query::Expr::value(status),
+                )
+                .col_expr(
+                    orchestrator_tasks::Column::UpdatedAt,
+                    sea_orm::sea_query::Expr::value(now),
+                );
+            if succeeded {
+                transition = transition
+                    .col_expr(
+                        orchestrator_tasks::Column::CompletedAt,
+                        sea_orm::sea_query::Expr::value(now),
+                    )
+                    .col_expr(
+                        orchestrator_tasks::Column::RestartCount,
+                        sea_orm::sea_query::Expr::value(0),
+                    );
+            }
+
+            match transition.exec(db).await {
+                Ok(result) if result.rows_affected > 0 => {
+                    tracing::info!(
+                        "Task {} exited with code {} ({})",
+                        task.id,
+                        exit.exit_code,
+                        status
+                    );
+                    orchestrator::publish_task_event(TaskEventKind::Completed, task.id, status);
+                    if status == "completed" {
+                        Self::cleanup_worktree(db, &task).await;
+                    } else if status == "failed" {
+                        Self::schedule_auto_restart(db, &task, failure_marked).await;
+                    }
+                }
+                Ok(_) => tracing::info!(
+                    "Task {} left in_progress before its exit was recorded, keeping its status",
+                    task.id
+                ),
+                Err(e) => tracing::error!("Failed to update task {}: {}", task.id, e),
+            }
+        } else {
+            tracing::info!(
+                "Task {} exited with code {} ({:?}, status {})",
+                task.id,
+                exit.exit_code,
+                exit.reason,
+                task.status
+            );
+        }
+
+        // A slot freed up and waiting dependents may now be able to start
+        orchestrator::request_schedule();
+    }
+}
+
+#[async_trait]
+impl Initializer for ProcessMonitorInitializer {
+    fn name(&self) -> String {
+        "process-monitor".to_string()
+    }
+
+    async fn after_routes(&self, router: Router, ctx: &AppContext) -> Result<Router> {
+        let mut exit_rx = PROCESS_MANAGER.subscribe_exits();
+        let ctx_clone = ctx.clone();
+
+        tokio::spawn(async move {
+            loop {
+                match exit_rx.recv().await {
+                    Ok(exit) => {
+                        // Handle exits concurrently so a slow post-task command doesn't hold up others
+                        let db = ctx_clone.db.clone();
+                        tokio::spawn(async move {
+                            let task_id = exit.task_id;
+                            Self::handle_process_exit(&db, exit).await;
+                            PROCESS_MANAGER.exit_recorded(task_id);
+                        });
+                    }
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        tracing::warn!("Process monitor missed {} exit events", n);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+
+        // Keep output redaction in sync with the configured patterns
+        redaction::reload(&ctx.db).await;
+        let mut settings_rx = settings::Entity::subscribe_changes();
+        let ctx_settings = ctx.clone();
+
+        tokio::spawn(async move {
+            loop {
+                match settings_rx.recv().await {
+                    Ok(keys) if !keys.iter().any(|k| k == "log_redaction_patterns") => {}
+                    Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {
+                        redaction::reload(&ctx_settings.db).await;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+
+        tracing::info!("Process monitor started");
+        Ok(router)
+    }
+}
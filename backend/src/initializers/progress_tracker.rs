@@ -0,0 +1,97 @@
+//! Progress Tracker Initializer
+//!
+//! Watches agent output for lines matching the `progress_marker` setting and
+//! stores the reported percentage in the task's `progress` column. Changes are
+//! also published so open terminals can update their progress bar live.
+
+use async_trait::async_trait;
+use axum::Router;
+use loco_rs::{
+    app::{AppContext, Initializer},
+    Result,
+};
+use sea_orm::{sea_query::Expr, ColumnTrait, EntityTrait, QueryFilter};
+use std::collections::HashMap;
+use tokio::sync::broadcast;
+
+use crate::models::_entities::{orchestrator_tasks, settings};
+use crate::services::orchestrator::{self, TaskProgress};
+use crate::services::process_manager::PROCESS_MANAGER;
+
+pub struct ProgressTrackerInitializer;
+
+impl ProgressTrackerInitializer {
+    async fn store(db: &sea_orm::DatabaseConnection, progress: TaskProgress) {
+        let result = orchestrator_tasks::Entity::update_many()
+            .col_expr(
+                orchestrator_tasks::Column::Progress,
+                Expr::value(progress.percent),
+            )
+            .filter(orchestrator_tasks::Column::Id.eq(progress.task_id))
+            .exec(db)
+            .await;
+        if let Err(e) = result {
+            tracing::warn!(
+                "Failed to store progress of task {}: {}",
+                progress.task_id,
+                e
+            );
+        }
+    }
+}
+
+#[async_trait]
+impl Initializer for ProgressTrackerInitializer {
+    fn name(&self) -> String {
+        "progress-tracker".to_string()
+    }
+
+    async fn after_routes(&self, router: Router, ctx: &AppContext) -> Result<Router> {
+        let db = ctx.db.clone();
+
+        tokio::spawn(async move {
+            let mut output_rx = PROCESS_MANAGER.subscribe_output();
+            let mut exit_rx = PROCESS_MANAGER.subscribe_exits();
+            let mut settings_rx = settings::Entity::subscribe_changes();
+            let mut pattern = orchestrator::progress_pattern(&db).await;
+            // Last value stored per running task, so repeated reports cost nothing
+            let mut last: HashMap<i32, i32> = HashMap::new();
+
+            loop {
+                tokio::select! {
+                    result = output_rx.recv() => match result {
+                        Ok(output) => {
+                            let Some(pattern) = &pattern else {
+                                continue;
+                            };
+                            let Some(percent) = orchestrator::parse_progress(pattern, &output.line) else {
+                                continue;
+                            };
+                            if last.insert(output.task_id, percent) == Some(percent) {
+                                continue;
+                            }
+                            let progress = TaskProgress {
+                                task_id: output.task_id,
+                                percent,
+                            };
+                            Self::store(&db, progress).await;
+                            orchestrator::publish_progress(progress);
+                        }
+                        // Missed lines only delay the next progress update
+                        Err(broadcast::error::RecvError::Lagged(_)) => {}
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    },
+                    Ok(exit) = exit_rx.recv() => {
+                        last.remove(&exit.task_id);
+                    }
+                    _ = settings_rx.recv() => {
+                        pattern = orchestrator::progress_pattern(&db).await;
+                    }
+                }
+            }
+        });
+
+        tracing::info!("Progress tracker started");
+        Ok(router)
+    }
+}
@@ -0,0 +1,107 @@
+//! Repo Fetcher Initializer
+//!
+//! Periodically fetches the target repo so new worktrees branch off fresh refs
+//! even after the orchestrator has been idle for a long time.
+
+use async_trait::async_trait;
+use axum::Router;
+use loco_rs::{
+    app::{AppContext, Initializer},
+    Result,
+};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::time::{interval_at, Instant};
+
+use crate::models::_entities::settings;
+use crate::services::git;
+
+/// Default seconds between fetches
+const DEFAULT_INTERVAL_SECS: u64 = 300;
+
+pub struct RepoFetcherInitializer;
+
+impl RepoFetcherInitializer {
+    /// Whether periodic fetching is enabled (`auto_fetch_enabled`, defaults to true)
+    async fn enabled(db: &sea_orm::DatabaseConnection) -> bool {
+        settings::Entity::get_value(db, "auto_fetch_enabled")
+            .await
+            .is_none_or(|v| v != "false")
+    }
+
+    /// Seconds between fetches (`auto_fetch_interval_secs`)
+    async fn interval(db: &sea_orm::DatabaseConnection) -> Duration {
+        let secs = settings::Entity::get_value(db, "auto_fetch_interval_secs")
+            .await
+            .and_then(|v| v.parse::<u64>().ok())
+            .filter(|s| *s > 0)
+            .unwrap_or(DEFAULT_INTERVAL_SECS);
+        Duration::from_secs(secs)
+    }
+
+    /// Run `git fetch --all --prune` in the configured repo, if any
+    async fn fetch(db: &sea_orm::DatabaseConnection) {
+        let Some(repo_path) = settings::Entity::get_value(db, "target_repo_path").await else {
+            return;
+        };
+
+        let ssh_command = git::ssh_command(db).await;
+        match git::command(ssh_command.as_deref())
+            .args(["-C", &repo_path, "fetch", "--all", "--prune"])
+            .output()
+            .await
+        {
+            Ok(output) if output.status.success() => {
+                tracing::debug!("Fetched {}", repo_path);
+            }
+            Ok(output) => tracing::warn!(
+                "Auto-fetch of {} failed: {}",
+                repo_path,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+            Err(e) => tracing::warn!("Failed to run git fetch for {}: {}", repo_path, e),
+        }
+    }
+}
+
+#[async_trait]
+impl Initializer for RepoFetcherInitializer {
+    fn name(&self) -> String {
+        "repo-fetcher".to_string()
+    }
+
+    async fn after_routes(&self, router: Router, ctx: &AppContext) -> Result<Router> {
+        let ctx_clone = ctx.clone();
+
+        tokio::spawn(async move {
+            let mut settings_rx = settings::Entity::subscribe_changes();
+            let mut enabled = Self::enabled(&ctx_clone.db).await;
+            let mut period = Self::interval(&ctx_clone.db).await;
+            let mut ticker = interval_at(Instant::now() + period, period);
+
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => {
+                        if enabled {
+                            Self::fetch(&ctx_clone.db).await;
+                        }
+                    }
+                    changed = settings_rx.recv() => {
+                        if matches!(changed, Err(broadcast::error::RecvError::Closed)) {
+                            break;
+                        }
+                        enabled = Self::enabled(&ctx_clone.db).await;
+                        let reloaded = Self::interval(&ctx_clone.db).await;
+                        if reloaded != period {
+                            period = reloaded;
+                            ticker = interval_at(Instant::now() + period, period);
+                        }
+                    }
+                }
+            }
+        });
+
+        tracing::info!("Repo fetcher started");
+        Ok(router)
+    }
+}
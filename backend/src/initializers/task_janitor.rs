@@ -0,0 +1,176 @@
+//! Task Janitor Initializer
+//!
+//! Periodically fails `in_progress` tasks whose process is no longer running.
+//! This is a safety net for exits the process monitor never saw, e.g. when
+//! the exit channel lagged.
+
+use async_trait::async_trait;
+use axum::Router;
+use loco_rs::{
+    app::{AppContext, Initializer},
+    Result,
+};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::time::{interval_at, Instant};
+
+use crate::models::_entities::{
+    orchestrator_task_logs, orchestrator_tasks, process_sessions, settings,
+};
+use crate::services::orchestrator::{self, TaskEventKind};
+use crate::services::process_manager::PROCESS_MANAGER;
+
+/// Default seconds between janitor sweeps
+const DEFAULT_INTERVAL_SECS: u64 = 300;
+
+pub struct TaskJanitorInitializer;
+
+impl TaskJanitorInitializer {
+    /// Whether the janitor is enabled (`janitor_enabled`, defaults to true)
+    async fn enabled(db: &sea_orm::DatabaseConnection) -> bool {
+        settings::Entity::get_value(db, "janitor_enabled")
+            .await
+            .is_none_or(|v| v != "false")
+    }
+
+    /// Seconds between sweeps (`janitor_interval_secs`)
+    async fn interval(db: &sea_orm::DatabaseConnection) -> Duration {
+        let secs = settings::Entity::get_value(db, "janitor_interval_secs")
+            .await
+            .and_then(|v| v.parse::<u64>().ok())
+            .filter(|s| *s > 0)
+            .unwrap_or(DEFAULT_INTERVAL_SECS);
+        Duration::from_secs(secs)
+    }
+
+    /// Fail in_progress tasks without a running process
+    ///
+    /// Tasks touched within the last `grace` are skipped, so tasks the poller is
+    /// still preparing a worktree for are not swept up before their agent starts.
+    /// Tasks whose exit is still being handled get up to the post-task timeout
+    /// on top, so a long-running post-task command is not cut off.
+    async fn sweep(db: &sea_orm::DatabaseConnection, grace: Duration) {
+        let cutoff = chrono::Utc::now()
+            - chrono::Duration::from_std(grace).unwrap_or_else(|_| chrono::Duration::zero());
+
+        let tasks = match orchestrator_tasks::Entity::find()
+            .filter(orchestrator_tasks::Column::Status.eq("in_progress"))
+            .filter(orchestrator_tasks::Column::UpdatedAt.lt(cutoff))
+            .all(db)
+            .await
+        {
+            Ok(tasks) => tasks,
+            Err(e) => {
+                tracing::error!("Janitor failed to load in_progress tasks: {}", e);
+                return;
+            }
+        };
+
+        let hook_timeout = orchestrator::post_task_timeout(db).await;
+        for task in tasks {
+            if PROCESS_MANAGER.is_running(task.id) {
+                continue;
+            }
+            // The process monitor is still recording this exit, which includes
+            // running the post-task command, unless the entry went stale
+            if PROCESS_MANAGER
+                .exit_pending_for(task.id)
+                .is_some_and(|age| age < hook_timeout + grace)
+            {
+                continue;
+            }
+
+            let now = chrono::Utc::now();
+
+            // Only tasks still in progress, in case the exit was recorded meanwhile
+            let updated = orchestrator_tasks::Entity::update_many()
+                .filter(orchestrator_tasks::Column::Id.eq(task.id))
+                .filter(orchestrator_tasks::Column::Status.eq("in_progress"))
+                .col_expr(
+                    orchestrator_tasks::Column::Status,
+                    sea_orm::sea_query::Expr::value("failed"),
+                )
+                .col_expr(
+                    orchestrator_tasks::Column::UpdatedAt,
+                    sea_orm::sea_query::Expr::value(now),
+                )
+                .exec(db)
+                .await;
+            match updated {
+                Ok(result) if result.rows_affected > 0 => {}
+                Ok(_) => continue,
+                Err(e) => {
+                    tracing::error!("Janitor failed to update task {}: {}", task.id, e);
+                    continue;
+                }
+            }
+
+            tracing::warn!(
+                "Task {} is in_progress but its process vanished, marked it failed",
+                task.id
+            );
+            orchestrator_task_logs::Entity::log_task_event(
+                db,
+                task.id,
+                "system",
+                "Agent process vanished without its exit being recorded, marking task failed",
+            )
+            .await;
+            orchestrator::publish_task_event(TaskEventKind::StatusChanged, task.id, "failed");
+            PROCESS_MANAGER.exit_recorded(task.id);
+
+            let _ = process_sessions::Entity::update_many()
+                .filter(process_sessions::Column::TaskId.eq(task.id))
+                .filter(process_sessions::Column::EndedAt.is_null())
+                .col_expr(
+                    process_sessions::Column::EndedAt,
+                    sea_orm::sea_query::Expr::value(now),
+                )
+                .exec(db)
+                .await;
+        }
+    }
+}
+
+#[async_trait]
+impl Initializer for TaskJanitorInitializer {
+    fn name(&self) -> String {
+        "task-janitor".to_string()
+    }
+
+    async fn after_routes(&self, router: Router, ctx: &AppContext) -> Result<Router> {
+        let ctx_clone = ctx.clone();
+
+        tokio::spawn(async move {
+            let mut settings_rx = settings::Entity::subscribe_changes();
+            let mut enabled = Self::enabled(&ctx_clone.db).await;
+            let mut period = Self::interval(&ctx_clone.db).await;
+            let mut ticker = interval_at(Instant::now() + period, period);
+
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => {
+                        if enabled {
+                            Self::sweep(&ctx_clone.db, period).await;
+                        }
+                    }
+                    changed = settings_rx.recv() => {
+                        if matches!(changed, Err(broadcast::error::RecvError::Closed)) {
+                            break;
+                        }
+                        enabled = Self::enabled(&ctx_clone.db).await;
+                        let reloaded = Self::interval(&ctx_clone.db).await;
+                        if reloaded != period {
+                            period = reloaded;
+                            ticker = interval_at(Instant::now() + period, period);
+                        }
+                    }
+                }
+            }
+        });
+
+        tracing::info!("Task janitor started");
+        Ok(router)
+    }
+}
@@ -0,0 +1,94 @@
+//! Task Log Listener Initializer
+//!
+//! Persists agent output lines as `output` / `stderr` task log events unless the
+//! `persist_output_logs` setting is "false". Lines are written in batches so a
+//! chatty agent costs one insert per batch rather than one per line.
+
+use async_trait::async_trait;
+use axum::Router;
+use loco_rs::{
+    app::{AppContext, Initializer},
+    Result,
+};
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+use crate::models::_entities::{orchestrator_task_logs, settings};
+use crate::services::metrics::{self, OUTPUT_LINES_DROPPED_TOTAL};
+use crate::services::process_manager::PROCESS_MANAGER;
+
+/// Longest a line waits in the buffer before being written
+const FLUSH_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Number of buffered lines that triggers an immediate write
+const MAX_BATCH_LINES: usize = 50;
+
+pub struct TaskLogListenerInitializer;
+
+impl TaskLogListenerInitializer {
+    /// Whether output lines are persisted (`persist_output_logs`, defaults to true)
+    async fn enabled(db: &sea_orm::DatabaseConnection) -> bool {
+        settings::Entity::get_value(db, "persist_output_logs")
+            .await
+            .is_none_or(|v| v != "false")
+    }
+
+    async fn flush(db: &sea_orm::DatabaseConnection, batch: &mut Vec<(i32, &'static str, String)>) {
+        orchestrator_task_logs::Entity::log_task_events(db, std::mem::take(batch)).await;
+    }
+}
+
+#[async_trait]
+impl Initializer for TaskLogListenerInitializer {
+    fn name(&self) -> String {
+        "task-log-listener".to_string()
+    }
+
+    async fn after_routes(&self, router: Router, ctx: &AppContext) -> Result<Router> {
+        let db = ctx.db.clone();
+
+        tokio::spawn(async move {
+            let mut output_rx = PROCESS_MANAGER.subscribe_output();
+            let mut settings_rx = settings::Entity::subscribe_changes();
+            let mut enabled = Self::enabled(&db).await;
+            let mut batch = Vec::new();
+            let mut flush = tokio::time::interval(FLUSH_INTERVAL);
+            flush.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+            loop {
+                tokio::select! {
+                    result = output_rx.recv() => match result {
+                        Ok(output) => {
+                            if !enabled {
+                                continue;
+                            }
+                            let event_type = if output.is_stderr { "stderr" } else { "output" };
+                            batch.push((output.task_id, event_type, output.line));
+                            if batch.len() >= MAX_BATCH_LINES {
+                                Self::flush(&db, &mut batch).await;
+                            }
+                        }
+                        Err(broadcast::error::RecvError::Lagged(n)) => {
+                            if enabled {
+                                metrics::add(&OUTPUT_LINES_DROPPED_TOTAL, n);
+                                tracing::warn!("Task log listener lagged, {} output lines not persisted", n);
+                            }
+                        }
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    },
+                    _ = flush.tick() => {
+                        Self::flush(&db, &mut batch).await;
+                    }
+                    _ = settings_rx.recv() => {
+                        enabled = Self::enabled(&db).await;
+                    }
+                }
+            }
+
+            Self::flush(&db, &mut batch).await;
+        });
+
+        tracing::info!("Task log listener started");
+        Ok(router)
+    }
+}
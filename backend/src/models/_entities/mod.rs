@@ -2,6 +2,7 @@
 
 pub mod prelude;
 
+pub mod orchestrator_task_logs;
 pub mod orchestrator_tasks;
 pub mod process_sessions;
 pub mod settings;
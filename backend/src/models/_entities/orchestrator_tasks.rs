@@ -3,7 +3,7 @@
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
 #[sea_orm(table_name = "orchestrator_tasks")]
 pub struct Model {
     pub created_at: DateTimeWithTimeZone,
@@ -22,6 +22,23 @@ pub struct Model {
     pub time_spent_ms: i32,
     pub started_at: Option<DateTimeWithTimeZone>,
     pub completed_at: Option<DateTimeWithTimeZone>,
+    pub input_tokens: Option<i32>,
+    pub output_tokens: Option<i32>,
+    #[sea_orm(column_type = "Double", nullable)]
+    pub estimated_cost: Option<f64>,
+    pub agent_session_id: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub tags: Option<String>,
+    pub depends_on_clickup_id: Option<String>,
+    pub base_branch: Option<String>,
+    pub queue_position: Option<i32>,
+    pub working_subdir: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub note: Option<String>,
+    pub agent_type: Option<String>,
+    pub progress: Option<i32>,
+    pub env_file: Option<String>,
+    pub restart_count: i32,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
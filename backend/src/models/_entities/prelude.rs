@@ -1,5 +1,6 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.19
 
+pub use super::orchestrator_task_logs::Entity as OrchestratorTaskLogs;
 pub use super::orchestrator_tasks::Entity as OrchestratorTasks;
 pub use super::process_sessions::Entity as ProcessSessions;
 pub use super::settings::Entity as Settings;
@@ -1,5 +1,6 @@
 pub mod _entities;
 pub mod users;
 pub mod orchestrator_tasks;
+pub mod orchestrator_task_logs;
 pub mod process_sessions;
 pub mod settings;
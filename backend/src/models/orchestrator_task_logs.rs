@@ -0,0 +1,68 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::Set;
+pub use super::_entities::orchestrator_task_logs::{ActiveModel, Model, Entity};
+pub type OrchestratorTaskLogs = Entity;
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert && self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+// implement your read-oriented logic here
+impl Model {}
+
+// implement your write-oriented logic here
+impl ActiveModel {}
+
+// implement your custom finders, selectors oriented logic here
+impl Entity {
+    /// Record an event for a task, logging instead of failing on errors
+    pub async fn log_task_event(db: &DatabaseConnection, task_id: i32, event_type: &str, message: &str) {
+        let now = chrono::Utc::now();
+        let entry = ActiveModel {
+            task_id: Set(task_id),
+            event_type: Set(event_type.to_string()),
+            message: Set(message.to_string()),
+            created_at: Set(now.into()),
+            updated_at: Set(now.into()),
+            ..Default::default()
+        };
+
+        if let Err(e) = Self::insert(entry).exec(db).await {
+            tracing::error!("Failed to log {} event for task {}: {}", event_type, task_id, e);
+        }
+    }
+
+    /// Record many `(task_id, event_type, message)` events in one insert
+    pub async fn log_task_events(db: &DatabaseConnection, events: Vec<(i32, &str, String)>) {
+        if events.is_empty() {
+            return;
+        }
+
+        let now = chrono::Utc::now();
+        let count = events.len();
+        let entries = events.into_iter().map(|(task_id, event_type, message)| ActiveModel {
+            task_id: Set(task_id),
+            event_type: Set(event_type.to_string()),
+            message: Set(message),
+            created_at: Set(now.into()),
+            updated_at: Set(now.into()),
+            ..Default::default()
+        });
+
+        if let Err(e) = Self::insert_many(entries).exec(db).await {
+            tracing::error!("Failed to log {} task events: {}", count, e);
+        }
+    }
+}
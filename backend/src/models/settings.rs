@@ -1,7 +1,13 @@
 use sea_orm::entity::prelude::*;
+use tokio::sync::broadcast;
 pub use super::_entities::settings::{ActiveModel, Model, Entity};
 pub type Settings = Entity;
 
+lazy_static::lazy_static! {
+    /// Broadcasts the keys of settings that were changed through the API
+    static ref SETTINGS_CHANGED: broadcast::Sender<Vec<String>> = broadcast::channel(16).0;
+}
+
 #[async_trait::async_trait]
 impl ActiveModelBehavior for ActiveModel {
     async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
@@ -25,4 +31,28 @@ impl Model {}
 impl ActiveModel {}
 
 // implement your custom finders, selectors oriented logic here
-impl Entity {}
+impl Entity {
+    /// Get a setting value by key, treating empty values as unset
+    pub async fn get_value(db: &DatabaseConnection, key: &str) -> Option<String> {
+        Self::find()
+            .filter(super::_entities::settings::Column::Key.eq(key))
+            .one(db)
+            .await
+            .ok()
+            .flatten()
+            .map(|s| s.value)
+            .filter(|v| !v.is_empty())
+    }
+
+    /// Subscribe to notifications about changed setting keys
+    pub fn subscribe_changes() -> broadcast::Receiver<Vec<String>> {
+        SETTINGS_CHANGED.subscribe()
+    }
+
+    /// Notify subscribers that the given setting keys changed
+    pub fn notify_changed(keys: Vec<String>) {
+        if !keys.is_empty() {
+            let _ = SETTINGS_CHANGED.send(keys);
+        }
+    }
+}
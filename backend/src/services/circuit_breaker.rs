@@ -0,0 +1,100 @@
+//! Circuit breaker for calls to an external service that may be down
+
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+struct BreakerState {
+    consecutive_failures: u32,
+    /// Calls are skipped until this time once the breaker has opened
+    open_until: Option<Instant>,
+}
+
+/// Stops calls to a failing service for a cooldown after repeated failures
+///
+/// After the cooldown a single call is let through; another failure reopens
+/// the breaker right away, a success closes it.
+pub struct CircuitBreaker {
+    name: &'static str,
+    state: Mutex<BreakerState>,
+}
+
+/// Breaker state for health reporting
+#[derive(Debug, Clone, Serialize)]
+pub struct BreakerStatus {
+    pub open: bool,
+    pub consecutive_failures: u32,
+    /// Seconds until calls are attempted again while open
+    pub retry_in_secs: Option<u64>,
+}
+
+impl CircuitBreaker {
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            state: Mutex::new(BreakerState {
+                consecutive_failures: 0,
+                open_until: None,
+            }),
+        }
+    }
+
+    /// Whether calls should be skipped right now
+    pub fn is_open(&self) -> bool {
+        self.state
+            .lock()
+            .map(|state| state.open_until.is_some_and(|until| Instant::now() < until))
+            .unwrap_or(false)
+    }
+
+    /// Close the breaker after a successful call
+    pub fn record_success(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            if state.consecutive_failures > 0 {
+                tracing::info!(
+                    "{} recovered after {} consecutive failure(s)",
+                    self.name,
+                    state.consecutive_failures
+                );
+            }
+            state.consecutive_failures = 0;
+            state.open_until = None;
+        }
+    }
+
+    /// Count a failed call, opening the breaker for `cooldown` once
+    /// `threshold` failures happened in a row
+    pub fn record_failure(&self, threshold: u32, cooldown: Duration) {
+        if let Ok(mut state) = self.state.lock() {
+            state.consecutive_failures += 1;
+            if state.consecutive_failures >= threshold.max(1) {
+                state.open_until = Some(Instant::now() + cooldown);
+                tracing::error!(
+                    "{} failed {} times in a row, pausing calls for {}s",
+                    self.name,
+                    state.consecutive_failures,
+                    cooldown.as_secs()
+                );
+            }
+        }
+    }
+
+    pub fn status(&self) -> BreakerStatus {
+        let Ok(state) = self.state.lock() else {
+            return BreakerStatus {
+                open: false,
+                consecutive_failures: 0,
+                retry_in_secs: None,
+            };
+        };
+        let remaining = state
+            .open_until
+            .and_then(|until| until.checked_duration_since(Instant::now()))
+            .filter(|d| !d.is_zero());
+        BreakerStatus {
+            open: remaining.is_some(),
+            consecutive_failures: state.consecutive_failures,
+            retry_in_secs: remaining.map(|d| d.as_secs().max(1)),
+        }
+    }
+}
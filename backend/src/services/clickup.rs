@@ -1,10 +1,18 @@
 //! ClickUp API client for hierarchy browsing and task operations
 
-use reqwest::Client;
+use async_trait::async_trait;
+use reqwest::{Client, RequestBuilder};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use crate::services::circuit_breaker::CircuitBreaker;
+use crate::services::clickup_fixtures::FixtureClient;
+use crate::services::metrics::{self, CLICKUP_API_ERRORS_TOTAL};
+
+/// Default base URL of the ClickUp REST API (v2)
 const CLICKUP_API_BASE: &str = "https://api.clickup.com/api/v2";
+/// Default base URL of the ClickUp web app
+const CLICKUP_APP_BASE: &str = "https://app.clickup.com";
 
 #[derive(Error, Debug)]
 pub enum ClickUpError {
@@ -12,16 +20,103 @@ pub enum ClickUpError {
     Request(#[from] reqwest::Error),
     #[error("API key not configured")]
     NoApiKey,
-    #[error("ClickUp API error: {0}")]
-    Api(String),
+    #[error("ClickUp API error: {status}: {message}")]
+    Api {
+        status: reqwest::StatusCode,
+        message: String,
+    },
+    #[error("Status '{status}' does not exist on the list (valid statuses: {})", .valid.join(", "))]
+    UnknownStatus { status: String, valid: Vec<String> },
+}
+
+impl ClickUpError {
+    /// Whether the error points at ClickUp being unreachable or failing,
+    /// rather than at a bad request or configuration
+    pub fn is_outage(&self) -> bool {
+        match self {
+            Self::Request(_) => true,
+            Self::Api { status, .. } => {
+                status.is_server_error() || *status == reqwest::StatusCode::TOO_MANY_REQUESTS
+            }
+            Self::NoApiKey | Self::UnknownStatus { .. } => false,
+        }
+    }
 }
 
 pub type Result<T> = std::result::Result<T, ClickUpError>;
 
+/// Tracks consecutive ClickUp outages so the poller can back off
+pub static CLICKUP_BREAKER: CircuitBreaker = CircuitBreaker::new("ClickUp API");
+
+/// Whether the task was created by hand rather than synced from ClickUp
+///
+/// Manual tasks use ids starting with `manual-` and have nothing to look up
+/// in the ClickUp API.
+pub fn is_manual_task(task_id: &str) -> bool {
+    task_id.starts_with("manual-")
+}
+
+/// Link to a task in the ClickUp web app
+///
+/// The base URL can be overridden with `CLICKUP_APP_URL` for enterprise
+/// workspaces. Manual tasks (see [`is_manual_task`]) have no link.
+pub fn task_url(task_id: &str) -> Option<String> {
+    if is_manual_task(task_id) {
+        return None;
+    }
+    let base = std::env::var("CLICKUP_APP_URL")
+        .ok()
+        .filter(|v| !v.is_empty())
+        .unwrap_or_else(|| CLICKUP_APP_BASE.to_string());
+    Some(format!("{}/t/{}", base.trim_end_matches('/'), task_id))
+}
+
+/// Find the list status matching `name`, ignoring case
+///
+/// ClickUp compares status names case-insensitively, so the canonical name from
+/// the list is returned for use in later requests.
+pub fn resolve_status(statuses: &[Status], name: &str) -> Result<String> {
+    statuses
+        .iter()
+        .find(|s| s.status.eq_ignore_ascii_case(name.trim()))
+        .map(|s| s.status.clone())
+        .ok_or_else(|| ClickUpError::UnknownStatus {
+            status: name.to_string(),
+            valid: statuses.iter().map(|s| s.status.clone()).collect(),
+        })
+}
+
+/// Extra filters for listing a list's tasks; the default applies none
+#[derive(Debug, Clone, Default)]
+pub struct TaskFilter {
+    /// Also return tasks in closed statuses
+    pub include_closed: bool,
+    /// Only return tasks assigned to one of these user ids
+    pub assignees: Vec<String>,
+}
+
+/// Version of the ClickUp REST API an endpoint group is served from
+///
+/// Most endpoints live under v2, newer ones (e.g. docs) only exist under v3.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiVersion {
+    V2,
+    V3,
+}
+
+/// Derive the v3 base URL from a v2 one: `…/api/v2` becomes `…/api/v3`, other
+/// bases (e.g. a mock server) get a `/v3` suffix
+fn v3_base_url(v2_base: &str) -> String {
+    let root = v2_base.strip_suffix("/v2").unwrap_or(v2_base);
+    format!("{}/v3", root)
+}
+
 /// ClickUp API client
 pub struct ClickUpClient {
     client: Client,
     api_key: String,
+    base_url: String,
+    base_url_v3: String,
 }
 
 // === API Response Types ===
@@ -101,6 +196,113 @@ pub struct Task {
     pub status: TaskStatus,
     pub priority: Option<TaskPriority>,
     pub list: TaskList,
+    #[serde(default)]
+    pub custom_fields: Vec<CustomField>,
+    #[serde(default)]
+    pub tags: Vec<TaskTag>,
+    /// Only included when the task is fetched on its own
+    #[serde(default)]
+    pub checklists: Vec<Checklist>,
+}
+
+impl Task {
+    /// Get the value of a custom field, matched by field id or name.
+    ///
+    /// Drop-down values are resolved to the selected option's name, other
+    /// values are returned in their string form. Unset fields yield `None`.
+    pub fn custom_field_value(&self, field: &str) -> Option<String> {
+        self.custom_fields
+            .iter()
+            .find(|f| f.id == field || f.name.eq_ignore_ascii_case(field))
+            .and_then(CustomField::value_as_string)
+    }
+
+    /// Resolve the task's integer priority, preferring a custom priority field
+    pub fn priority_value(&self, priority_field_id: Option<&str>) -> Option<i32> {
+        priority_field_id
+            .and_then(|field| self.custom_field_value(field))
+            .and_then(|v| priority_name_to_int(&v))
+            .or_else(|| priority_to_int(&self.priority))
+    }
+
+    /// Tag names joined with commas, or `None` when the task has no tags
+    pub fn joined_tags(&self) -> Option<String> {
+        if self.tags.is_empty() {
+            return None;
+        }
+        Some(
+            self.tags
+                .iter()
+                .map(|t| t.name.as_str())
+                .collect::<Vec<_>>()
+                .join(","),
+        )
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Checklist {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub items: Vec<ChecklistItem>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ChecklistItem {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub resolved: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TaskTag {
+    pub name: String,
+    pub tag_fg: Option<String>,
+    pub tag_bg: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CustomField {
+    pub id: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub field_type: Option<String>,
+    pub type_config: Option<serde_json::Value>,
+    pub value: Option<serde_json::Value>,
+}
+
+impl CustomField {
+    /// Render the field value as a plain string
+    pub fn value_as_string(&self) -> Option<String> {
+        let value = self.value.as_ref()?;
+
+        // Drop-down values are the orderindex of the selected option
+        if self.field_type.as_deref() == Some("drop_down") {
+            let options = self
+                .type_config
+                .as_ref()
+                .and_then(|c| c.get("options"))
+                .and_then(|o| o.as_array())?;
+            let index = value
+                .as_i64()
+                .or_else(|| value.as_str().and_then(|s| s.parse().ok()))?;
+            return options
+                .iter()
+                .find(|o| o.get("orderindex").and_then(|i| i.as_i64()) == Some(index))
+                .and_then(|o| o.get("name"))
+                .and_then(|n| n.as_str())
+                .map(|n| n.to_string());
+        }
+
+        match value {
+            serde_json::Value::Null => None,
+            serde_json::Value::String(s) if s.is_empty() => None,
+            serde_json::Value::String(s) => Some(s.clone()),
+            other => Some(other.to_string()),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -124,6 +326,26 @@ pub struct TaskList {
     pub name: Option<String>,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CommentUser {
+    pub username: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Comment {
+    /// Plain-text rendering of the comment's rich text
+    #[serde(default)]
+    pub comment_text: String,
+    pub user: Option<CommentUser>,
+    /// Unix time in milliseconds, as a string
+    pub date: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CommentsResponse {
+    pub comments: Vec<Comment>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct TasksResponse {
     pub tasks: Vec<Task>,
@@ -135,6 +357,20 @@ pub struct UpdateTaskRequest {
     pub status: Option<String>,
 }
 
+/// A new task for a list, also the body ClickUp expects
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CreateTaskRequest {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// 1=urgent, 2=high, 3=normal, 4=low
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<i32>,
+    /// Initial status, the list's first status when omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct TimeEntryRequest {
     pub start: i64,
@@ -142,12 +378,124 @@ pub struct TimeEntryRequest {
     pub time: i64,
 }
 
+/// ClickUp operations used by the orchestrator
+///
+/// Implemented by [`ClickUpClient`] for the real API and by
+/// [`FixtureClient`](crate::services::clickup_fixtures::FixtureClient) for
+/// offline development. Get one with [`client_from_env`].
+#[async_trait]
+pub trait ClickUpApi: Send + Sync {
+    // === Hierarchy Browser Methods ===
+
+    /// Get all workspaces (teams) the user has access to
+    async fn get_workspaces(&self) -> Result<Vec<Team>>;
+
+    /// Get all spaces in a workspace
+    async fn get_spaces(&self, team_id: &str) -> Result<Vec<Space>>;
+
+    /// Get all folders in a space
+    async fn get_folders(&self, space_id: &str) -> Result<Vec<Folder>>;
+
+    /// Get all lists in a folder
+    async fn get_lists_in_folder(&self, folder_id: &str) -> Result<Vec<List>>;
+
+    /// Get folderless lists in a space
+    async fn get_folderless_lists(&self, space_id: &str) -> Result<Vec<List>>;
+
+    /// Get list details including statuses
+    async fn get_list(&self, list_id: &str) -> Result<ListDetails>;
+
+    // === Task Operations ===
+
+    /// Get tasks from a list with optional status and task filters
+    ///
+    /// ClickUp includes each task's `custom_fields` in list responses, so no
+    /// extra query parameter is needed to read them.
+    async fn get_tasks(
+        &self,
+        list_id: &str,
+        status: Option<&str>,
+        filter: &TaskFilter,
+    ) -> Result<Vec<Task>>;
+
+    /// Get a task's comments, newest first
+    async fn get_comments(&self, task_id: &str) -> Result<Vec<Comment>>;
+
+    /// Get a task's checklists with their items
+    async fn get_checklists(&self, task_id: &str) -> Result<Vec<Checklist>>;
+
+    /// Update a task's status
+    async fn update_task_status(&self, task_id: &str, status: &str) -> Result<Task>;
+
+    /// Create a task in a list
+    async fn create_task(&self, list_id: &str, request: &CreateTaskRequest) -> Result<Task>;
+
+    /// Add a time entry to a task
+    async fn add_time_entry(
+        &self,
+        task_id: &str,
+        start_ms: i64,
+        end_ms: i64,
+        duration_ms: i64,
+    ) -> Result<serde_json::Value>;
+
+    /// Get statuses for a list
+    async fn get_list_statuses(&self, list_id: &str) -> Result<Vec<Status>> {
+        let list: ListDetails = self.get_list(list_id).await?;
+        Ok(list.statuses)
+    }
+
+    /// Resolve configured status names against a list's statuses
+    ///
+    /// Fetches the list once and returns the canonical names in the same order,
+    /// or an error naming the valid statuses for the first name that doesn't match.
+    async fn resolve_statuses(&self, list_id: &str, names: &[&str]) -> Result<Vec<String>> {
+        let statuses = self.get_list_statuses(list_id).await?;
+        names
+            .iter()
+            .map(|name| resolve_status(&statuses, name))
+            .collect()
+    }
+}
+
+/// Create the ClickUp client for this process
+///
+/// Serves canned responses from the directory in `CLICKUP_FIXTURES_DIR` when
+/// set, see [`FixtureClient`](crate::services::clickup_fixtures::FixtureClient),
+/// and talks to the API with `CLICKUP_API_KEY` otherwise.
+pub fn client_from_env() -> Result<Box<dyn ClickUpApi>> {
+    match std::env::var("CLICKUP_FIXTURES_DIR") {
+        Ok(dir) if !dir.is_empty() => Ok(Box::new(FixtureClient::new(dir))),
+        _ => Ok(Box::new(ClickUpClient::from_env()?)),
+    }
+}
+
 impl ClickUpClient {
     /// Create a new ClickUp client
+    ///
+    /// Requests go to `CLICKUP_API_BASE` when set, e.g. a mock server in tests,
+    /// and to the public v2 API otherwise. v3 endpoints go to
+    /// `CLICKUP_API_V3_BASE`, derived from the v2 base when unset.
     pub fn new(api_key: String) -> Self {
+        let env = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+        let base_url = env("CLICKUP_API_BASE").unwrap_or_else(|| CLICKUP_API_BASE.to_string());
+        let mut client = Self::with_base_url(api_key, base_url);
+        if let Some(base_url_v3) = env("CLICKUP_API_V3_BASE") {
+            client.base_url_v3 = base_url_v3.trim_end_matches('/').to_string();
+        }
+        client
+    }
+
+    /// Create a client that sends requests to `base_url` instead of the ClickUp API
+    ///
+    /// `base_url` is the v2 base, the v3 base is derived from it.
+    pub fn with_base_url(api_key: String, base_url: impl Into<String>) -> Self {
+        let base_url = base_url.into().trim_end_matches('/').to_string();
         Self {
             client: Client::new(),
             api_key,
+            base_url_v3: v3_base_url(&base_url),
+            base_url,
         }
     }
 
@@ -157,47 +505,76 @@ impl ClickUpClient {
         Ok(Self::new(api_key))
     }
 
-    /// Make an authenticated GET request
-    async fn get<T: for<'de> Deserialize<'de>>(&self, endpoint: &str) -> Result<T> {
-        let url = format!("{}{}", CLICKUP_API_BASE, endpoint);
-        let response = self
-            .client
-            .get(&url)
+    /// Full URL of `endpoint` under the given API version
+    fn url(&self, version: ApiVersion, endpoint: &str) -> String {
+        let base = match version {
+            ApiVersion::V2 => &self.base_url,
+            ApiVersion::V3 => &self.base_url_v3,
+        };
+        format!("{}{}", base, endpoint)
+    }
+
+    /// Send an authenticated request and decode the JSON response
+    async fn send<T: for<'de> Deserialize<'de>>(&self, request: RequestBuilder) -> Result<T> {
+        let response = request
             .header("Authorization", &self.api_key)
             .send()
-            .await?;
+            .await
+            .inspect_err(|_| metrics::inc(&CLICKUP_API_ERRORS_TOTAL))?;
 
         if !response.status().is_success() {
+            metrics::inc(&CLICKUP_API_ERRORS_TOTAL);
             let status = response.status();
-            let text = response.text().await.unwrap_or_default();
-            return Err(ClickUpError::Api(format!("{}: {}", status, text)));
+            let message = response.text().await.unwrap_or_default();
+            return Err(ClickUpError::Api { status, message });
         }
 
         Ok(response.json().await?)
     }
 
-    /// Make an authenticated PUT request
-    async fn put<B: Serialize, T: for<'de> Deserialize<'de>>(
+    /// Make an authenticated GET request against the given API version
+    pub async fn get_versioned<T: for<'de> Deserialize<'de>>(
+        &self,
+        version: ApiVersion,
+        endpoint: &str,
+    ) -> Result<T> {
+        self.send(self.client.get(self.url(version, endpoint))).await
+    }
+
+    /// Make an authenticated PUT request against the given API version
+    pub async fn put_versioned<B: Serialize, T: for<'de> Deserialize<'de>>(
         &self,
+        version: ApiVersion,
         endpoint: &str,
         body: &B,
     ) -> Result<T> {
-        let url = format!("{}{}", CLICKUP_API_BASE, endpoint);
-        let response = self
-            .client
-            .put(&url)
-            .header("Authorization", &self.api_key)
-            .json(body)
-            .send()
-            .await?;
+        self.send(self.client.put(self.url(version, endpoint)).json(body))
+            .await
+    }
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let text = response.text().await.unwrap_or_default();
-            return Err(ClickUpError::Api(format!("{}: {}", status, text)));
-        }
+    /// Make an authenticated POST request against the given API version
+    pub async fn post_versioned<B: Serialize, T: for<'de> Deserialize<'de>>(
+        &self,
+        version: ApiVersion,
+        endpoint: &str,
+        body: &B,
+    ) -> Result<T> {
+        self.send(self.client.post(self.url(version, endpoint)).json(body))
+            .await
+    }
 
-        Ok(response.json().await?)
+    /// Make an authenticated GET request
+    async fn get<T: for<'de> Deserialize<'de>>(&self, endpoint: &str) -> Result<T> {
+        self.get_versioned(ApiVersion::V2, endpoint).await
+    }
+
+    /// Make an authenticated PUT request
+    async fn put<B: Serialize, T: for<'de> Deserialize<'de>>(
+        &self,
+        endpoint: &str,
+        body: &B,
+    ) -> Result<T> {
+        self.put_versioned(ApiVersion::V2, endpoint, body).await
     }
 
     /// Make an authenticated POST request
@@ -206,93 +583,122 @@ impl ClickUpClient {
         endpoint: &str,
         body: &B,
     ) -> Result<T> {
-        let url = format!("{}{}", CLICKUP_API_BASE, endpoint);
-        let response = self
-            .client
-            .post(&url)
-            .header("Authorization", &self.api_key)
-            .json(body)
-            .send()
-            .await?;
+        self.post_versioned(ApiVersion::V2, endpoint, body).await
+    }
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let text = response.text().await.unwrap_or_default();
-            return Err(ClickUpError::Api(format!("{}: {}", status, text)));
-        }
+    /// Make an authenticated GET request against a v3 endpoint
+    pub async fn get_v3<T: for<'de> Deserialize<'de>>(&self, endpoint: &str) -> Result<T> {
+        self.get_versioned(ApiVersion::V3, endpoint).await
+    }
 
-        Ok(response.json().await?)
+    /// Make an authenticated PUT request against a v3 endpoint
+    pub async fn put_v3<B: Serialize, T: for<'de> Deserialize<'de>>(
+        &self,
+        endpoint: &str,
+        body: &B,
+    ) -> Result<T> {
+        self.put_versioned(ApiVersion::V3, endpoint, body).await
     }
+}
 
+#[async_trait]
+impl ClickUpApi for ClickUpClient {
     // === Hierarchy Browser Methods ===
 
     /// Get all workspaces (teams) the user has access to
-    pub async fn get_workspaces(&self) -> Result<Vec<Team>> {
+    async fn get_workspaces(&self) -> Result<Vec<Team>> {
         let response: TeamsResponse = self.get("/team").await?;
         Ok(response.teams)
     }
 
     /// Get all spaces in a workspace
-    pub async fn get_spaces(&self, team_id: &str) -> Result<Vec<Space>> {
+    async fn get_spaces(&self, team_id: &str) -> Result<Vec<Space>> {
         let response: SpacesResponse = self.get(&format!("/team/{}/space", team_id)).await?;
         Ok(response.spaces)
     }
 
     /// Get all folders in a space
-    pub async fn get_folders(&self, space_id: &str) -> Result<Vec<Folder>> {
+    async fn get_folders(&self, space_id: &str) -> Result<Vec<Folder>> {
         let response: FoldersResponse = self.get(&format!("/space/{}/folder", space_id)).await?;
         Ok(response.folders)
     }
 
     /// Get all lists in a folder
-    pub async fn get_lists_in_folder(&self, folder_id: &str) -> Result<Vec<List>> {
+    async fn get_lists_in_folder(&self, folder_id: &str) -> Result<Vec<List>> {
         let response: ListsResponse = self.get(&format!("/folder/{}/list", folder_id)).await?;
         Ok(response.lists)
     }
 
     /// Get folderless lists in a space
-    pub async fn get_folderless_lists(&self, space_id: &str) -> Result<Vec<List>> {
+    async fn get_folderless_lists(&self, space_id: &str) -> Result<Vec<List>> {
         let response: ListsResponse = self.get(&format!("/space/{}/list", space_id)).await?;
         Ok(response.lists)
     }
 
     /// Get list details including statuses
-    pub async fn get_list(&self, list_id: &str) -> Result<ListDetails> {
+    async fn get_list(&self, list_id: &str) -> Result<ListDetails> {
         self.get(&format!("/list/{}", list_id)).await
     }
 
-    /// Get statuses for a list
-    pub async fn get_list_statuses(&self, list_id: &str) -> Result<Vec<Status>> {
-        let list: ListDetails = self.get_list(list_id).await?;
-        Ok(list.statuses)
-    }
-
     // === Task Operations ===
 
-    /// Get tasks from a list with optional status filter
-    pub async fn get_tasks(&self, list_id: &str, status: Option<&str>) -> Result<Vec<Task>> {
-        let endpoint = match status {
-            Some(s) => format!(
-                "/list/{}/task?statuses[]={}",
-                list_id,
-                urlencoding::encode(s)
-            ),
-            None => format!("/list/{}/task", list_id),
+    /// Get tasks from a list with optional status and task filters
+    ///
+    /// ClickUp includes each task's `custom_fields` in list responses, so no
+    /// extra query parameter is needed to read them.
+    async fn get_tasks(
+        &self,
+        list_id: &str,
+        status: Option<&str>,
+        filter: &TaskFilter,
+    ) -> Result<Vec<Task>> {
+        let mut params = Vec::new();
+        if let Some(s) = status {
+            params.push(format!("statuses[]={}", urlencoding::encode(s)));
+        }
+        if filter.include_closed {
+            params.push("include_closed=true".to_string());
+        }
+        for assignee in &filter.assignees {
+            params.push(format!("assignees[]={}", urlencoding::encode(assignee)));
+        }
+
+        let endpoint = if params.is_empty() {
+            format!("/list/{}/task", list_id)
+        } else {
+            format!("/list/{}/task?{}", list_id, params.join("&"))
         };
         let response: TasksResponse = self.get(&endpoint).await?;
         Ok(response.tasks)
     }
 
+    /// Get a task's comments, newest first
+    async fn get_comments(&self, task_id: &str) -> Result<Vec<Comment>> {
+        let response: CommentsResponse = self.get(&format!("/task/{}/comment", task_id)).await?;
+        Ok(response.comments)
+    }
+
+    /// Get a task's checklists, which ClickUp only returns with the task itself
+    async fn get_checklists(&self, task_id: &str) -> Result<Vec<Checklist>> {
+        let task: Task = self.get(&format!("/task/{}", task_id)).await?;
+        Ok(task.checklists)
+    }
+
     /// Update a task's status
-    pub async fn update_task_status(&self, task_id: &str, status: &str) -> Result<Task> {
+    async fn update_task_status(&self, task_id: &str, status: &str) -> Result<Task> {
         let body = UpdateTaskRequest {
             status: Some(status.to_string()),
         };
         self.put(&format!("/task/{}", task_id), &body).await
     }
 
+    /// Create a task in a list
+    async fn create_task(&self, list_id: &str, request: &CreateTaskRequest) -> Result<Task> {
+        self.post(&format!("/list/{}/task", list_id), request).await
+    }
+
     /// Add a time entry to a task
-    pub async fn add_time_entry(
+    async fn add_time_entry(
         &self,
         task_id: &str,
         start_ms: i64,
@@ -310,13 +716,20 @@ impl ClickUpClient {
 
 /// Helper to convert ClickUp priority to integer (1=urgent, 2=high, 3=normal, 4=low)
 pub fn priority_to_int(priority: &Option<TaskPriority>) -> Option<i32> {
-    priority.as_ref().and_then(|p| {
-        p.priority.as_ref().and_then(|s| match s.as_str() {
-            "urgent" => Some(1),
-            "high" => Some(2),
-            "normal" => Some(3),
-            "low" => Some(4),
-            _ => None,
-        })
-    })
+    priority
+        .as_ref()
+        .and_then(|p| p.priority.as_deref().and_then(priority_name_to_int))
+}
+
+/// Helper to convert a priority name or number to integer (1=urgent, 2=high, 3=normal, 4=low)
+///
+/// Used for custom priority fields, which may hold either a name or a number.
+pub fn priority_name_to_int(value: &str) -> Option<i32> {
+    match value.trim().to_lowercase().as_str() {
+        "urgent" => Some(1),
+        "high" => Some(2),
+        "normal" => Some(3),
+        "low" => Some(4),
+        other => other.parse().ok().filter(|p| (1..=4).contains(p)),
+    }
 }
@@ -0,0 +1,226 @@
+//! Fixture-backed ClickUp client for offline development and tests
+//!
+//! Responses are read from JSON files laid out like the API paths they stand
+//! in for, relative to the fixtures directory:
+//!
+//! - `team.json` for `GET /team`
+//! - `team/<team_id>/space.json`, `space/<space_id>/folder.json`,
+//!   `folder/<folder_id>/list.json`, `space/<space_id>/list.json`
+//! - `list/<list_id>.json` for list details and statuses
+//! - `list/<list_id>/task.json` for the list's tasks
+//! - `task/<task_id>/comment.json` for comments, optional
+//!
+//! Each file holds the body the real API would return, so captured responses
+//! can be dropped in as they are. Status updates and time entries are kept in
+//! memory for the lifetime of the process. Created tasks are echoed back but
+//! not added to the list fixtures.
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::services::clickup::{
+    Checklist, ClickUpApi, ClickUpError, Comment, CommentsResponse, CreateTaskRequest, Folder,
+    FoldersResponse, List, ListDetails, ListsResponse, Result, Space, SpacesResponse, Task,
+    TaskFilter, TaskList, TaskPriority, TaskStatus, TasksResponse, Team, TeamsResponse,
+};
+
+lazy_static::lazy_static! {
+    /// Status changes made through fixture clients, keyed by task id
+    ///
+    /// Shared across clients because callers create a new one per request.
+    static ref STATUS_OVERRIDES: Mutex<HashMap<String, String>> = Mutex::new(HashMap::new());
+}
+
+/// ClickUp client serving canned responses from a directory
+pub struct FixtureClient {
+    dir: PathBuf,
+}
+
+impl FixtureClient {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Read and parse the fixture for an API path such as `list/123/task`
+    async fn load<T: for<'de> Deserialize<'de>>(&self, path: &str) -> Result<T> {
+        let file = self.dir.join(format!("{}.json", path));
+        let content = tokio::fs::read_to_string(&file)
+            .await
+            .map_err(|e| ClickUpError::Api {
+                status: reqwest::StatusCode::NOT_FOUND,
+                message: format!("No fixture at {}: {}", file.display(), e),
+            })?;
+        serde_json::from_str(&content).map_err(|e| ClickUpError::Api {
+            status: reqwest::StatusCode::INTERNAL_SERVER_ERROR,
+            message: format!("Invalid fixture {}: {}", file.display(), e),
+        })
+    }
+
+    /// Apply status changes made earlier in this process
+    fn with_overrides(mut tasks: Vec<Task>) -> Vec<Task> {
+        if let Ok(overrides) = STATUS_OVERRIDES.lock() {
+            for task in &mut tasks {
+                if let Some(status) = overrides.get(&task.id) {
+                    task.status.status = status.clone();
+                }
+            }
+        }
+        tasks
+    }
+
+    /// Find a task in any list fixture
+    async fn find_task(&self, task_id: &str) -> Result<Task> {
+        let mut entries = tokio::fs::read_dir(self.dir.join("list"))
+            .await
+            .map_err(|e| ClickUpError::Api {
+                status: reqwest::StatusCode::NOT_FOUND,
+                message: format!("No list fixtures: {}", e),
+            })?;
+
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            if !entry.path().is_dir() {
+                continue;
+            }
+            let list_id = entry.file_name().to_string_lossy().to_string();
+            let Ok(response) = self
+                .load::<TasksResponse>(&format!("list/{}/task", list_id))
+                .await
+            else {
+                continue;
+            };
+            if let Some(task) = response.tasks.into_iter().find(|t| t.id == task_id) {
+                return Ok(task);
+            }
+        }
+
+        Err(ClickUpError::Api {
+            status: reqwest::StatusCode::NOT_FOUND,
+            message: format!("Task {} not found in fixtures", task_id),
+        })
+    }
+}
+
+#[async_trait]
+impl ClickUpApi for FixtureClient {
+    async fn get_workspaces(&self) -> Result<Vec<Team>> {
+        let response: TeamsResponse = self.load("team").await?;
+        Ok(response.teams)
+    }
+
+    async fn get_spaces(&self, team_id: &str) -> Result<Vec<Space>> {
+        let response: SpacesResponse = self.load(&format!("team/{}/space", team_id)).await?;
+        Ok(response.spaces)
+    }
+
+    async fn get_folders(&self, space_id: &str) -> Result<Vec<Folder>> {
+        let response: FoldersResponse = self.load(&format!("space/{}/folder", space_id)).await?;
+        Ok(response.folders)
+    }
+
+    async fn get_lists_in_folder(&self, folder_id: &str) -> Result<Vec<List>> {
+        let response: ListsResponse = self.load(&format!("folder/{}/list", folder_id)).await?;
+        Ok(response.lists)
+    }
+
+    async fn get_folderless_lists(&self, space_id: &str) -> Result<Vec<List>> {
+        let response: ListsResponse = self.load(&format!("space/{}/list", space_id)).await?;
+        Ok(response.lists)
+    }
+
+    async fn get_list(&self, list_id: &str) -> Result<ListDetails> {
+        self.load(&format!("list/{}", list_id)).await
+    }
+
+    /// Filters by status, ignoring case like ClickUp; the other filters are
+    /// not applied
+    async fn get_tasks(
+        &self,
+        list_id: &str,
+        status: Option<&str>,
+        _filter: &TaskFilter,
+    ) -> Result<Vec<Task>> {
+        let response: TasksResponse = self.load(&format!("list/{}/task", list_id)).await?;
+        Ok(Self::with_overrides(response.tasks)
+            .into_iter()
+            .filter(|t| status.is_none_or(|s| t.status.status.eq_ignore_ascii_case(s)))
+            .collect())
+    }
+
+    /// Comments from `task/<id>/comment.json`, or none without that file
+    async fn get_comments(&self, task_id: &str) -> Result<Vec<Comment>> {
+        match self
+            .load::<CommentsResponse>(&format!("task/{}/comment", task_id))
+            .await
+        {
+            Ok(response) => Ok(response.comments),
+            Err(_) => Ok(Vec::new()),
+        }
+    }
+
+    async fn get_checklists(&self, task_id: &str) -> Result<Vec<Checklist>> {
+        Ok(self.find_task(task_id).await?.checklists)
+    }
+
+    async fn update_task_status(&self, task_id: &str, status: &str) -> Result<Task> {
+        let mut task = self.find_task(task_id).await?;
+        if let Ok(mut overrides) = STATUS_OVERRIDES.lock() {
+            overrides.insert(task_id.to_string(), status.to_string());
+        }
+        tracing::info!("[fixtures] Task {} moved to '{}'", task_id, status);
+        task.status.status = status.to_string();
+        Ok(task)
+    }
+
+    async fn create_task(&self, list_id: &str, request: &CreateTaskRequest) -> Result<Task> {
+        tracing::info!("[fixtures] Created task '{}' in list {}", request.name, list_id);
+        let priority = request.priority.map(|p| TaskPriority {
+            id: Some(p.to_string()),
+            priority: match p {
+                1 => Some("urgent".to_string()),
+                2 => Some("high".to_string()),
+                3 => Some("normal".to_string()),
+                4 => Some("low".to_string()),
+                _ => None,
+            },
+            color: None,
+        });
+        Ok(Task {
+            id: format!("fixture-{}", chrono::Utc::now().timestamp_millis()),
+            name: request.name.clone(),
+            description: request.description.clone(),
+            status: TaskStatus {
+                status: request.status.clone().unwrap_or_else(|| "to do".to_string()),
+                color: None,
+                status_type: None,
+            },
+            priority,
+            list: TaskList {
+                id: list_id.to_string(),
+                name: None,
+            },
+            custom_fields: Vec::new(),
+            tags: Vec::new(),
+            checklists: Vec::new(),
+        })
+    }
+
+    async fn add_time_entry(
+        &self,
+        task_id: &str,
+        start_ms: i64,
+        end_ms: i64,
+        duration_ms: i64,
+    ) -> Result<serde_json::Value> {
+        tracing::info!(
+            "[fixtures] Time entry of {}ms on task {}",
+            duration_ms,
+            task_id
+        );
+        Ok(serde_json::json!({
+            "data": { "start": start_ms, "end": end_ms, "duration": duration_ms }
+        }))
+    }
+}
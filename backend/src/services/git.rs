@@ -0,0 +1,265 @@
+//! Git helpers shared by the poller and controllers
+
+use sea_orm::DatabaseConnection;
+use serde::Serialize;
+use std::process::Output;
+use std::time::Duration;
+use tokio::process::Command;
+use tokio::sync::Mutex;
+
+use crate::models::_entities::settings;
+
+/// Commit identity used when the `git_author_*` settings are unset
+const DEFAULT_AUTHOR_NAME: &str = "ClickUp Orchestrator";
+const DEFAULT_AUTHOR_EMAIL: &str = "bot@local";
+
+/// Attempts made for `git worktree add` when it hits a lock conflict
+const WORKTREE_ADD_ATTEMPTS: u32 = 3;
+
+/// Base delay between worktree creation attempts, multiplied by the attempt number
+const WORKTREE_ADD_BACKOFF: Duration = Duration::from_millis(500);
+
+lazy_static::lazy_static! {
+    /// Serializes worktree creation so concurrent `git` calls don't fight over index.lock
+    static ref WORKTREE_LOCK: Mutex<()> = Mutex::new(());
+}
+
+/// Get the configured `git_ssh_command` setting, e.g. `ssh -i /path/to/key`
+pub async fn ssh_command(db: &DatabaseConnection) -> Option<String> {
+    settings::Entity::get_value(db, "git_ssh_command").await
+}
+
+/// Identity used for commits made in task worktrees
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitAuthor {
+    pub name: String,
+    pub email: String,
+}
+
+impl GitAuthor {
+    /// Environment that makes `git commit` use this identity as both author
+    /// and committer, whatever the host's git config says
+    pub fn env(&self) -> [(&'static str, &str); 4] {
+        [
+            ("GIT_AUTHOR_NAME", &self.name),
+            ("GIT_AUTHOR_EMAIL", &self.email),
+            ("GIT_COMMITTER_NAME", &self.name),
+            ("GIT_COMMITTER_EMAIL", &self.email),
+        ]
+    }
+}
+
+/// Get the commit identity from the `git_author_name` and `git_author_email`
+/// settings, defaulting to `ClickUp Orchestrator <bot@local>`
+pub async fn author(db: &DatabaseConnection) -> GitAuthor {
+    GitAuthor {
+        name: settings::Entity::get_value(db, "git_author_name")
+            .await
+            .unwrap_or_else(|| DEFAULT_AUTHOR_NAME.to_string()),
+        email: settings::Entity::get_value(db, "git_author_email")
+            .await
+            .unwrap_or_else(|| DEFAULT_AUTHOR_EMAIL.to_string()),
+    }
+}
+
+/// Build a `git` command that uses `ssh_command` for remote operations
+pub fn command(ssh_command: Option<&str>) -> Command {
+    let mut command = Command::new("git");
+    if let Some(ssh_command) = ssh_command {
+        command.env("GIT_SSH_COMMAND", ssh_command);
+    }
+    command
+}
+
+/// Whether git failed because another process held a lock file
+fn is_lock_conflict(stderr: &[u8]) -> bool {
+    let stderr = String::from_utf8_lossy(stderr);
+    stderr.contains(".lock") || stderr.contains("Another git process")
+}
+
+/// Create a worktree with a new branch based on `base_branch`
+///
+/// Creation is serialized across the process and retried with backoff when
+/// git reports a lock conflict. Other failures, such as a missing base branch,
+/// are returned immediately.
+pub async fn add_worktree(
+    repo_path: &str,
+    branch: &str,
+    worktree_path: &str,
+    base_branch: &str,
+    ssh_command: Option<&str>,
+) -> std::io::Result<Output> {
+    run_worktree_add(
+        repo_path,
+        &["-b", branch, worktree_path, base_branch],
+        ssh_command,
+    )
+    .await
+}
+
+/// Create a worktree that checks out the existing `branch`
+///
+/// Serialized and retried like [`add_worktree`].
+pub async fn checkout_worktree(
+    repo_path: &str,
+    branch: &str,
+    worktree_path: &str,
+    ssh_command: Option<&str>,
+) -> std::io::Result<Output> {
+    run_worktree_add(repo_path, &[worktree_path, branch], ssh_command).await
+}
+
+/// Whether a local branch named `branch` exists in the repo
+pub async fn branch_exists(repo_path: &str, branch: &str) -> bool {
+    Command::new("git")
+        .args([
+            "-C",
+            repo_path,
+            "rev-parse",
+            "--verify",
+            "--quiet",
+            &format!("refs/heads/{}", branch),
+        ])
+        .output()
+        .await
+        .is_ok_and(|o| o.status.success())
+}
+
+/// Discover the default branch of the repo's `origin` remote, e.g. `main`
+///
+/// Reads `refs/remotes/origin/HEAD`, which `git clone` sets up. Without it a
+/// local `main` or `master` branch is assumed to be the default.
+pub async fn detect_default_branch(repo_path: &str) -> Option<String> {
+    let output = Command::new("git")
+        .args(["-C", repo_path, "symbolic-ref", "--short", "refs/remotes/origin/HEAD"])
+        .output()
+        .await
+        .ok()?;
+    if output.status.success() {
+        let head = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if let Some(branch) = head.strip_prefix("origin/").filter(|b| !b.is_empty()) {
+            return Some(branch.to_string());
+        }
+    }
+
+    for branch in ["main", "master"] {
+        if branch_exists(repo_path, branch).await {
+            return Some(branch.to_string());
+        }
+    }
+    None
+}
+
+/// Whether the worktree's branch has an upstream that contains all of its commits
+pub async fn is_pushed(worktree_path: &str) -> bool {
+    let output = Command::new("git")
+        .args(["-C", worktree_path, "rev-list", "--count", "@{upstream}..HEAD"])
+        .output()
+        .await;
+    match output {
+        Ok(output) if output.status.success() => {
+            String::from_utf8_lossy(&output.stdout).trim() == "0"
+        }
+        _ => false,
+    }
+}
+
+/// Size of a diff as reported by `git diff --shortstat`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct DiffStat {
+    pub files_changed: u64,
+    pub insertions: u64,
+    pub deletions: u64,
+}
+
+/// Parse `git diff --shortstat` output, e.g. ` 3 files changed, 10 insertions(+), 2 deletions(-)`
+///
+/// Git omits the parts that are zero, and prints nothing for an empty diff.
+pub fn parse_shortstat(output: &str) -> DiffStat {
+    let mut stat = DiffStat::default();
+    for part in output.trim().split(',') {
+        let mut words = part.split_whitespace();
+        let (Some(count), Some(kind)) = (words.next(), words.next()) else {
+            continue;
+        };
+        let Ok(count) = count.parse() else {
+            continue;
+        };
+        if kind.starts_with("file") {
+            stat.files_changed = count;
+        } else if kind.starts_with("insertion") {
+            stat.insertions = count;
+        } else if kind.starts_with("deletion") {
+            stat.deletions = count;
+        }
+    }
+    stat
+}
+
+/// Diff statistics of the worktree's `HEAD` against its merge-base with `base_branch`
+///
+/// `--end-of-options` keeps a base branch starting with `-` from being read as a flag.
+pub async fn diff_shortstat(worktree_path: &str, base_branch: &str) -> Result<DiffStat, String> {
+    let range = format!("{}...HEAD", base_branch);
+    let output = Command::new("git")
+        .args(["-C", worktree_path, "diff", "--shortstat", "--end-of-options", &range])
+        .kill_on_drop(true)
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run git diff: {}", e))?;
+
+    if output.status.success() {
+        Ok(parse_shortstat(&String::from_utf8_lossy(&output.stdout)))
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
+
+/// Force-remove a worktree, running from the repo so worktrees outside it are found
+pub async fn remove_worktree(repo_path: &str, worktree_path: &str) -> Result<(), String> {
+    let output = Command::new("git")
+        .args(["-C", repo_path, "worktree", "remove", "--force", worktree_path])
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run git worktree remove: {}", e))?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
+
+/// Run `git worktree add <args>` under the worktree lock, retrying lock conflicts
+async fn run_worktree_add(
+    repo_path: &str,
+    args: &[&str],
+    ssh_command: Option<&str>,
+) -> std::io::Result<Output> {
+    let _guard = WORKTREE_LOCK.lock().await;
+
+    let mut attempt = 1;
+    loop {
+        let output = command(ssh_command)
+            .args(["-C", repo_path, "worktree", "add"])
+            .args(args)
+            .output()
+            .await?;
+
+        if output.status.success()
+            || attempt >= WORKTREE_ADD_ATTEMPTS
+            || !is_lock_conflict(&output.stderr)
+        {
+            return Ok(output);
+        }
+
+        tracing::warn!(
+            "git worktree add hit a lock conflict (attempt {}/{}), retrying: {}",
+            attempt,
+            WORKTREE_ADD_ATTEMPTS,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+        tokio::time::sleep(WORKTREE_ADD_BACKOFF * attempt).await;
+        attempt += 1;
+    }
+}
@@ -0,0 +1,36 @@
+//! Process-wide counters exposed by the Prometheus metrics endpoint
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Completed poller cycles
+pub static POLLER_CYCLES_TOTAL: AtomicU64 = AtomicU64::new(0);
+
+/// Failed ClickUp API requests
+pub static CLICKUP_API_ERRORS_TOTAL: AtomicU64 = AtomicU64::new(0);
+
+/// Agent processes spawned
+pub static AGENT_SPAWNS_TOTAL: AtomicU64 = AtomicU64::new(0);
+
+/// Agent processes that exited with code 0
+pub static AGENT_EXITS_SUCCESS_TOTAL: AtomicU64 = AtomicU64::new(0);
+
+/// Agent processes that exited with a non-zero code
+pub static AGENT_EXITS_FAILURE_TOTAL: AtomicU64 = AtomicU64::new(0);
+
+/// Output lines dropped because a subscriber fell behind the output channel
+pub static OUTPUT_LINES_DROPPED_TOTAL: AtomicU64 = AtomicU64::new(0);
+
+/// Increment a counter by one
+pub fn inc(counter: &AtomicU64) {
+    counter.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Increment a counter by `n`
+pub fn add(counter: &AtomicU64, n: u64) {
+    counter.fetch_add(n, Ordering::Relaxed);
+}
+
+/// Read the current value of a counter
+pub fn get(counter: &AtomicU64) -> u64 {
+    counter.load(Ordering::Relaxed)
+}
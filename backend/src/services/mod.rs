@@ -1,2 +1,9 @@
+pub mod circuit_breaker;
 pub mod clickup;
+pub mod clickup_fixtures;
+pub mod git;
 pub mod process_manager;
+pub mod orchestrator;
+pub mod usage;
+pub mod redaction;
+pub mod metrics;
@@ -0,0 +1,948 @@
+//! Orchestration helpers that combine process management with task persistence
+
+use regex::Regex;
+use sea_orm::{ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, Set};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::time::Duration;
+use tokio::sync::{broadcast, Notify};
+
+use crate::models::_entities::{orchestrator_tasks, process_sessions, settings};
+use crate::services::clickup;
+use crate::services::git;
+use crate::services::process_manager::{self, ProcessError, SpawnOptions, PROCESS_MANAGER};
+
+/// Default number of retries after a transient agent spawn failure
+const DEFAULT_SPAWN_RETRIES: u32 = 2;
+
+/// Default number of ClickUp comments included in a task prompt
+const DEFAULT_PROMPT_COMMENTS: usize = 5;
+
+/// File name of the lock taken in a repository's worktrees directory
+const REPO_LOCK_FILE: &str = ".orchestrator.lock";
+
+/// Lock file for one worktrees directory, `file` is set while it is held
+struct RepoLock {
+    path: String,
+    file: Option<std::fs::File>,
+}
+
+/// Default time to wait for agents to exit after being killed
+const DEFAULT_SHUTDOWN_TIMEOUT_SECS: u64 = 5;
+
+/// Default seconds the post-task command may run
+const DEFAULT_POST_TASK_TIMEOUT_SECS: u64 = 600;
+
+/// Base branch used when `dev_branch` is unset and none can be detected
+const DEFAULT_DEV_BRANCH: &str = "dev";
+
+/// Agent type used for tasks when neither the task nor settings choose one
+const DEFAULT_AGENT_TYPE: &str = "claude";
+
+/// Branch name template used when `branch_template` is not set
+pub const DEFAULT_BRANCH_TEMPLATE: &str = "task/{id}-{name}";
+
+lazy_static::lazy_static! {
+    /// Wakes the poller to start queued tasks outside its regular interval
+    static ref SCHEDULE_REQUESTED: Notify = Notify::new();
+    /// The repository lock this process holds or last tried to take
+    static ref REPO_LOCK: std::sync::Mutex<Option<RepoLock>> = std::sync::Mutex::new(None);
+    /// Progress changes reported by running agents
+    static ref PROGRESS_TX: broadcast::Sender<TaskProgress> = broadcast::channel(256).0;
+    /// Task lifecycle events for the dashboard
+    static ref TASK_EVENTS_TX: broadcast::Sender<TaskEvent> = broadcast::channel(256).0;
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskEventKind {
+    /// The poller claimed a new task
+    Created,
+    /// A task moved to another status, e.g. started or failed to start
+    StatusChanged,
+    /// A task's agent finished, `status` holds the outcome
+    Completed,
+}
+
+/// A change to a task, streamed to dashboards over `/ws/events`
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskEvent {
+    #[serde(rename = "type")]
+    pub kind: TaskEventKind,
+    pub task_id: i32,
+    pub status: String,
+}
+
+/// Announce a task change to dashboard subscribers
+pub fn publish_task_event(kind: TaskEventKind, task_id: i32, status: &str) {
+    // No subscribers is fine, the dashboard refetches when it connects
+    let _ = TASK_EVENTS_TX.send(TaskEvent {
+        kind,
+        task_id,
+        status: status.to_string(),
+    });
+}
+
+/// Subscribe to task events of all tasks
+pub fn subscribe_task_events() -> broadcast::Receiver<TaskEvent> {
+    TASK_EVENTS_TX.subscribe()
+}
+
+/// A task's progress as last reported by its agent
+#[derive(Debug, Clone, Copy)]
+pub struct TaskProgress {
+    pub task_id: i32,
+    /// Percent complete, 0 to 100
+    pub percent: i32,
+}
+
+/// Announce a progress change to subscribers such as terminal websockets
+pub fn publish_progress(progress: TaskProgress) {
+    // No subscribers is fine, the value is persisted separately
+    let _ = PROGRESS_TX.send(progress);
+}
+
+/// Subscribe to progress changes of all tasks
+pub fn subscribe_progress() -> broadcast::Receiver<TaskProgress> {
+    PROGRESS_TX.subscribe()
+}
+
+/// Pattern matching progress lines in agent output (`progress_marker`)
+///
+/// Unset disables progress tracking, an invalid pattern is ignored with a warning.
+pub async fn progress_pattern(db: &DatabaseConnection) -> Option<Regex> {
+    let pattern = settings::Entity::get_value(db, "progress_marker").await?;
+    Regex::new(&pattern)
+        .inspect_err(|e| tracing::warn!("Invalid progress_marker pattern, ignoring: {}", e))
+        .ok()
+}
+
+/// Extract a percentage from an output line matching the progress pattern
+///
+/// A pattern with two capture groups reports a step fraction, e.g.
+/// `step (\d+) of (\d+)`; with one group it reports a percentage directly.
+/// The result is clamped to 0–100.
+pub fn parse_progress(pattern: &Regex, line: &str) -> Option<i32> {
+    let captures = pattern.captures(line)?;
+    let number = |i: usize| captures.get(i)?.as_str().trim().parse::<f64>().ok();
+    let percent = match (number(1), number(2)) {
+        (Some(step), Some(total)) if total > 0.0 => step / total * 100.0,
+        (Some(percent), None) if captures.len() == 2 => percent,
+        _ => return None,
+    };
+    Some(percent.round().clamp(0.0, 100.0) as i32)
+}
+
+/// Ask the poller to re-evaluate queued tasks, e.g. after a task finished
+pub fn request_schedule() {
+    SCHEDULE_REQUESTED.notify_one();
+}
+
+/// Wait until queued tasks should be re-evaluated
+pub async fn schedule_requested() {
+    SCHEDULE_REQUESTED.notified().await;
+}
+
+/// Get queued tasks in the order they should start
+///
+/// Tasks with a manual `queue_position` come first, the rest follow by
+/// priority (1=urgent first) and then age.
+pub async fn queued_tasks(
+    db: &DatabaseConnection,
+) -> Result<Vec<orchestrator_tasks::Model>, sea_orm::DbErr> {
+    let mut queued = orchestrator_tasks::Entity::find()
+        .filter(orchestrator_tasks::Column::Status.eq("queued"))
+        .all(db)
+        .await?;
+
+    queued.sort_by_key(|t| {
+        (
+            t.queue_position.is_none(),
+            t.queue_position,
+            t.priority.unwrap_or(99),
+            t.created_at,
+        )
+    });
+
+    Ok(queued)
+}
+
+/// Where a queued task's dependency stands
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DependencyState {
+    Completed,
+    /// Not finished yet, or not synced from ClickUp yet
+    Pending,
+    /// Failed or abandoned, with that status; it will not complete on its own
+    Blocked(String),
+}
+
+/// State of the task with the given ClickUp id as a dependency
+pub async fn dependency_state(db: &DatabaseConnection, clickup_task_id: &str) -> DependencyState {
+    let status = orchestrator_tasks::Entity::find()
+        .filter(orchestrator_tasks::Column::ClickupTaskId.eq(clickup_task_id))
+        .one(db)
+        .await
+        .ok()
+        .flatten()
+        .map(|t| t.status);
+    match status.as_deref() {
+        Some("completed") => DependencyState::Completed,
+        Some(status @ ("failed" | "abandoned")) => DependencyState::Blocked(status.to_string()),
+        _ => DependencyState::Pending,
+    }
+}
+
+/// Whether following dependencies from `depends_on` leads back to `clickup_task_id`
+pub async fn has_dependency_cycle(
+    db: &DatabaseConnection,
+    clickup_task_id: &str,
+    depends_on: &str,
+) -> bool {
+    let mut visited = HashSet::new();
+    let mut current = depends_on.to_string();
+
+    loop {
+        if current == clickup_task_id {
+            return true;
+        }
+        if !visited.insert(current.clone()) {
+            // A cycle that doesn't involve this task, it is not ours to reject
+            return false;
+        }
+
+        let next = orchestrator_tasks::Entity::find()
+            .filter(orchestrator_tasks::Column::ClickupTaskId.eq(&current))
+            .one(db)
+            .await
+            .ok()
+            .flatten()
+            .and_then(|t| t.depends_on_clickup_id);
+
+        match next {
+            Some(next) => current = next,
+            None => return false,
+        }
+    }
+}
+
+/// Directory under which task worktrees are created for a repository
+///
+/// Defaults to `<repo>/worktrees`. When `worktrees_root` is set, worktrees go to
+/// `<worktrees_root>/<repo-name>` instead, keeping them out of the repo tree.
+pub fn worktrees_dir(repo_path: &str, worktrees_root: Option<&str>) -> String {
+    match worktrees_root {
+        Some(root) => {
+            let repo_name = std::path::Path::new(repo_path.trim_end_matches('/'))
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| "repo".to_string());
+            format!("{}/{}", root.trim_end_matches('/'), repo_name)
+        }
+        None => format!("{}/worktrees", repo_path),
+    }
+}
+
+/// Sanitize a task name for use in worktree and branch names
+pub fn worktree_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '-' })
+        .collect::<String>()
+        .to_lowercase()
+}
+
+/// Whether `name` is a legal git branch name, following `git check-ref-format`
+pub fn is_valid_branch_name(name: &str) -> bool {
+    !name.is_empty()
+        && name != "@"
+        && !name.starts_with('-')
+        && !name.ends_with('/')
+        && !name.ends_with('.')
+        && !name.contains("..")
+        && !name.contains("//")
+        && !name.contains("@{")
+        && !name
+            .chars()
+            .any(|c| c.is_control() || " ~^:?*[\\".contains(c))
+        && name
+            .split('/')
+            .all(|part| !part.starts_with('.') && !part.ends_with(".lock"))
+}
+
+/// Make `name` a legal git branch name by replacing or dropping what git rejects
+fn sanitize_branch_name(name: &str) -> String {
+    let mut name: String = name
+        .chars()
+        .map(|c| if c.is_control() || " ~^:?*[\\".contains(c) { '-' } else { c })
+        .collect();
+    while name.contains("..") {
+        name = name.replace("..", ".");
+    }
+    name = name.replace("@{", "-");
+
+    let parts: Vec<&str> = name
+        .split('/')
+        .map(|part| part.trim_start_matches('.'))
+        .map(|part| part.strip_suffix(".lock").unwrap_or(part))
+        .filter(|part| !part.is_empty())
+        .collect();
+
+    parts
+        .join("/")
+        .trim_start_matches('-')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+/// Render a branch name template for a task
+///
+/// Supports `{id}` (the ClickUp task id), `{name}` (the sanitized task name)
+/// and `{priority}` (empty when unset). Fails when the result is not a legal
+/// branch name even after sanitizing.
+pub fn render_branch_name(template: &str, task: &orchestrator_tasks::Model) -> Result<String, String> {
+    let rendered = template
+        .replace("{id}", &task.clickup_task_id)
+        .replace("{name}", &worktree_name(&task.name))
+        .replace(
+            "{priority}",
+            &task.priority.map(|p| p.to_string()).unwrap_or_default(),
+        );
+    let branch = sanitize_branch_name(&rendered);
+
+    if !is_valid_branch_name(&branch) {
+        return Err(format!(
+            "Branch template '{}' produced an invalid branch name '{}'",
+            template, rendered
+        ));
+    }
+    Ok(branch)
+}
+
+/// The `branch_template` setting, or the default `task/{id}-{name}`
+pub async fn branch_template(db: &DatabaseConnection) -> String {
+    settings::Entity::get_value(db, "branch_template")
+        .await
+        .unwrap_or_else(|| DEFAULT_BRANCH_TEMPLATE.to_string())
+}
+
+/// Fixed text at the start of branch names made from `template`, e.g. `task/`
+pub fn branch_prefix(template: &str) -> &str {
+    template.split('{').next().unwrap_or_default()
+}
+
+/// Whether `path` is relative and cannot leave the directory it is joined to
+pub fn is_contained_relative(path: &std::path::Path) -> bool {
+    path.components()
+        .all(|c| matches!(c, std::path::Component::Normal(_)))
+}
+
+/// Join a worktree-relative path onto the worktree, `None` if it would leave it
+fn worktree_file(worktree_path: &str, relative: Option<&str>, kind: &str) -> Option<String> {
+    let relative = std::path::Path::new(relative.map(str::trim).filter(|f| !f.is_empty())?);
+    if !is_contained_relative(relative) {
+        tracing::warn!("Ignoring {} outside the worktree: {}", kind, relative.display());
+        return None;
+    }
+    Some(std::path::Path::new(worktree_path).join(relative).to_string_lossy().to_string())
+}
+
+/// Full path of a task's env file, which is relative to its worktree
+///
+/// `None` when the task has no env file or the path would leave the worktree.
+pub fn task_env_file(worktree_path: &str, env_file: Option<&str>) -> Option<String> {
+    worktree_file(worktree_path, env_file, "env file")
+}
+
+/// Output log location used when `output_file_path` is unset
+const DEFAULT_OUTPUT_FILE: &str = ".orchestrator/output.log";
+
+/// Worktree-relative file agent output is copied to
+///
+/// `None` unless `write_output_files` is "true". The path comes from
+/// `output_file_path`, defaulting to `.orchestrator/output.log`.
+pub async fn output_file_path(db: &DatabaseConnection) -> Option<String> {
+    let enabled = settings::Entity::get_value(db, "write_output_files")
+        .await
+        .is_some_and(|v| v == "true");
+    if !enabled {
+        return None;
+    }
+    Some(
+        settings::Entity::get_value(db, "output_file_path")
+            .await
+            .unwrap_or_else(|| DEFAULT_OUTPUT_FILE.to_string()),
+    )
+}
+
+/// Full path of a task's output log, see [`output_file_path`]
+pub fn task_output_file(worktree_path: &str, output_file: Option<&str>) -> Option<String> {
+    worktree_file(worktree_path, output_file, "output file")
+}
+
+/// Directory the agent runs in: the worktree root or a subdirectory of it
+///
+/// `working_subdir` must be a relative path inside the worktree that exists.
+pub fn agent_working_dir(worktree_path: &str, working_subdir: Option<&str>) -> Result<String, String> {
+    let Some(subdir) = working_subdir.map(|s| s.trim().trim_matches('/')).filter(|s| !s.is_empty())
+    else {
+        return Ok(worktree_path.to_string());
+    };
+
+    let relative = std::path::Path::new(subdir);
+    if !is_contained_relative(relative) {
+        return Err(format!("Invalid working subdirectory: {}", subdir));
+    }
+
+    let dir = std::path::Path::new(worktree_path).join(relative);
+    if !dir.is_dir() {
+        return Err(format!(
+            "Working subdirectory does not exist in worktree: {}",
+            subdir
+        ));
+    }
+
+    Ok(dir.to_string_lossy().to_string())
+}
+
+/// Branch task worktrees are based on by default
+///
+/// The `dev_branch` setting when set, otherwise the target repo's detected
+/// default branch, falling back to `dev`.
+pub async fn dev_branch(db: &DatabaseConnection) -> String {
+    if let Some(branch) = settings::Entity::get_value(db, "dev_branch").await {
+        return branch;
+    }
+    if let Some(repo_path) = settings::Entity::get_value(db, "target_repo_path").await {
+        if let Some(branch) = git::detect_default_branch(&repo_path).await {
+            return branch;
+        }
+    }
+    DEFAULT_DEV_BRANCH.to_string()
+}
+
+/// Resolve the worktrees directory for a repository from settings
+pub async fn configured_worktrees_dir(db: &DatabaseConnection, repo_path: &str) -> String {
+    let worktrees_root = settings::Entity::get_value(db, "worktrees_root").await;
+    worktrees_dir(repo_path, worktrees_root.as_deref())
+}
+
+/// How long the post-task command may run (`post_task_timeout_secs`)
+pub async fn post_task_timeout(db: &DatabaseConnection) -> Duration {
+    let secs = settings::Entity::get_value(db, "post_task_timeout_secs")
+        .await
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_POST_TASK_TIMEOUT_SECS);
+    Duration::from_secs(secs)
+}
+
+/// Free space, in MB, required on the worktrees filesystem to create a worktree
+///
+/// Read from `min_free_disk_mb`; unset or 0 disables the check.
+pub async fn min_free_disk_mb(db: &DatabaseConnection) -> Option<u64> {
+    settings::Entity::get_value(db, "min_free_disk_mb")
+        .await
+        .and_then(|v| v.parse().ok())
+        .filter(|mb| *mb > 0)
+}
+
+/// Available space in MB from `df -Pk` output for a single path
+pub fn parse_df_available_mb(output: &str) -> Option<u64> {
+    let kilobytes: u64 = output.lines().nth(1)?.split_whitespace().nth(3)?.parse().ok()?;
+    Some(kilobytes / 1024)
+}
+
+/// Refuse with an "insufficient disk" message when `dir` has less than `min_mb` free
+///
+/// When `df` can't tell, the check passes; git reports a full disk on its own.
+pub async fn ensure_free_disk(dir: &str, min_mb: u64) -> Result<(), String> {
+    let output = tokio::process::Command::new("df")
+        .args(["-Pk", dir])
+        .output()
+        .await;
+    let available = match output {
+        Ok(output) if output.status.success() => {
+            parse_df_available_mb(&String::from_utf8_lossy(&output.stdout))
+        }
+        Ok(output) => {
+            tracing::warn!(
+                "df failed for {}: {}",
+                dir,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+            None
+        }
+        Err(e) => {
+            tracing::warn!("Failed to run df for {}: {}", dir, e);
+            None
+        }
+    };
+
+    match available {
+        Some(available) if available < min_mb => Err(format!(
+            "Insufficient disk space: {} MB free in {}, min_free_disk_mb is {}",
+            available, dir, min_mb
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Timezone timestamps are shown in (`display_timezone`, an IANA name)
+///
+/// Only affects presentation, timestamps are stored in UTC. Unset or unknown
+/// names fall back to UTC, the latter with a warning.
+pub async fn display_timezone(db: &DatabaseConnection) -> chrono_tz::Tz {
+    let Some(name) = settings::Entity::get_value(db, "display_timezone").await else {
+        return chrono_tz::UTC;
+    };
+    name.trim().parse().unwrap_or_else(|e| {
+        tracing::warn!("Invalid display_timezone '{}', using UTC: {}", name, e);
+        chrono_tz::UTC
+    })
+}
+
+/// Agent type used for tasks that don't pick one (`default_agent_type`)
+pub async fn default_agent_type(db: &DatabaseConnection) -> String {
+    settings::Entity::get_value(db, "default_agent_type")
+        .await
+        .unwrap_or_else(|| DEFAULT_AGENT_TYPE.to_string())
+}
+
+/// Agent type a task runs with
+///
+/// The task's own `agent_type` wins, then the `default_agent_type` setting.
+pub async fn task_agent_type(db: &DatabaseConnection, task: &orchestrator_tasks::Model) -> String {
+    match &task.agent_type {
+        Some(agent) => agent.clone(),
+        None => default_agent_type(db).await,
+    }
+}
+
+/// Instructions added to task prompts for the given agent type
+///
+/// `agent_prompt_<agent>` (e.g. `agent_prompt_codex`) takes precedence over the
+/// generic `agent_prompt`.
+pub async fn agent_prompt(db: &DatabaseConnection, agent: &str) -> Option<String> {
+    match settings::Entity::get_value(db, &format!("agent_prompt_{}", agent)).await {
+        Some(prompt) => Some(prompt),
+        None => settings::Entity::get_value(db, "agent_prompt").await,
+    }
+}
+
+/// Compose the prompt sent to a task's agent
+///
+/// The task description (or a fallback naming the task), then the ClickUp
+/// checklist, discussion and the agent instructions when there are any.
+pub fn build_task_prompt(
+    task: &orchestrator_tasks::Model,
+    agent_prompt: Option<&str>,
+    checklist: Option<&str>,
+    discussion: Option<&str>,
+) -> String {
+    let task_description = task
+        .description
+        .clone()
+        .unwrap_or_else(|| format!("Complete task: {}", task.name));
+
+    let checklist = checklist.filter(|c| !c.is_empty());
+    let discussion = discussion.filter(|d| !d.is_empty());
+    let instructions = agent_prompt.filter(|i| !i.is_empty());
+    if checklist.is_none() && discussion.is_none() && instructions.is_none() {
+        return task_description;
+    }
+
+    let mut prompt = format!("## Task\n{}", task_description);
+    if let Some(checklist) = checklist {
+        prompt.push_str(&format!("\n\n## Checklist\n{}", checklist));
+    }
+    if let Some(discussion) = discussion {
+        prompt.push_str(&format!("\n\n## Discussion\n{}", discussion));
+    }
+    if let Some(instructions) = instructions {
+        prompt.push_str(&format!("\n\n## Instructions\n{}", instructions));
+    }
+    prompt
+}
+
+/// Build a task's prompt from its agent's instructions and, when enabled, its comments
+///
+/// The `prompt_footer` setting, e.g. a reminder to commit the work, is
+/// appended after everything else when set.
+pub async fn compose_task_prompt(db: &DatabaseConnection, task: &orchestrator_tasks::Model) -> String {
+    let agent = task_agent_type(db, task).await;
+    let agent_prompt = agent_prompt(db, &agent).await;
+    let checklist = task_checklist(db, &task.clickup_task_id).await;
+    let discussion = task_discussion(db, &task.clickup_task_id).await;
+    let mut prompt = build_task_prompt(
+        task,
+        agent_prompt.as_deref(),
+        checklist.as_deref(),
+        discussion.as_deref(),
+    );
+    let footer = settings::Entity::get_value(db, "prompt_footer").await;
+    if let Some(footer) = footer.as_deref().map(str::trim).filter(|f| !f.is_empty()) {
+        prompt.push_str(&format!("\n\n{}", footer));
+    }
+    prompt
+}
+
+/// The task's ClickUp checklist items as a markdown task list
+///
+/// Only fetched when `include_checklists_in_prompt` is "true", since it costs
+/// an API call per spawn. Resolved items are checked, several checklists get a
+/// heading each. Failures to fetch are logged and leave the checklist out.
+async fn task_checklist(db: &DatabaseConnection, clickup_task_id: &str) -> Option<String> {
+    let enabled = settings::Entity::get_value(db, "include_checklists_in_prompt")
+        .await
+        .is_some_and(|v| v == "true");
+    if !enabled || clickup::is_manual_task(clickup_task_id) {
+        return None;
+    }
+
+    let checklists = match clickup::client_from_env() {
+        Ok(client) => client.get_checklists(clickup_task_id).await,
+        Err(e) => Err(e),
+    };
+    let checklists = match checklists {
+        Ok(checklists) => checklists,
+        Err(e) => {
+            tracing::warn!("Failed to fetch checklists for task {}: {}", clickup_task_id, e);
+            return None;
+        }
+    };
+
+    let checklists: Vec<_> = checklists.iter().filter(|c| !c.items.is_empty()).collect();
+    let sections: Vec<String> = checklists
+        .iter()
+        .map(|checklist| {
+            let items = checklist
+                .items
+                .iter()
+                .map(|item| format!("- [{}] {}", if item.resolved { "x" } else { " " }, item.name))
+                .collect::<Vec<_>>()
+                .join("\n");
+            if checklists.len() > 1 {
+                format!("### {}\n{}", checklist.name, items)
+            } else {
+                items
+            }
+        })
+        .collect();
+
+    (!sections.is_empty()).then(|| sections.join("\n\n"))
+}
+
+/// The task's latest ClickUp comments as plain text, oldest first
+///
+/// Only fetched when `include_comments_in_prompt` is "true"; the number of
+/// comments comes from `prompt_comments_limit`. Failures to fetch are logged
+/// and leave the discussion out rather than blocking the run.
+async fn task_discussion(db: &DatabaseConnection, clickup_task_id: &str) -> Option<String> {
+    let enabled = settings::Entity::get_value(db, "include_comments_in_prompt")
+        .await
+        .is_some_and(|v| v == "true");
+    if !enabled || clickup::is_manual_task(clickup_task_id) {
+        return None;
+    }
+
+    let limit = settings::Entity::get_value(db, "prompt_comments_limit")
+        .await
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_PROMPT_COMMENTS);
+
+    let comments = match clickup::client_from_env() {
+        Ok(client) => client.get_comments(clickup_task_id).await,
+        Err(e) => Err(e),
+    };
+    let comments = match comments {
+        Ok(comments) => comments,
+        Err(e) => {
+            tracing::warn!("Failed to fetch comments for task {}: {}", clickup_task_id, e);
+            return None;
+        }
+    };
+
+    let entries: Vec<String> = comments
+        .iter()
+        .take(limit)
+        .rev()
+        .filter_map(|comment| {
+            let text = plain_comment_text(&comment.comment_text);
+            if text.is_empty() {
+                return None;
+            }
+            let author = comment
+                .user
+                .as_ref()
+                .and_then(|u| u.username.as_deref())
+                .unwrap_or("unknown");
+            Some(format!("**{}**: {}", author, text))
+        })
+        .collect();
+
+    (!entries.is_empty()).then(|| entries.join("\n\n"))
+}
+
+/// Reduce a comment to plain text: drop `@` from mentions and blank lines
+fn plain_comment_text(text: &str) -> String {
+    text.lines()
+        .map(|line| {
+            line.split(' ')
+                .map(|word| word.strip_prefix('@').filter(|w| !w.is_empty()).unwrap_or(word))
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Extra attempts after a transient agent spawn failure (`spawn_retry_count`)
+pub async fn spawn_retry_count(db: &DatabaseConnection) -> u32 {
+    settings::Entity::get_value(db, "spawn_retry_count")
+        .await
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_SPAWN_RETRIES)
+}
+
+/// How long an agent may go without output before it is killed
+///
+/// Read from `idle_timeout_secs`; unset or 0 disables the check.
+pub async fn idle_timeout(db: &DatabaseConnection) -> Option<Duration> {
+    settings::Entity::get_value(db, "idle_timeout_secs")
+        .await
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|secs| *secs > 0)
+        .map(Duration::from_secs)
+}
+
+/// Command template agents run inside, e.g. a `docker run` invocation
+///
+/// Read from `sandbox_command`; unset runs agents directly on the host. See
+/// [`sandboxed_command`](crate::services::process_manager::sandboxed_command).
+pub async fn sandbox_command(db: &DatabaseConnection) -> Option<String> {
+    settings::Entity::get_value(db, "sandbox_command").await
+}
+
+/// Whether claude should run with `--dangerously-skip-permissions`
+///
+/// On unless the `claude_skip_permissions` setting is "false". When off, claude
+/// runs non-interactively with `-p` and denies any tool call that needs
+/// approval, so the agent can only use the tools allowed by its own settings.
+pub async fn claude_skip_permissions(db: &DatabaseConnection) -> bool {
+    settings::Entity::get_value(db, "claude_skip_permissions")
+        .await
+        .map_or(true, |v| v != "false")
+}
+
+/// Hold the machine-wide lock for orchestrating the repo using `worktrees_dir`
+///
+/// Takes an exclusive `flock` on `<worktrees_dir>/.orchestrator.lock`, so a
+/// second server instance pointed at the same repo stays idle instead of
+/// creating duplicate worktrees. Returns true while this process holds it. A
+/// lock held for a different directory is released first; the OS releases it
+/// when the process exits.
+pub fn acquire_repo_lock(worktrees_dir: &str) -> bool {
+    let path = format!("{}/{}", worktrees_dir.trim_end_matches('/'), REPO_LOCK_FILE);
+    let mut state = REPO_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let first_attempt = match state.as_ref() {
+        Some(lock) if lock.path == path && lock.file.is_some() => return true,
+        Some(lock) => lock.path != path,
+        None => true,
+    };
+    // Dropping the previous file releases its lock
+    *state = None;
+
+    let file = std::fs::create_dir_all(worktrees_dir).and_then(|()| {
+        std::fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+    });
+    let file = match file {
+        Ok(file) => file,
+        Err(e) => {
+            tracing::error!("Failed to open repo lock {}: {}", path, e);
+            *state = Some(RepoLock { path, file: None });
+            return false;
+        }
+    };
+
+    match file.try_lock() {
+        Ok(()) => {
+            tracing::info!("Acquired repo lock {}", path);
+            *state = Some(RepoLock { path, file: Some(file) });
+            true
+        }
+        Err(e) => {
+            // Only warn once per path, the poller retries every cycle
+            if first_attempt {
+                tracing::warn!(
+                    "Repo lock {} is held by another orchestrator instance, staying idle: {}",
+                    path,
+                    e
+                );
+            }
+            *state = Some(RepoLock { path, file: None });
+            false
+        }
+    }
+}
+
+/// Release the repo lock, if held
+pub fn release_repo_lock() {
+    let mut state = REPO_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(RepoLock { path, file: Some(_) }) = state.take() {
+        tracing::info!("Released repo lock {}", path);
+    }
+}
+
+/// Kill all running agents and mark their tasks as stopped
+///
+/// Returns the IDs of the tasks that were stopped.
+pub async fn stop_all_agents(db: &DatabaseConnection, timeout: Duration) -> Vec<i32> {
+    let task_ids = PROCESS_MANAGER.kill_all(timeout).await;
+    if !task_ids.is_empty() {
+        mark_stopped(db, &task_ids).await;
+    }
+    task_ids
+}
+
+/// Mark tasks whose process was killed as stopped and close their sessions
+///
+/// Killed processes exit with [`ExitReason::Killed`](process_manager::ExitReason),
+/// which the process monitor leaves to the caller.
+pub async fn mark_stopped(db: &DatabaseConnection, task_ids: &[i32]) {
+    let now = chrono::Utc::now();
+
+    if let Err(e) = orchestrator_tasks::Entity::update_many()
+        .filter(orchestrator_tasks::Column::Id.is_in(task_ids.to_vec()))
+        .col_expr(
+            orchestrator_tasks::Column::Status,
+            sea_orm::sea_query::Expr::value("stopped"),
+        )
+        .col_expr(
+            orchestrator_tasks::Column::UpdatedAt,
+            sea_orm::sea_query::Expr::value(now),
+        )
+        .exec(db)
+        .await
+    {
+        tracing::error!("Failed to mark tasks as stopped: {}", e);
+    }
+    for task_id in task_ids {
+        publish_task_event(TaskEventKind::StatusChanged, *task_id, "stopped");
+    }
+
+    // Close any open process sessions
+    let _ = process_sessions::Entity::update_many()
+        .filter(process_sessions::Column::TaskId.is_in(task_ids.to_vec()))
+        .filter(process_sessions::Column::EndedAt.is_null())
+        .col_expr(
+            process_sessions::Column::EndedAt,
+            sea_orm::sea_query::Expr::value(now),
+        )
+        .exec(db)
+        .await;
+}
+
+/// Wait before automatically restarting a failed task, `None` once the cap is hit
+///
+/// `attempts` counts the automatic restarts so far. The wait starts at
+/// `backoff` and doubles with each attempt.
+pub fn auto_restart_delay(attempts: i32, max_attempts: i32, backoff: Duration) -> Option<Duration> {
+    if attempts >= max_attempts {
+        return None;
+    }
+    Some(backoff.saturating_mul(1u32 << attempts.clamp(0, 10)))
+}
+
+/// Spawn the agent in the task's worktree, mark it in progress and open a session
+///
+/// Used to restart a task whose worktree already exists; the agent continues
+/// its previous conversation when one was captured. `restart_count` is stored
+/// on the task: 0 for a manual run, the attempt number for an automatic restart.
+pub async fn spawn_in_worktree(
+    db: &DatabaseConnection,
+    task: orchestrator_tasks::Model,
+    prompt: &str,
+    worktree_path: &str,
+    restart_count: i32,
+) -> process_manager::Result<orchestrator_tasks::Model> {
+    let id = task.id;
+    let resume_session = task.agent_session_id.clone();
+
+    let working_dir = agent_working_dir(worktree_path, task.working_subdir.as_deref())
+        .map_err(ProcessError::InvalidWorkingDir)?;
+
+    let agent = task_agent_type(db, &task).await;
+    let sandbox = sandbox_command(db).await;
+    let env_file = task_env_file(worktree_path, task.env_file.as_deref());
+    let git_author = git::author(db).await;
+    let output_file_path = output_file_path(db).await;
+    let output_file = task_output_file(worktree_path, output_file_path.as_deref());
+    let options = SpawnOptions {
+        resume_session: resume_session.as_deref(),
+        skip_permissions: claude_skip_permissions(db).await,
+        retries: spawn_retry_count(db).await,
+        idle_timeout: idle_timeout(db).await,
+        sandbox: sandbox.as_deref(),
+        env_file: env_file.as_deref(),
+        git_author: Some(&git_author),
+        output_file: output_file.as_deref(),
+    };
+
+    let pid = PROCESS_MANAGER
+        .spawn_agent(id, &agent, prompt, &working_dir, options)
+        .await
+        .inspect_err(|e| tracing::error!("Failed to start task {}: {}", id, e))?;
+    tracing::info!("Started task {} with PID {}", id, pid);
+
+    let now = chrono::Utc::now();
+    let mut active: orchestrator_tasks::ActiveModel = task.into();
+    active.status = Set("in_progress".to_string());
+    active.started_at = Set(Some(now.into()));
+    active.progress = Set(None);
+    active.restart_count = Set(restart_count);
+    active.updated_at = Set(now.into());
+    let updated = active.update(db).await?;
+    publish_task_event(TaskEventKind::StatusChanged, id, &updated.status);
+
+    let session = process_sessions::ActiveModel {
+        task_id: Set(id),
+        pid: Set(Some(pid as i32)),
+        started_at: Set(now.into()),
+        ended_at: Set(None),
+        exit_code: Set(None),
+        created_at: Set(now.into()),
+        updated_at: Set(now.into()),
+        ..Default::default()
+    };
+    let _ = process_sessions::Entity::insert(session).exec(db).await;
+
+    Ok(updated)
+}
+
+/// Stop all agents and UI sessions on server shutdown so no orphaned processes hold worktrees
+///
+/// The wait is controlled by the `shutdown_timeout_secs` setting.
+pub async fn shutdown(db: &DatabaseConnection) {
+    let timeout_secs = settings::Entity::get_value(db, "shutdown_timeout_secs")
+        .await
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_SHUTDOWN_TIMEOUT_SECS);
+
+    let stopped = stop_all_agents(db, Duration::from_secs(timeout_secs)).await;
+    if !stopped.is_empty() {
+        tracing::info!("Stopped {} running agent(s) on shutdown: {:?}", stopped.len(), stopped);
+    }
+
+    let sessions = PROCESS_MANAGER.kill_all_sessions().await;
+    if !sessions.is_empty() {
+        tracing::info!("Killed {} UI session(s) on shutdown: {:?}", sessions.len(), sessions);
+    }
+
+    release_repo_lock();
+}
@@ -1,11 +1,355 @@
 //! Process Manager for spawning and managing CLI agent processes
 
+use dashmap::mapref::entry::Entry;
 use dashmap::DashMap;
-use std::sync::Arc;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use regex::Regex;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWriteExt, BufReader};
 use tokio::process::Command;
 use tokio::sync::{broadcast, mpsc};
 
+use crate::services::git::GitAuthor;
+use crate::services::metrics::{self, AGENT_SPAWNS_TOTAL};
+use crate::services::redaction::Redactor;
+
+/// Base delay between agent spawn attempts, multiplied by the attempt number
+const SPAWN_RETRY_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Number of trailing output lines kept per process for exit handling
+const OUTPUT_TAIL_LINES: usize = 200;
+
+/// Default number of output lines buffered for output subscribers
+///
+/// Each subscriber can fall this many lines behind before it starts losing
+/// output. A larger buffer tolerates chattier agents and slower clients at the
+/// cost of memory, since every buffered line is held until all subscribers
+/// have seen it. Override with `PROCESS_OUTPUT_BUFFER_LINES`.
+const DEFAULT_OUTPUT_BUFFER_LINES: usize = 1000;
+
+/// Maximum number of output lines kept per session process
+const SESSION_OUTPUT_MAX_LINES: usize = 10_000;
+
+/// How long a finished session's output stays available for polling
+const SESSION_OUTPUT_RETENTION: Duration = Duration::from_secs(600);
+
+/// How long to wait for room in a process's input queue
+const INPUT_SEND_TIMEOUT: Duration = Duration::from_secs(2);
+
+lazy_static::lazy_static! {
+    /// Matches the session id agents print, e.g. claude's `"session_id":"<uuid>"`
+    /// or codex's `session id: <uuid>`
+    static ref SESSION_ID_RE: Regex = Regex::new(
+        r#"(?i)session[ _-]?id["']?\s*[:=]\s*["']?([0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12})"#
+    )
+    .expect("session id pattern is valid");
+}
+
+#[derive(Error, Debug)]
+pub enum ProcessError {
+    /// The task or session (named in the payload) already has a process
+    #[error("{0} already has a running process")]
+    AlreadyRunning(String),
+    #[error("Working directory does not exist: {0}")]
+    WorkingDirMissing(String),
+    #[error("Unknown agent type: {0}")]
+    UnknownAgent(String),
+    #[error("The '{0}' command is not found in PATH. Please install it and ensure it's in your PATH.")]
+    AgentNotFound(String),
+    #[error("Failed to spawn process: {0}")]
+    SpawnFailed(#[from] std::io::Error),
+    /// No process is running for the task or session named in the payload
+    #[error("No process for {0}")]
+    NotFound(String),
+    #[error("Input buffer full for task {0}: the process is not reading its input")]
+    InputFull(i32),
+    /// The task's `working_subdir` can't be used, the payload says why
+    #[error("Invalid working directory: {0}")]
+    InvalidWorkingDir(String),
+    #[error("Database error: {0}")]
+    Database(#[from] sea_orm::DbErr),
+    /// Starting another session would exceed the session limit
+    #[error("Too many active sessions ({active} of {limit}), wait for one to finish or cancel it")]
+    TooManySessions { active: usize, limit: usize },
+}
+
+pub type Result<T> = std::result::Result<T, ProcessError>;
+
+/// Take a piped stdio stream from a freshly spawned child
+fn take_stream<T>(stream: Option<T>, name: &str) -> Result<T> {
+    stream.ok_or_else(|| {
+        ProcessError::SpawnFailed(std::io::Error::other(format!("Failed to get {}", name)))
+    })
+}
+
+/// CLI agents that can run tasks and sessions
+pub const AGENT_TYPES: &[&str] = &["claude", "codex", "gemini"];
+
+/// Add the arguments for a non-interactive run of `prompt` to an agent command
+///
+/// - claude: `[--resume <id>] -p <prompt> [--dangerously-skip-permissions]`
+/// - codex: `exec [resume <id>] <prompt> --full-auto`
+/// - gemini: `<prompt> -y`, with no resume by id in non-interactive mode
+pub fn push_agent_args(
+    command: &mut Command,
+    agent: &str,
+    prompt: &str,
+    resume_session: Option<&str>,
+    skip_permissions: bool,
+) {
+    match agent {
+        "codex" => {
+            command.arg("exec");
+            if let Some(id) = resume_session {
+                command.arg("resume").arg(id);
+            }
+            command.arg(prompt).arg("--full-auto");
+        }
+        "gemini" => {
+            if resume_session.is_some() {
+                tracing::debug!("Gemini does not support session resume, starting fresh");
+            }
+            command.arg(prompt).arg("-y");
+        }
+        _ => {
+            if let Some(id) = resume_session {
+                command.arg("--resume").arg(id);
+            }
+            command.arg("-p").arg(prompt);
+            if skip_permissions {
+                command.arg("--dangerously-skip-permissions");
+            }
+        }
+    }
+}
+
+/// Options for starting a task's agent
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SpawnOptions<'a> {
+    /// Conversation to continue instead of starting fresh
+    pub resume_session: Option<&'a str>,
+    /// Add claude's `--dangerously-skip-permissions`; without it tool calls that
+    /// need approval are denied, since `-p` runs cannot prompt
+    pub skip_permissions: bool,
+    /// Extra attempts after a transient spawn failure such as EAGAIN
+    pub retries: u32,
+    /// Kill the process when it writes nothing to stdout or stderr for this long
+    pub idle_timeout: Option<Duration>,
+    /// Run the agent inside this command template, see [`sandboxed_command`]
+    pub sandbox: Option<&'a str>,
+    /// `.env`-style file whose variables are set for the agent process; a
+    /// missing file is ignored. With a sandbox they are set on the sandbox
+    /// command, which has to pass them on (e.g. docker's `-e KEY`)
+    pub env_file: Option<&'a str>,
+    /// Identity for commits the agent makes, set through `GIT_AUTHOR_*` and
+    /// `GIT_COMMITTER_*` so the host's git config isn't used
+    pub git_author: Option<&'a GitAuthor>,
+    /// File every output line is appended to, created along with its directory
+    pub output_file: Option<&'a str>,
+}
+
+/// Parse `.env`-style `KEY=VALUE` lines
+///
+/// Blank lines and `#` comments are skipped, as is an `export ` prefix. Values
+/// may be wrapped in single or double quotes, which are removed.
+pub fn parse_env_file(content: &str) -> Vec<(String, String)> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let line = line.strip_prefix("export ").unwrap_or(line);
+            let (key, value) = line.split_once('=')?;
+            let key = key.trim();
+            if key.is_empty() || key.contains(char::is_whitespace) {
+                return None;
+            }
+            let value = value.trim();
+            let value = [('"', '"'), ('\'', '\'')]
+                .iter()
+                .find_map(|(open, close)| {
+                    value
+                        .strip_prefix(*open)
+                        .and_then(|v| v.strip_suffix(*close))
+                })
+                .unwrap_or(value);
+            Some((key.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+/// Read and parse an env file, treating a missing file as empty
+async fn load_env_file(path: &str) -> Vec<(String, String)> {
+    match tokio::fs::read_to_string(path).await {
+        Ok(content) => parse_env_file(&content),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => {
+            tracing::warn!("Failed to read env file {}: {}", path, e);
+            Vec::new()
+        }
+    }
+}
+
+/// On-disk copy of a task's combined output, shared by its stdout and stderr readers
+#[derive(Clone)]
+struct OutputFile(Arc<tokio::sync::Mutex<Option<tokio::fs::File>>>);
+
+impl OutputFile {
+    /// Open `path` for appending, logging and returning `None` on failure
+    ///
+    /// A directory created for the file gets a `.gitignore` ignoring everything
+    /// so agents don't commit their own logs.
+    async fn open(path: &str) -> Option<Self> {
+        if let Some(dir) = std::path::Path::new(path).parent() {
+            if !dir.as_os_str().is_empty() && !dir.exists() {
+                if let Err(e) = tokio::fs::create_dir_all(dir).await {
+                    tracing::warn!("Failed to create output directory {}: {}", dir.display(), e);
+                    return None;
+                }
+                let _ = tokio::fs::write(dir.join(".gitignore"), "*\n").await;
+            }
+        }
+
+        match tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await
+        {
+            Ok(file) => Some(Self(Arc::new(tokio::sync::Mutex::new(Some(file))))),
+            Err(e) => {
+                tracing::warn!("Failed to open output file {}: {}", path, e);
+                None
+            }
+        }
+    }
+
+    /// Append a line; after a write error the file is dropped and output
+    /// keeps streaming without it
+    async fn write_line(&self, line: &str) {
+        let mut file = self.0.lock().await;
+        if let Some(handle) = file.as_mut() {
+            if let Err(e) = handle.write_all(format!("{}\n", line).as_bytes()).await {
+                tracing::warn!("Failed to write output file, no longer writing it: {}", e);
+                *file = None;
+            }
+        }
+    }
+}
+
+/// Quote a value for safe use as a single `sh` word
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// Build a command that runs `agent` inside a sandbox command template
+///
+/// `{worktree}` and `{prompt}` in the template are replaced with the shell
+/// quoted working directory and prompt, then the agent and the arguments added
+/// afterwards (see [`push_agent_args`]) are appended. The agent always gets the
+/// prompt through those arguments; `{prompt}` is only for templates that need
+/// it for something else, such as labelling the container, so don't use it to
+/// pass the prompt to the agent a second time. For example
+/// `docker run --rm -i -v {worktree}:/work -w /work agent-image` runs
+/// `claude -p <prompt> …` in the container. The agent is looked up inside the
+/// sandbox, not on the host, and gets no PTY wrapper.
+///
+/// The command leads its own process group so [`force_kill`] reaches whatever
+/// the template started, not just `sh`.
+pub fn sandboxed_command(template: &str, worktree: &str, prompt: &str, agent: &str) -> Command {
+    let rendered = template
+        .replace("{worktree}", &shell_quote(worktree))
+        .replace("{prompt}", &shell_quote(prompt));
+    let mut command = Command::new("sh");
+    // Arguments after `sh` become "$@", so they reach the sandbox unquoted by hand
+    command
+        .arg("-c")
+        .arg(format!("{} \"$@\"", rendered))
+        .arg("sh")
+        .arg(agent)
+        .process_group(0);
+    command
+}
+
+/// Force-kill `pid`, and the process group it leads if it has one
+///
+/// A pid that does not lead a group has no group of that id, so only the
+/// process itself is killed.
+async fn force_kill(pid: u32) {
+    let _ = Command::new("kill")
+        .arg("-9")
+        .arg("--")
+        .arg(format!("-{}", pid))
+        .arg(pid.to_string())
+        .output()
+        .await;
+}
+
+/// Whether a spawn failure is transient and worth retrying
+///
+/// EAGAIN (`WouldBlock`) shows up when the machine is briefly out of processes
+/// or memory; a missing binary or bad working directory will fail the same way
+/// every time.
+fn is_retriable_spawn_error(e: &std::io::Error) -> bool {
+    matches!(
+        e.kind(),
+        std::io::ErrorKind::WouldBlock
+            | std::io::ErrorKind::Interrupted
+            | std::io::ErrorKind::ResourceBusy
+    )
+}
+
+/// Find the most recent agent conversation id in the output
+pub fn parse_agent_session_id(lines: &[String]) -> Option<String> {
+    lines
+        .iter()
+        .rev()
+        .find_map(|line| SESSION_ID_RE.captures(line))
+        .and_then(|caps| caps.get(1))
+        .map(|m| m.as_str().to_string())
+}
+
+/// Line reader that tolerates invalid UTF-8
+///
+/// `AsyncBufReadExt::lines` fails on the first invalid sequence, which ends
+/// the reader loop and silently drops the rest of the agent's output. This
+/// splits on raw newlines instead and decodes each line with
+/// `String::from_utf8_lossy`, so bad bytes become replacement characters.
+pub struct LossyLines<R> {
+    reader: BufReader<R>,
+    buf: Vec<u8>,
+}
+
+impl<R: AsyncRead + Unpin> LossyLines<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader: BufReader::new(reader),
+            buf: Vec::new(),
+        }
+    }
+
+    /// Next line without its `\n` or `\r\n` terminator, or `None` at EOF or on
+    /// a read error
+    pub async fn next_line(&mut self) -> Option<String> {
+        self.buf.clear();
+        match self.reader.read_until(b'\n', &mut self.buf).await {
+            Ok(0) | Err(_) => None,
+            Ok(_) => {
+                if self.buf.last() == Some(&b'\n') {
+                    self.buf.pop();
+                    if self.buf.last() == Some(&b'\r') {
+                        self.buf.pop();
+                    }
+                }
+                Some(String::from_utf8_lossy(&self.buf).into_owned())
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct OutputLine {
     pub task_id: i32,
@@ -13,22 +357,161 @@ pub struct OutputLine {
     pub is_stderr: bool,
 }
 
+/// Why a task process stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitReason {
+    /// The process exited on its own
+    Exited,
+    /// Killed after producing no output for the idle timeout
+    IdleTimeout,
+    /// Killed with [`ProcessManager::kill_process`]; whoever asked sets the task status
+    Killed,
+}
+
+/// Emitted once a process has exited and its output has been drained
+#[derive(Debug, Clone)]
+pub struct ProcessExit {
+    pub task_id: i32,
+    pub exit_code: i32,
+    pub reason: ExitReason,
+    /// The last lines of combined stdout/stderr output
+    pub output_tail: Vec<String>,
+}
+
+/// Bounded buffer of the most recent output lines of a process
+#[derive(Clone, Default)]
+struct OutputTail(Arc<Mutex<VecDeque<String>>>);
+
+impl OutputTail {
+    fn push(&self, line: &str) {
+        if let Ok(mut tail) = self.0.lock() {
+            if tail.len() == OUTPUT_TAIL_LINES {
+                tail.pop_front();
+            }
+            tail.push_back(line.to_string());
+        }
+    }
+
+    fn lines(&self) -> Vec<String> {
+        self.0
+            .lock()
+            .map(|tail| tail.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Time of the last output line of a process, for the idle timeout
+#[derive(Clone)]
+struct LastOutput(Arc<Mutex<tokio::time::Instant>>);
+
+impl LastOutput {
+    fn new() -> Self {
+        Self(Arc::new(Mutex::new(tokio::time::Instant::now())))
+    }
+
+    fn touch(&self) {
+        if let Ok(mut last) = self.0.lock() {
+            *last = tokio::time::Instant::now();
+        }
+    }
+
+    fn get(&self) -> tokio::time::Instant {
+        self.0
+            .lock()
+            .map(|last| *last)
+            .unwrap_or_else(|_| tokio::time::Instant::now())
+    }
+
+    /// Resolve once no output has arrived for `timeout`
+    async fn idle_for(&self, timeout: Duration) {
+        loop {
+            let deadline = self.get() + timeout;
+            if tokio::time::Instant::now() >= deadline {
+                return;
+            }
+            tokio::time::sleep_until(deadline).await;
+        }
+    }
+}
+
 pub struct ProcessHandle {
     pub pid: Option<u32>,
     input_tx: mpsc::Sender<String>,
     kill_tx: mpsc::Sender<()>,
+    /// Set by `kill_process` so the exit is reported as [`ExitReason::Killed`]
+    kill_requested: Arc<AtomicBool>,
+}
+
+impl ProcessHandle {
+    /// Handle for a process that is still being spawned
+    ///
+    /// Its channels have no receivers, so input and kills report the task as
+    /// having no process until the real handle replaces it.
+    fn placeholder() -> Self {
+        let (input_tx, _) = mpsc::channel(1);
+        let (kill_tx, _) = mpsc::channel(1);
+        Self {
+            pid: None,
+            input_tx,
+            kill_tx,
+            kill_requested: Arc::new(AtomicBool::new(false)),
+        }
+    }
+}
+
+/// Output captured from a session process
+#[derive(Debug, Clone, Default)]
+pub struct SessionOutput {
+    pub lines: Vec<String>,
+    pub done: bool,
+    pub exit_code: Option<i32>,
+    /// Conversation id the agent reported, used to continue the session later
+    pub agent_session_id: Option<String>,
+    /// Lines discarded after the session hit `SESSION_OUTPUT_MAX_LINES`
+    pub dropped_lines: usize,
+}
+
+/// Handle to a process that belongs to a session rather than a task
+struct SessionHandle {
+    kill_tx: mpsc::Sender<()>,
 }
 
 pub struct ProcessManager {
     processes: Arc<DashMap<i32, ProcessHandle>>,
+    /// Tasks whose process exited but whose exit is not recorded yet, with the
+    /// time of exit; see [`ProcessManager::exit_pending_for`]
+    exits_pending: Arc<DashMap<i32, std::time::Instant>>,
+    /// Ad-hoc agent runs, such as the voice BA agent, keyed by session id
+    sessions: Arc<DashMap<String, SessionHandle>>,
+    /// Held while checking the session limit and registering a session
+    session_slots: Arc<Mutex<()>>,
+    /// Output of running and recently finished sessions, for polling clients
+    session_outputs: Arc<DashMap<String, SessionOutput>>,
     output_tx: broadcast::Sender<OutputLine>,
+    output_capacity: usize,
+    exit_tx: broadcast::Sender<ProcessExit>,
+    /// Resolved agent binary paths keyed by command name
+    agent_paths: Arc<DashMap<String, String>>,
+    /// Scrubs secrets from output before it leaves the reader tasks
+    redactor: Arc<RwLock<Arc<Redactor>>>,
+    /// Shell command run in place of the real agent, for tests
+    agent_command_override: Option<String>,
 }
 
 impl Clone for ProcessManager {
     fn clone(&self) -> Self {
         Self {
             processes: Arc::clone(&self.processes),
+            exits_pending: Arc::clone(&self.exits_pending),
+            sessions: Arc::clone(&self.sessions),
+            session_slots: Arc::clone(&self.session_slots),
+            session_outputs: Arc::clone(&self.session_outputs),
             output_tx: self.output_tx.clone(),
+            output_capacity: self.output_capacity,
+            exit_tx: self.exit_tx.clone(),
+            agent_paths: Arc::clone(&self.agent_paths),
+            redactor: Arc::clone(&self.redactor),
+            agent_command_override: self.agent_command_override.clone(),
         }
     }
 }
@@ -40,12 +523,95 @@ impl Default for ProcessManager {
 }
 
 impl ProcessManager {
+    /// Create a process manager with the output buffer size taken from
+    /// `PROCESS_OUTPUT_BUFFER_LINES` and the agent override from
+    /// `ORCHESTRATOR_AGENT_CMD`
     pub fn new() -> Self {
-        let (output_tx, _) = broadcast::channel(1000);
+        let output_capacity = std::env::var("PROCESS_OUTPUT_BUFFER_LINES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|c| *c > 0)
+            .unwrap_or(DEFAULT_OUTPUT_BUFFER_LINES);
+        let manager = Self::with_output_capacity(output_capacity);
+        match std::env::var("ORCHESTRATOR_AGENT_CMD") {
+            Ok(command) if !command.is_empty() => manager.with_agent_command(command),
+            _ => manager,
+        }
+    }
+
+    /// Create a process manager buffering `output_capacity` lines per subscriber
+    pub fn with_output_capacity(output_capacity: usize) -> Self {
+        let (output_tx, _) = broadcast::channel(output_capacity);
+        let (exit_tx, _) = broadcast::channel(100);
         Self {
             processes: Arc::new(DashMap::new()),
+            exits_pending: Arc::new(DashMap::new()),
+            sessions: Arc::new(DashMap::new()),
+            session_slots: Arc::new(Mutex::new(())),
+            session_outputs: Arc::new(DashMap::new()),
             output_tx,
+            output_capacity,
+            exit_tx,
+            agent_paths: Arc::new(DashMap::new()),
+            redactor: Arc::new(RwLock::new(Arc::new(Redactor::default()))),
+            agent_command_override: None,
+        }
+    }
+
+    /// Run `command` with `sh -c` instead of the real agent binary
+    ///
+    /// The agent arguments are passed as positional parameters (`$1`, `$2`, …),
+    /// and no PTY wrapper or `PATH` lookup is involved, so a test can stand in
+    /// an `echo` or a script that exits with a chosen code.
+    pub fn with_agent_command(mut self, command: impl Into<String>) -> Self {
+        self.agent_command_override = Some(command.into());
+        self
+    }
+
+    /// Replace the redactor applied to process output
+    pub fn set_redactor(&self, redactor: Redactor) {
+        if let Ok(mut current) = self.redactor.write() {
+            *current = Arc::new(redactor);
+        }
+    }
+
+    /// Get the redactor currently applied to process output
+    fn current_redactor(lock: &RwLock<Arc<Redactor>>) -> Arc<Redactor> {
+        lock.read()
+            .map(|r| Arc::clone(&r))
+            .unwrap_or_default()
+    }
+
+    /// Resolve an agent command to its binary path
+    ///
+    /// The result of `which` is cached for the lifetime of the process and only
+    /// re-resolved when the cached binary disappears or the cache is cleared.
+    pub async fn resolve_agent(&self, agent: &str) -> Option<String> {
+        let cached = self.agent_paths.get(agent).map(|p| p.value().clone());
+        if let Some(path) = cached {
+            if std::path::Path::new(&path).exists() {
+                return Some(path);
+            }
+            self.agent_paths.remove(agent);
+        }
+
+        let output = Command::new("which").arg(agent).output().await.ok()?;
+        if !output.status.success() {
+            return None;
+        }
+
+        let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if path.is_empty() {
+            return None;
         }
+
+        self.agent_paths.insert(agent.to_string(), path.clone());
+        Some(path)
+    }
+
+    /// Forget all resolved agent paths so they are looked up again
+    pub fn clear_agent_cache(&self) {
+        self.agent_paths.clear();
     }
 
     /// Subscribe to output from all processes
@@ -53,6 +619,21 @@ impl ProcessManager {
         self.output_tx.subscribe()
     }
 
+    /// Number of lines each output subscriber can buffer
+    pub fn output_capacity(&self) -> usize {
+        self.output_capacity
+    }
+
+    /// Number of output lines not yet seen by the slowest subscriber
+    pub fn output_backlog(&self) -> usize {
+        self.output_tx.len()
+    }
+
+    /// Subscribe to process exit events
+    pub fn subscribe_exits(&self) -> broadcast::Receiver<ProcessExit> {
+        self.exit_tx.subscribe()
+    }
+
     /// Check if a process is running for a task
     pub fn is_running(&self, task_id: i32) -> bool {
         self.processes.contains_key(&task_id)
@@ -64,82 +645,181 @@ impl ProcessManager {
     }
 
     /// Spawn a CLI agent process for a task
+    ///
+    /// `agent` is one of [`AGENT_TYPES`]. Transient spawn failures are retried
+    /// up to `options.retries` times with a growing delay. With
+    /// `options.idle_timeout` set, a process that goes quiet for that long is
+    /// killed and its exit reported with [`ExitReason::IdleTimeout`]. Without
+    /// `options.skip_permissions`, claude denies tool calls that need approval
+    /// rather than asking for them on stdin.
     pub async fn spawn_agent(
         &self,
         task_id: i32,
+        agent: &str,
         prompt: &str,
         worktree_path: &str,
-    ) -> Result<u32, String> {
-        if self.is_running(task_id) {
-            return Err(format!("Task {} already has a running process", task_id));
-        }
-
+        options: SpawnOptions<'_>,
+    ) -> Result<u32> {
         // Verify working directory exists
         if !std::path::Path::new(worktree_path).exists() {
-            return Err(format!(
-                "Working directory does not exist: {}",
-                worktree_path
-            ));
+            return Err(ProcessError::WorkingDirMissing(worktree_path.to_string()));
         }
 
-        // Check if claude command is available
-        let claude_check = Command::new("which")
-            .arg("claude")
-            .output()
-            .await;
+        if !AGENT_TYPES.contains(&agent) {
+            return Err(ProcessError::UnknownAgent(agent.to_string()));
+        }
 
-        if claude_check.is_err() || !claude_check.unwrap().status.success() {
-            return Err(
-                "The 'claude' command is not found in PATH. Please install Claude Code CLI and ensure it's in your PATH.".to_string()
-            );
+        // Claim the task's slot before spawning so concurrent spawns can't both
+        // start it; the placeholder is replaced once the process is up
+        match self.processes.entry(task_id) {
+            Entry::Occupied(_) => {
+                return Err(ProcessError::AlreadyRunning(format!("Task {}", task_id)));
+            }
+            Entry::Vacant(slot) => {
+                slot.insert(ProcessHandle::placeholder());
+            }
         }
 
-        // Use script command to provide a PTY for claude
-        // This makes claude think it's running in a terminal
-        // On macOS: script -q file command args...
-        // The -q flag suppresses the "Script started/done" messages
-        // The -p flag makes claude run in non-interactive "print" mode (closes when done)
-        let mut child = Command::new("script")
-            .arg("-q")              // Quiet mode
-            .arg("/dev/null")       // Don't save transcript to file
-            .arg("claude")
-            .arg("-p")              // Non-interactive print mode (exits when done)
-            .arg(prompt)
-            .arg("--dangerously-skip-permissions")
+        let spawned = self
+            .spawn_reserved(task_id, agent, prompt, worktree_path, options)
+            .await;
+        if spawned.is_err() {
+            self.processes.remove(&task_id);
+        }
+        spawned
+    }
+
+    /// Start the agent for a task whose slot [`spawn_agent`](Self::spawn_agent) reserved
+    async fn spawn_reserved(
+        &self,
+        task_id: i32,
+        agent: &str,
+        prompt: &str,
+        worktree_path: &str,
+        options: SpawnOptions<'_>,
+    ) -> Result<u32> {
+        let mut command = if let Some(override_command) = &self.agent_command_override {
+            let mut command = Command::new("sh");
+            command.arg("-c").arg(override_command).arg(agent);
+            command
+        } else if let Some(template) = options.sandbox {
+            sandboxed_command(template, worktree_path, prompt, agent)
+        } else {
+            // Check if the agent command is available
+            let Some(agent_path) = self.resolve_agent(agent).await else {
+                return Err(ProcessError::AgentNotFound(agent.to_string()));
+            };
+
+            // Use script command to provide a PTY for the agent
+            // This makes the agent think it's running in a terminal
+            // On macOS: script -q file command args...
+            // The -q flag suppresses the "Script started/done" messages
+            let mut command = Command::new("script");
+            command
+                .arg("-q")              // Quiet mode
+                .arg("/dev/null")       // Don't save transcript to file
+                .arg(&agent_path);
+            command
+        };
+        push_agent_args(
+            &mut command,
+            agent,
+            prompt,
+            options.resume_session,
+            options.skip_permissions,
+        );
+        if let Some(env_file) = options.env_file {
+            command.envs(load_env_file(env_file).await);
+        }
+        if let Some(author) = options.git_author {
+            command.envs(author.env());
+        }
+        command
             .current_dir(worktree_path)
             .stdin(std::process::Stdio::piped())
             .stdout(std::process::Stdio::piped())
-            .stderr(std::process::Stdio::piped())
-            .spawn()
-            .map_err(|e| format!("Failed to spawn claude process: {} (working dir: {})", e, worktree_path))?;
+            .stderr(std::process::Stdio::piped());
+
+        let mut attempt = 0;
+        let mut child = loop {
+            match command.spawn() {
+                Ok(child) => break child,
+                Err(e) if attempt < options.retries && is_retriable_spawn_error(&e) => {
+                    attempt += 1;
+                    tracing::warn!(
+                        "Spawning {} for task {} failed (attempt {}/{}), retrying: {}",
+                        agent,
+                        task_id,
+                        attempt,
+                        options.retries + 1,
+                        e
+                    );
+                    tokio::time::sleep(SPAWN_RETRY_BACKOFF * attempt).await;
+                }
+                Err(e) => {
+                    if e.kind() == std::io::ErrorKind::NotFound {
+                        self.agent_paths.remove(agent);
+                    }
+                    tracing::error!(
+                        "Failed to spawn {} process: {} (working dir: {})",
+                        agent,
+                        e,
+                        worktree_path
+                    );
+                    return Err(ProcessError::SpawnFailed(e));
+                }
+            }
+        };
 
         let pid = child.id();
+        metrics::inc(&AGENT_SPAWNS_TOTAL);
 
         // Take ownership of streams
-        let stdin = child.stdin.take().ok_or("Failed to get stdin")?;
-        let stdout = child.stdout.take().ok_or("Failed to get stdout")?;
-        let stderr = child.stderr.take().ok_or("Failed to get stderr")?;
+        let stdin = take_stream(child.stdin.take(), "stdin")?;
+        let stdout = take_stream(child.stdout.take(), "stdout")?;
+        let stderr = take_stream(child.stderr.take(), "stderr")?;
 
         // Create channels for input and kill signal
         let (input_tx, mut input_rx) = mpsc::channel::<String>(100);
         let (kill_tx, mut kill_rx) = mpsc::channel::<()>(1);
 
         // Store process handle
+        let kill_requested = Arc::new(AtomicBool::new(false));
         let handle = ProcessHandle {
             pid,
             input_tx,
             kill_tx,
+            kill_requested: Arc::clone(&kill_requested),
         };
         self.processes.insert(task_id, handle);
+        self.exits_pending.remove(&task_id);
 
         let output_tx = self.output_tx.clone();
         let processes = Arc::clone(&self.processes);
+        let tail = OutputTail::default();
+        let last_output = LastOutput::new();
+        let output_file = match options.output_file {
+            Some(path) => OutputFile::open(path).await,
+            None => None,
+        };
 
         // Spawn task to handle stdout
         let output_tx_stdout = output_tx.clone();
-        tokio::spawn(async move {
-            let mut reader = BufReader::new(stdout).lines();
-            while let Ok(Some(line)) = reader.next_line().await {
+        let tail_stdout = tail.clone();
+        let last_output_stdout = last_output.clone();
+        let redactor_stdout = Arc::clone(&self.redactor);
+        let output_file_stdout = output_file.clone();
+        let stdout_task = tokio::spawn(async move {
+            let mut reader = LossyLines::new(stdout);
+            while let Some(line) = reader.next_line().await {
+                last_output_stdout.touch();
+                let line = Self::current_redactor(&redactor_stdout)
+                    .redact(&line)
+                    .into_owned();
+                tail_stdout.push(&line);
+                if let Some(file) = &output_file_stdout {
+                    file.write_line(&line).await;
+                }
                 let _ = output_tx_stdout.send(OutputLine {
                     task_id,
                     line,
@@ -150,9 +830,21 @@ impl ProcessManager {
 
         // Spawn task to handle stderr
         let output_tx_stderr = output_tx.clone();
-        tokio::spawn(async move {
-            let mut reader = BufReader::new(stderr).lines();
-            while let Ok(Some(line)) = reader.next_line().await {
+        let tail_stderr = tail.clone();
+        let last_output_stderr = last_output.clone();
+        let redactor_stderr = Arc::clone(&self.redactor);
+        let output_file_stderr = output_file;
+        let stderr_task = tokio::spawn(async move {
+            let mut reader = LossyLines::new(stderr);
+            while let Some(line) = reader.next_line().await {
+                last_output_stderr.touch();
+                let line = Self::current_redactor(&redactor_stderr)
+                    .redact(&line)
+                    .into_owned();
+                tail_stderr.push(&line);
+                if let Some(file) = &output_file_stderr {
+                    file.write_line(&line).await;
+                }
                 let _ = output_tx_stderr.send(OutputLine {
                     task_id,
                     line,
@@ -183,70 +875,373 @@ impl ProcessManager {
 
         // Spawn task to wait for process completion and cleanup
         let processes_cleanup = Arc::clone(&processes);
+        let exits_pending = Arc::clone(&self.exits_pending);
         let output_tx_exit = output_tx.clone();
+        let exit_tx = self.exit_tx.clone();
+        let idle_timeout = options.idle_timeout;
         tokio::spawn(async move {
-            let status = child.wait().await;
+            let idle = async {
+                match idle_timeout {
+                    Some(timeout) => last_output.idle_for(timeout).await,
+                    None => std::future::pending().await,
+                }
+            };
+
+            let (status, reason) = tokio::select! {
+                status = child.wait() => {
+                    let reason = if kill_requested.load(Ordering::SeqCst) {
+                        ExitReason::Killed
+                    } else {
+                        ExitReason::Exited
+                    };
+                    (status, reason)
+                }
+                _ = idle => {
+                    tracing::warn!(
+                        "Task {} produced no output for {:?}, killing it",
+                        task_id,
+                        idle_timeout.unwrap_or_default()
+                    );
+                    if let Some(pid) = pid {
+                        force_kill(pid).await;
+                    }
+                    let _ = child.kill().await;
+                    (child.wait().await, ExitReason::IdleTimeout)
+                }
+            };
             let exit_code = status
                 .ok()
                 .and_then(|s| s.code())
                 .unwrap_or(-1);
 
+            // Let the readers drain what is left in the pipes
+            let _ = tokio::time::timeout(Duration::from_secs(5), async {
+                let _ = stdout_task.await;
+                let _ = stderr_task.await;
+            })
+            .await;
+
+            let line = match reason {
+                ExitReason::IdleTimeout => format!(
+                    "\n[Process killed after {}s without output]",
+                    idle_timeout.unwrap_or_default().as_secs()
+                ),
+                ExitReason::Exited => format!("\n[Process exited with code {}]", exit_code),
+                ExitReason::Killed => "\n[Process killed]".to_string(),
+            };
             let _ = output_tx_exit.send(OutputLine {
                 task_id,
-                line: format!("\n[Process exited with code {}]", exit_code),
+                line,
                 is_stderr: false,
             });
 
+            // Pending before the process is forgotten, so the task is never seen
+            // as neither running nor exiting
+            exits_pending.insert(task_id, std::time::Instant::now());
             processes_cleanup.remove(&task_id);
+
+            let _ = exit_tx.send(ProcessExit {
+                task_id,
+                exit_code,
+                reason,
+                output_tail: tail.lines(),
+            });
         });
 
         Ok(pid.unwrap_or(0))
     }
 
     /// Send input to a process
-    pub async fn send_input(&self, task_id: i32, input: &str) -> Result<(), String> {
-        let handle = self
+    ///
+    /// When `append_newline` is set and the data does not already end in a
+    /// newline, one is added so the input is submitted as a line. Fails with
+    /// [`ProcessError::InputFull`] if the process does not drain its stdin
+    /// queue within a short timeout.
+    pub async fn send_input(
+        &self,
+        task_id: i32,
+        input: &str,
+        append_newline: bool,
+    ) -> Result<()> {
+        // Clone the sender so the map entry isn't held across the await
+        let input_tx = self
             .processes
             .get(&task_id)
-            .ok_or(format!("No process for task {}", task_id))?;
+            .map(|h| h.input_tx.clone())
+            .ok_or_else(|| ProcessError::NotFound(format!("task {}", task_id)))?;
 
-        handle
-            .input_tx
-            .send(input.to_string())
-            .await
-            .map_err(|e| format!("Failed to send input: {}", e))
+        let mut data = input.to_string();
+        if append_newline && !data.ends_with('\n') {
+            data.push('\n');
+        }
+
+        match tokio::time::timeout(INPUT_SEND_TIMEOUT, input_tx.send(data)).await {
+            Ok(Ok(())) => Ok(()),
+            // The input task only stops once the process is gone
+            Ok(Err(_)) => Err(ProcessError::NotFound(format!("task {}", task_id))),
+            Err(_) => Err(ProcessError::InputFull(task_id)),
+        }
     }
 
     /// Kill a process
-    pub async fn kill_process(&self, task_id: i32) -> Result<(), String> {
-        let handle = self
+    pub async fn kill_process(&self, task_id: i32) -> Result<()> {
+        // Copy what we need so the map entry isn't held across the awaits;
+        // the exit task removes it once the process is gone
+        let (kill_tx, pid) = self
             .processes
             .get(&task_id)
-            .ok_or(format!("No process for task {}", task_id))?;
+            .map(|h| {
+                h.kill_requested.store(true, Ordering::SeqCst);
+                (h.kill_tx.clone(), h.pid)
+            })
+            .ok_or_else(|| ProcessError::NotFound(format!("task {}", task_id)))?;
 
-        handle
-            .kill_tx
+        // The kill receiver is only dropped once the process has exited
+        kill_tx
             .send(())
             .await
-            .map_err(|e| format!("Failed to send kill signal: {}", e))?;
+            .map_err(|_| ProcessError::NotFound(format!("task {}", task_id)))?;
 
         // Also try to kill the process directly
-        if let Some(pid) = handle.pid {
-            // Use kill command to terminate
-            let _ = Command::new("kill")
-                .arg("-9")
-                .arg(pid.to_string())
-                .output()
-                .await;
+        if let Some(pid) = pid {
+            force_kill(pid).await;
         }
 
         Ok(())
     }
 
+    /// Spawn a process that is tracked by session id instead of a task
+    ///
+    /// Output is redacted, logged and kept for `session_output`. The session is
+    /// forgotten once the process exits or is killed with `kill_session_process`,
+    /// its output is dropped a few minutes later.
+    ///
+    /// With a non-zero `limit`, fails with [`ProcessError::TooManySessions`]
+    /// when that many sessions are already running. The check and the
+    /// registration happen together, so concurrent calls cannot overshoot it.
+    pub async fn spawn_session_process(
+        &self,
+        session_id: &str,
+        mut command: Command,
+        limit: usize,
+    ) -> Result<u32> {
+        let (kill_tx, mut kill_rx) = mpsc::channel::<()>(1);
+        {
+            let _slots = self.session_slots.lock().unwrap_or_else(|e| e.into_inner());
+            if self.sessions.contains_key(session_id) {
+                return Err(ProcessError::AlreadyRunning(format!("Session {}", session_id)));
+            }
+            let active = self.sessions.len();
+            if limit > 0 && active >= limit {
+                return Err(ProcessError::TooManySessions { active, limit });
+            }
+            self.sessions
+                .insert(session_id.to_string(), SessionHandle { kill_tx });
+        }
+
+        let spawned = command
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .map_err(ProcessError::from)
+            .and_then(|mut child| {
+                let stdout = take_stream(child.stdout.take(), "stdout")?;
+                let stderr = take_stream(child.stderr.take(), "stderr")?;
+                Ok((child, stdout, stderr))
+            });
+        let (mut child, stdout, stderr) = match spawned {
+            Ok(spawned) => spawned,
+            Err(e) => {
+                self.sessions.remove(session_id);
+                return Err(e);
+            }
+        };
+        let pid = child.id();
+
+        self.session_outputs
+            .insert(session_id.to_string(), SessionOutput::default());
+
+        let session_stdout = session_id.to_string();
+        let redactor_stdout = Arc::clone(&self.redactor);
+        let outputs_stdout = Arc::clone(&self.session_outputs);
+        let stdout_task = tokio::spawn(async move {
+            let mut reader = LossyLines::new(stdout);
+            while let Some(line) = reader.next_line().await {
+                let line = Self::current_redactor(&redactor_stdout).redact(&line).into_owned();
+                tracing::info!("[session {}] {}", session_stdout, line);
+                Self::push_session_line(&outputs_stdout, &session_stdout, line);
+            }
+        });
+
+        let session_stderr = session_id.to_string();
+        let redactor_stderr = Arc::clone(&self.redactor);
+        let outputs_stderr = Arc::clone(&self.session_outputs);
+        let stderr_task = tokio::spawn(async move {
+            let mut reader = LossyLines::new(stderr);
+            while let Some(line) = reader.next_line().await {
+                let line = Self::current_redactor(&redactor_stderr).redact(&line).into_owned();
+                tracing::warn!("[session {}] {}", session_stderr, line);
+                Self::push_session_line(&outputs_stderr, &session_stderr, line);
+            }
+        });
+
+        let sessions = Arc::clone(&self.sessions);
+        let session_outputs = Arc::clone(&self.session_outputs);
+        let session_id = session_id.to_string();
+        tokio::spawn(async move {
+            let exit_code = tokio::select! {
+                status = child.wait() => status.ok().and_then(|s| s.code()),
+                _ = kill_rx.recv() => {
+                    if let Some(pid) = pid {
+                        force_kill(pid).await;
+                    }
+                    let _ = child.kill().await;
+                    None
+                }
+            };
+
+            let _ = tokio::time::timeout(Duration::from_secs(5), async {
+                let _ = stdout_task.await;
+                let _ = stderr_task.await;
+            })
+            .await;
+
+            sessions.remove(&session_id);
+            if let Some(mut output) = session_outputs.get_mut(&session_id) {
+                output.done = true;
+                output.exit_code = exit_code;
+                output.agent_session_id = parse_agent_session_id(&output.lines);
+            }
+            tracing::info!("Session {} process exited with code {:?}", session_id, exit_code);
+
+            tokio::time::sleep(SESSION_OUTPUT_RETENTION).await;
+            session_outputs.remove(&session_id);
+        });
+
+        Ok(pid.unwrap_or(0))
+    }
+
+    /// Append a line to a session's captured output
+    fn push_session_line(outputs: &DashMap<String, SessionOutput>, session_id: &str, line: String) {
+        if let Some(mut output) = outputs.get_mut(session_id) {
+            if output.lines.len() < SESSION_OUTPUT_MAX_LINES {
+                output.lines.push(line);
+            } else {
+                if output.dropped_lines == 0 {
+                    tracing::warn!(
+                        "Session {} exceeded {} output lines, further output is only logged",
+                        session_id,
+                        SESSION_OUTPUT_MAX_LINES
+                    );
+                }
+                output.dropped_lines += 1;
+            }
+        }
+    }
+
+    /// Get a session's output lines after index `since`
+    ///
+    /// Returns `None` when the session is unknown or its output has expired.
+    pub fn session_output(&self, session_id: &str, since: usize) -> Option<SessionOutput> {
+        self.session_outputs.get(session_id).map(|output| SessionOutput {
+            lines: output.lines.iter().skip(since).cloned().collect(),
+            done: output.done,
+            exit_code: output.exit_code,
+            agent_session_id: output.agent_session_id.clone(),
+            dropped_lines: output.dropped_lines,
+        })
+    }
+
+    /// Check if a session has a running process
+    pub fn is_session_running(&self, session_id: &str) -> bool {
+        self.sessions.contains_key(session_id)
+    }
+
+    /// Kill the process belonging to a session
+    pub async fn kill_session_process(&self, session_id: &str) -> Result<()> {
+        // Clone the sender so the map entry isn't held across the await
+        let kill_tx = self
+            .sessions
+            .get(session_id)
+            .map(|h| h.kill_tx.clone())
+            .ok_or_else(|| ProcessError::NotFound(format!("session {}", session_id)))?;
+
+        kill_tx
+            .send(())
+            .await
+            .map_err(|_| ProcessError::NotFound(format!("session {}", session_id)))
+    }
+
+    /// Number of session processes currently running
+    pub fn running_sessions(&self) -> usize {
+        self.sessions.len()
+    }
+
+    /// Get the IDs of running UI sessions
+    pub fn running_session_ids(&self) -> Vec<String> {
+        self.sessions.iter().map(|r| r.key().clone()).collect()
+    }
+
+    /// Kill every running UI session process
+    ///
+    /// Returns the IDs of the sessions that were running when called.
+    pub async fn kill_all_sessions(&self) -> Vec<String> {
+        let session_ids = self.running_session_ids();
+        for session_id in &session_ids {
+            if let Err(e) = self.kill_session_process(session_id).await {
+                tracing::warn!("Failed to kill session {}: {}", session_id, e);
+            }
+        }
+        session_ids
+    }
+
+    /// How long ago the task's process exited, while its exit is not recorded yet
+    ///
+    /// Set when the process exits and cleared with [`exit_recorded`](Self::exit_recorded)
+    /// once the process monitor has finalized the task.
+    pub fn exit_pending_for(&self, task_id: i32) -> Option<Duration> {
+        self.exits_pending.get(&task_id).map(|exited| exited.elapsed())
+    }
+
+    /// Mark the task's exit as recorded, see [`exit_pending_for`](Self::exit_pending_for)
+    pub fn exit_recorded(&self, task_id: i32) {
+        self.exits_pending.remove(&task_id);
+    }
+
     /// Get list of running task IDs
     pub fn running_tasks(&self) -> Vec<i32> {
         self.processes.iter().map(|r| *r.key()).collect()
     }
+
+    /// Kill every running process and wait up to `timeout` for them to exit
+    ///
+    /// Returns the IDs of the tasks that were running when called.
+    pub async fn kill_all(&self, timeout: Duration) -> Vec<i32> {
+        let task_ids = self.running_tasks();
+
+        for task_id in &task_ids {
+            if let Err(e) = self.kill_process(*task_id).await {
+                tracing::warn!("Failed to kill process for task {}: {}", task_id, e);
+            }
+        }
+
+        // Processes are removed from the map once their exit is observed
+        let deadline = tokio::time::Instant::now() + timeout;
+        while !self.processes.is_empty() && tokio::time::Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+
+        if !self.processes.is_empty() {
+            tracing::warn!(
+                "{} process(es) still running after {:?}",
+                self.processes.len(),
+                timeout
+            );
+        }
+
+        task_ids
+    }
 }
 
 // Global process manager instance
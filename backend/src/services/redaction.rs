@@ -0,0 +1,85 @@
+//! Redaction of secrets from agent output
+//!
+//! Lines are scrubbed in the process manager's reader tasks, before they are
+//! broadcast or stored, so every consumer sees the same redacted output.
+
+use regex::Regex;
+use sea_orm::DatabaseConnection;
+use std::borrow::Cow;
+
+use crate::models::_entities::settings;
+use crate::services::process_manager::PROCESS_MANAGER;
+
+/// Replacement text for redacted secrets
+const REDACTED: &str = "***";
+
+/// Common API key and token shapes
+const DEFAULT_PATTERNS: &[&str] = &[
+    r"pk_[A-Za-z0-9_]{20,}",
+    r"sk-[A-Za-z0-9_\-]{20,}",
+    r"gh[pousr]_[A-Za-z0-9]{36,}",
+    r"github_pat_[A-Za-z0-9_]{22,}",
+    r"AKIA[0-9A-Z]{16}",
+    r"xox[abprs]-[A-Za-z0-9\-]{10,}",
+    r"(?i)bearer\s+[A-Za-z0-9_\-\.=]{16,}",
+];
+
+#[derive(Debug, Default)]
+pub struct Redactor {
+    patterns: Vec<Regex>,
+}
+
+impl Redactor {
+    pub fn new(patterns: Vec<Regex>) -> Self {
+        Self { patterns }
+    }
+
+    /// Build the redactor from the defaults, the configured ClickUp token and
+    /// the `log_redaction_patterns` setting (a JSON array of regexes)
+    pub async fn load(db: &DatabaseConnection) -> Self {
+        let mut patterns: Vec<Regex> = DEFAULT_PATTERNS
+            .iter()
+            .filter_map(|p| Regex::new(p).ok())
+            .collect();
+
+        if let Ok(token) = std::env::var("CLICKUP_API_KEY") {
+            if !token.is_empty() {
+                if let Ok(re) = Regex::new(&regex::escape(&token)) {
+                    patterns.push(re);
+                }
+            }
+        }
+
+        if let Some(value) = settings::Entity::get_value(db, "log_redaction_patterns").await {
+            match serde_json::from_str::<Vec<String>>(&value) {
+                Ok(custom) => {
+                    for pattern in custom {
+                        match Regex::new(&pattern) {
+                            Ok(re) => patterns.push(re),
+                            Err(e) => tracing::warn!("Invalid redaction pattern '{}': {}", pattern, e),
+                        }
+                    }
+                }
+                Err(e) => tracing::warn!("log_redaction_patterns must be a JSON array: {}", e),
+            }
+        }
+
+        Self::new(patterns)
+    }
+
+    /// Replace every match of the configured patterns with `***`
+    pub fn redact<'a>(&self, line: &'a str) -> Cow<'a, str> {
+        let mut result = Cow::Borrowed(line);
+        for re in &self.patterns {
+            if re.is_match(&result) {
+                result = Cow::Owned(re.replace_all(&result, REDACTED).into_owned());
+            }
+        }
+        result
+    }
+}
+
+/// Reload the process manager's redactor from settings
+pub async fn reload(db: &DatabaseConnection) {
+    PROCESS_MANAGER.set_redactor(Redactor::load(db).await);
+}
@@ -0,0 +1,75 @@
+//! Token usage parsing from agent output
+//!
+//! Agents print a usage summary when they finish (e.g. `tokens used: 1234`).
+//! The patterns are configurable through settings, each with a single capture
+//! group holding the number.
+
+use regex::Regex;
+use sea_orm::DatabaseConnection;
+
+use crate::models::_entities::settings;
+
+const DEFAULT_INPUT_TOKENS_PATTERN: &str = r"(?i)input[ _]tokens?\W{0,3}([\d,]+)";
+const DEFAULT_OUTPUT_TOKENS_PATTERN: &str = r"(?i)output[ _]tokens?\W{0,3}([\d,]+)";
+const DEFAULT_COST_PATTERN: &str = r"(?i)(?:total|estimated)?[ _]?cost\W{0,3}\$?([\d.]+)";
+
+/// Token usage extracted from an agent run; unparsed values stay `None`
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TokenUsage {
+    pub input_tokens: Option<i32>,
+    pub output_tokens: Option<i32>,
+    pub estimated_cost: Option<f64>,
+}
+
+pub struct UsagePatterns {
+    input_tokens: Regex,
+    output_tokens: Regex,
+    cost: Regex,
+}
+
+impl UsagePatterns {
+    /// Load patterns from settings, falling back to the defaults
+    pub async fn load(db: &DatabaseConnection) -> Self {
+        Self {
+            input_tokens: Self::pattern(db, "usage_input_tokens_regex", DEFAULT_INPUT_TOKENS_PATTERN)
+                .await,
+            output_tokens: Self::pattern(
+                db,
+                "usage_output_tokens_regex",
+                DEFAULT_OUTPUT_TOKENS_PATTERN,
+            )
+            .await,
+            cost: Self::pattern(db, "usage_cost_regex", DEFAULT_COST_PATTERN).await,
+        }
+    }
+
+    async fn pattern(db: &DatabaseConnection, key: &str, default: &str) -> Regex {
+        if let Some(pattern) = settings::Entity::get_value(db, key).await {
+            match Regex::new(&pattern) {
+                Ok(re) => return re,
+                Err(e) => tracing::warn!("Invalid {} pattern, using default: {}", key, e),
+            }
+        }
+        Regex::new(default).expect("default usage pattern is valid")
+    }
+
+    /// Parse usage from output lines, preferring the last match of each pattern
+    pub fn parse(&self, lines: &[String]) -> TokenUsage {
+        TokenUsage {
+            input_tokens: Self::last_capture(&self.input_tokens, lines)
+                .and_then(|v| v.replace(',', "").parse().ok()),
+            output_tokens: Self::last_capture(&self.output_tokens, lines)
+                .and_then(|v| v.replace(',', "").parse().ok()),
+            estimated_cost: Self::last_capture(&self.cost, lines).and_then(|v| v.parse().ok()),
+        }
+    }
+
+    fn last_capture(re: &Regex, lines: &[String]) -> Option<String> {
+        lines
+            .iter()
+            .rev()
+            .find_map(|line| re.captures(line))
+            .and_then(|caps| caps.get(1))
+            .map(|m| m.as_str().to_string())
+    }
+}
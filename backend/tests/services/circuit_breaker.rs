@@ -0,0 +1,35 @@
+use std::time::Duration;
+
+use backend::services::circuit_breaker::CircuitBreaker;
+
+const COOLDOWN: Duration = Duration::from_millis(50);
+
+#[tokio::test]
+async fn breaker_opens_half_opens_and_closes() {
+    let breaker = CircuitBreaker::new("test");
+    assert!(!breaker.is_open());
+
+    // Closed until the threshold is reached
+    breaker.record_failure(2, COOLDOWN);
+    assert!(!breaker.is_open());
+    breaker.record_failure(2, COOLDOWN);
+    assert!(breaker.is_open());
+    assert!(breaker.status().open);
+    assert_eq!(breaker.status().consecutive_failures, 2);
+
+    // Half-open after the cooldown, a failed trial call reopens it
+    tokio::time::sleep(COOLDOWN * 2).await;
+    assert!(!breaker.is_open());
+    breaker.record_failure(2, COOLDOWN);
+    assert!(breaker.is_open());
+
+    // A successful trial call closes it again
+    tokio::time::sleep(COOLDOWN * 2).await;
+    assert!(!breaker.is_open());
+    breaker.record_success();
+    let status = breaker.status();
+    assert!(!breaker.is_open());
+    assert!(!status.open);
+    assert_eq!(status.consecutive_failures, 0);
+    assert_eq!(status.retry_in_secs, None);
+}
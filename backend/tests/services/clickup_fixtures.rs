@@ -0,0 +1,65 @@
+use backend::services::clickup::{ClickUpApi, TaskFilter};
+use backend::services::clickup_fixtures::FixtureClient;
+
+fn client() -> FixtureClient {
+    FixtureClient::new(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/clickup"
+    ))
+}
+
+#[tokio::test]
+async fn fixture_client_serves_hierarchy() {
+    let client = client();
+
+    let teams = client.get_workspaces().await.expect("team fixture");
+    assert_eq!(teams.len(), 1);
+
+    let spaces = client
+        .get_spaces(&teams[0].id)
+        .await
+        .expect("space fixture");
+    let lists = client
+        .get_folderless_lists(&spaces[0].id)
+        .await
+        .expect("list fixture");
+    assert_eq!(lists[0].id, "900");
+
+    let statuses = client
+        .resolve_statuses("900", &["READY FOR DEV", "in development"])
+        .await
+        .expect("statuses resolve");
+    assert_eq!(statuses, vec!["Ready for Dev", "In Development"]);
+}
+
+#[tokio::test]
+async fn fixture_client_filters_and_moves_tasks() {
+    let client = client();
+    let filter = TaskFilter::default();
+
+    let ready = client
+        .get_tasks("900", Some("Ready for Dev"), &filter)
+        .await
+        .expect("task fixture");
+    assert_eq!(ready.len(), 1);
+    assert_eq!(ready[0].id, "fx1");
+
+    let moved = client
+        .update_task_status("fx1", "In Development")
+        .await
+        .expect("task found");
+    assert_eq!(moved.status.status, "In Development");
+
+    let ready = client
+        .get_tasks("900", Some("Ready for Dev"), &filter)
+        .await
+        .expect("task fixture");
+    assert!(ready.is_empty());
+
+    assert!(client
+        .get_comments("fx1")
+        .await
+        .expect("comments")
+        .is_empty());
+    assert!(client.get_tasks("404", None, &filter).await.is_err());
+}
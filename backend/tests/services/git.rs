@@ -0,0 +1,22 @@
+use backend::services::git::{parse_shortstat, DiffStat};
+
+#[test]
+fn shortstat_output_is_parsed() {
+    assert_eq!(
+        parse_shortstat(" 3 files changed, 10 insertions(+), 2 deletions(-)\n"),
+        DiffStat {
+            files_changed: 3,
+            insertions: 10,
+            deletions: 2,
+        }
+    );
+    assert_eq!(
+        parse_shortstat(" 1 file changed, 1 deletion(-)\n"),
+        DiffStat {
+            files_changed: 1,
+            insertions: 0,
+            deletions: 1,
+        }
+    );
+    assert_eq!(parse_shortstat(""), DiffStat::default());
+}
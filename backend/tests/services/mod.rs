@@ -0,0 +1,5 @@
+mod circuit_breaker;
+mod clickup_fixtures;
+mod git;
+mod orchestrator;
+mod process_manager;
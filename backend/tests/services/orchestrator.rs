@@ -0,0 +1,83 @@
+use std::time::Duration;
+
+use backend::services::orchestrator::{auto_restart_delay, parse_df_available_mb, parse_progress};
+use regex::Regex;
+
+#[test]
+fn auto_restart_backs_off_until_the_cap() {
+    let backoff = Duration::from_secs(30);
+
+    assert_eq!(
+        auto_restart_delay(0, 3, backoff),
+        Some(Duration::from_secs(30))
+    );
+    assert_eq!(
+        auto_restart_delay(1, 3, backoff),
+        Some(Duration::from_secs(60))
+    );
+    assert_eq!(
+        auto_restart_delay(2, 3, backoff),
+        Some(Duration::from_secs(120))
+    );
+    assert_eq!(auto_restart_delay(3, 3, backoff), None);
+    assert_eq!(auto_restart_delay(0, 0, backoff), None);
+}
+
+#[test]
+fn df_available_space_is_parsed() {
+    let output = "Filesystem     1024-blocks     Used Available Capacity Mounted on\n\
+                  /dev/sda1        102400000 51200000  51200000      50% /\n";
+    assert_eq!(parse_df_available_mb(output), Some(50_000));
+
+    // Rounded down to whole megabytes
+    let output = "Filesystem 1024-blocks Used Available Capacity Mounted on\n\
+                  tmpfs 4096 1 2047 1% /tmp\n";
+    assert_eq!(parse_df_available_mb(output), Some(1));
+}
+
+#[test]
+fn malformed_df_output_is_rejected() {
+    assert_eq!(parse_df_available_mb(""), None);
+    assert_eq!(
+        parse_df_available_mb("Filesystem 1024-blocks Used Available Capacity Mounted on\n"),
+        None
+    );
+    assert_eq!(
+        parse_df_available_mb("Filesystem 1024-blocks Used Available\n/dev/sda1 100 50\n"),
+        None
+    );
+    assert_eq!(
+        parse_df_available_mb("Filesystem 1024-blocks Used Available\n/dev/sda1 100 50 lots\n"),
+        None
+    );
+}
+
+#[test]
+fn progress_is_parsed_from_marker_lines() {
+    let percent = Regex::new(r"PROGRESS: (\d+)%").unwrap();
+    assert_eq!(parse_progress(&percent, "PROGRESS: 42%"), Some(42));
+
+    let steps = Regex::new(r"step (\d+) of (\d+)").unwrap();
+    assert_eq!(parse_progress(&steps, "Working on step 1 of 3"), Some(33));
+    assert_eq!(parse_progress(&steps, "step 3 of 3"), Some(100));
+}
+
+#[test]
+fn out_of_range_progress_is_clamped() {
+    let percent = Regex::new(r"PROGRESS: (\d+)%").unwrap();
+    assert_eq!(parse_progress(&percent, "PROGRESS: 250%"), Some(100));
+
+    let steps = Regex::new(r"step (\d+) of (\d+)").unwrap();
+    assert_eq!(parse_progress(&steps, "step 5 of 4"), Some(100));
+}
+
+#[test]
+fn malformed_progress_lines_are_ignored() {
+    let percent = Regex::new(r"PROGRESS: (\S+)%").unwrap();
+    assert_eq!(parse_progress(&percent, "no marker here"), None);
+    assert_eq!(parse_progress(&percent, "PROGRESS: lots%"), None);
+
+    // A zero total has no meaningful fraction
+    let steps = Regex::new(r"step (\d+) of (\d+)").unwrap();
+    assert_eq!(parse_progress(&steps, "step 1 of 0"), None);
+}
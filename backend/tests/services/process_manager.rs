@@ -0,0 +1,161 @@
+use std::time::Duration;
+
+use backend::services::process_manager::{
+    parse_env_file, sandboxed_command, ExitReason, LossyLines, ProcessError, ProcessManager,
+    SpawnOptions,
+};
+use tokio::process::Command;
+
+#[tokio::test]
+async fn lossy_lines_survive_invalid_utf8() {
+    let input: &[u8] = b"ok\n\xff\xfe bad\r\nafter\nno newline";
+    let mut reader = LossyLines::new(input);
+
+    let mut lines = Vec::new();
+    while let Some(line) = reader.next_line().await {
+        lines.push(line);
+    }
+
+    assert_eq!(
+        lines,
+        vec![
+            "ok".to_string(),
+            "\u{FFFD}\u{FFFD} bad".to_string(),
+            "after".to_string(),
+            "no newline".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn env_file_lines_are_parsed() {
+    let content = "# comment\n\nAPI_KEY=abc=123\nexport REGION = eu \nQUOTED=\"a b\"\nSINGLE='x'\nnot a var\n=empty\n";
+
+    assert_eq!(
+        parse_env_file(content),
+        vec![
+            ("API_KEY".to_string(), "abc=123".to_string()),
+            ("REGION".to_string(), "eu".to_string()),
+            ("QUOTED".to_string(), "a b".to_string()),
+            ("SINGLE".to_string(), "x".to_string()),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn fake_agent_output_and_exit_are_broadcast() {
+    let manager = ProcessManager::with_output_capacity(100)
+        .with_agent_command("echo hello; echo oops >&2; exit 3");
+    let mut output_rx = manager.subscribe_output();
+    let mut exit_rx = manager.subscribe_exits();
+    let worktree = std::env::temp_dir();
+
+    manager
+        .spawn_agent(
+            1,
+            "claude",
+            "do the thing",
+            &worktree.to_string_lossy(),
+            SpawnOptions::default(),
+        )
+        .await
+        .expect("fake agent spawns");
+
+    let exit = tokio::time::timeout(Duration::from_secs(10), exit_rx.recv())
+        .await
+        .expect("process exits")
+        .expect("exit is broadcast");
+    assert_eq!(exit.task_id, 1);
+    assert_eq!(exit.exit_code, 3);
+    assert_eq!(exit.reason, ExitReason::Exited);
+    assert!(exit.output_tail.contains(&"hello".to_string()));
+    assert!(exit.output_tail.contains(&"oops".to_string()));
+    assert!(!manager.is_running(1));
+
+    let mut lines = Vec::new();
+    while let Ok(line) = output_rx.try_recv() {
+        lines.push((line.line, line.is_stderr));
+    }
+    assert!(lines.contains(&("hello".to_string(), false)));
+    assert!(lines.contains(&("oops".to_string(), true)));
+}
+
+#[tokio::test]
+async fn silent_fake_agent_is_killed_after_idle_timeout() {
+    let manager = ProcessManager::with_output_capacity(100).with_agent_command("sleep 30");
+    let mut exit_rx = manager.subscribe_exits();
+    let worktree = std::env::temp_dir();
+
+    manager
+        .spawn_agent(
+            2,
+            "claude",
+            "hang",
+            &worktree.to_string_lossy(),
+            SpawnOptions {
+                idle_timeout: Some(Duration::from_millis(200)),
+                ..SpawnOptions::default()
+            },
+        )
+        .await
+        .expect("fake agent spawns");
+
+    let exit = tokio::time::timeout(Duration::from_secs(10), exit_rx.recv())
+        .await
+        .expect("process is killed")
+        .expect("exit is broadcast");
+    assert_eq!(exit.task_id, 2);
+    assert_eq!(exit.reason, ExitReason::IdleTimeout);
+    assert!(!manager.is_running(2));
+}
+
+#[tokio::test]
+async fn session_limit_is_enforced_on_spawn() {
+    let manager = ProcessManager::with_output_capacity(100);
+    let sleep = || {
+        let mut command = Command::new("sleep");
+        command.arg("30");
+        command
+    };
+
+    manager
+        .spawn_session_process("first", sleep(), 1)
+        .await
+        .expect("first session spawns");
+    let second = manager.spawn_session_process("second", sleep(), 1).await;
+    assert!(matches!(
+        second,
+        Err(ProcessError::TooManySessions {
+            active: 1,
+            limit: 1
+        })
+    ));
+    assert!(!manager.is_session_running("second"));
+
+    manager.kill_all_sessions().await;
+}
+
+#[test]
+fn sandbox_template_placeholders_are_shell_quoted() {
+    let command = sandboxed_command(
+        "run --dir {worktree} --label {prompt}",
+        "/tmp/work tree",
+        "it's done",
+        "claude",
+    );
+    let args: Vec<_> = command
+        .as_std()
+        .get_args()
+        .map(|a| a.to_string_lossy().into_owned())
+        .collect();
+
+    assert_eq!(
+        args,
+        [
+            "-c",
+            "run --dir '/tmp/work tree' --label 'it'\\''s done' \"$@\"",
+            "sh",
+            "claude",
+        ]
+    );
+}
@@ -0,0 +1,15 @@
+// Agents API
+
+import { get } from './client';
+
+export interface AgentInfo {
+	name: string;
+	available: boolean;
+	version: string | null;
+	path: string | null;
+	is_default: boolean;
+}
+
+export async function getAgents(refresh = false): Promise<AgentInfo[]> {
+	return get<AgentInfo[]>(refresh ? '/agents?refresh=true' : '/agents');
+}
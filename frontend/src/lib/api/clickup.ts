@@ -1,6 +1,6 @@
 // ClickUp hierarchy browser API
 
-import { get } from './client';
+import { get, post } from './client';
 
 export interface Team {
 	id: string;
@@ -59,3 +59,34 @@ export async function getFolderlessLists(spaceId: string): Promise<List[]> {
 export async function getListStatuses(listId: string): Promise<Status[]> {
 	return get<Status[]>(`/clickup/lists/${listId}/statuses`);
 }
+
+export interface TaskPreview {
+	id: string;
+	name: string;
+	status: string;
+	priority: number | null;
+}
+
+export async function getListTasks(listId: string, status?: string): Promise<TaskPreview[]> {
+	const query = status ? `?status=${encodeURIComponent(status)}` : '';
+	return get<TaskPreview[]>(`/clickup/lists/${listId}/tasks${query}`);
+}
+
+export interface CreateTaskRequest {
+	name: string;
+	description?: string;
+	/** 1=urgent, 2=high, 3=normal, 4=low */
+	priority?: number;
+	/** Initial status, the list's first status when omitted */
+	status?: string;
+}
+
+export interface CreatedTask {
+	id: string;
+	name: string;
+	status: { status: string };
+}
+
+export async function createListTask(listId: string, task: CreateTaskRequest): Promise<CreatedTask> {
+	return post<CreatedTask>(`/clickup/lists/${listId}/tasks`, task);
+}
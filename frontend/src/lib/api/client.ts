@@ -15,7 +15,17 @@ export class ApiError extends Error {
 async function handleResponse<T>(response: Response): Promise<T> {
 	if (!response.ok) {
 		const text = await response.text();
-		throw new ApiError(response.status, text || response.statusText);
+		let message = text || response.statusText;
+		try {
+			// Prefer the `{ error }` body the backend sends with error statuses
+			const body = JSON.parse(text);
+			if (typeof body?.error === 'string') {
+				message = body.error;
+			}
+		} catch {
+			// Not JSON, keep the raw text
+		}
+		throw new ApiError(response.status, message);
 	}
 	return response.json();
 }
@@ -47,6 +57,17 @@ export async function put<T>(endpoint: string, data: unknown): Promise<T> {
 	return handleResponse<T>(response);
 }
 
+export async function patch<T>(endpoint: string, data: unknown): Promise<T> {
+	const response = await fetch(`${API_BASE}${endpoint}`, {
+		method: 'PATCH',
+		headers: {
+			'Content-Type': 'application/json'
+		},
+		body: JSON.stringify(data)
+	});
+	return handleResponse<T>(response);
+}
+
 export async function del<T>(endpoint: string): Promise<T> {
 	const response = await fetch(`${API_BASE}${endpoint}`, {
 		method: 'DELETE'
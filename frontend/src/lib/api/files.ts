@@ -0,0 +1,24 @@
+// Files API
+
+import { get } from './client';
+
+export interface SearchMatch {
+	path: string;
+	line_number: number;
+	line: string;
+}
+
+export interface SearchResponse {
+	matches: SearchMatch[];
+	truncated: boolean;
+}
+
+export async function searchFiles(
+	path: string,
+	query: string,
+	regex = false
+): Promise<SearchResponse> {
+	const params = new URLSearchParams({ path, query });
+	if (regex) params.set('regex', 'true');
+	return get<SearchResponse>(`/files/search?${params}`);
+}
@@ -7,11 +7,26 @@ export interface ValidatePathResponse {
 	error?: string;
 }
 
+export interface BranchInfo {
+	name: string;
+	worktree_path: string | null;
+}
+
 export interface BranchesResponse {
 	branches: string[];
+	details: BranchInfo[];
 	current?: string;
 }
 
+export interface GitStatus {
+	branch: string | null;
+	upstream: string | null;
+	ahead: number;
+	behind: number;
+	dirty: boolean;
+	remote_url: string | null;
+}
+
 export interface DetectPathResponse {
 	found: boolean;
 	path?: string;
@@ -21,14 +36,27 @@ export async function validatePath(path: string): Promise<ValidatePathResponse>
 	return post<ValidatePathResponse>('/git/validate-path', { path });
 }
 
-export async function getBranches(path: string): Promise<BranchesResponse> {
-	return get<BranchesResponse>(`/git/branches?path=${encodeURIComponent(path)}`);
+export async function getBranches(
+	path: string,
+	excludeTaskBranches = false
+): Promise<BranchesResponse> {
+	const query = excludeTaskBranches ? '&exclude_task_branches=true' : '';
+	return get<BranchesResponse>(`/git/branches?path=${encodeURIComponent(path)}${query}`);
 }
 
 export async function fetchRepo(path: string): Promise<{ success: boolean }> {
 	return post<{ success: boolean }>('/git/fetch', { path });
 }
 
+export async function getGitStatus(path: string): Promise<GitStatus> {
+	return get<GitStatus>(`/git/status?path=${encodeURIComponent(path)}`);
+}
+
+/** Detect a repository's default branch, null when it can't be determined */
+export async function detectDefaultBranch(path: string): Promise<{ branch: string | null }> {
+	return post<{ branch: string | null }>('/git/default-branch', { path });
+}
+
 export async function detectPath(markerFilename: string): Promise<DetectPathResponse> {
 	return post<DetectPathResponse>('/git/detect-path', { marker_filename: markerFilename });
 }
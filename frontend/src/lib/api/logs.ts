@@ -0,0 +1,38 @@
+// Global task logs API
+
+import { get } from './client';
+
+export interface GlobalLogEntry {
+	id: number;
+	task_id: number;
+	/** Name of the task, null if it has been deleted */
+	task_name: string | null;
+	event_type: string;
+	message: string;
+	created_at: string;
+}
+
+export interface GlobalLogsPage {
+	logs: GlobalLogEntry[];
+	total: number;
+}
+
+export interface GlobalLogsFilter {
+	event_type?: string;
+	task_id?: number;
+	/** RFC 3339 timestamp */
+	since?: string;
+	limit?: number;
+	offset?: number;
+}
+
+export async function getLogs(filter: GlobalLogsFilter = {}): Promise<GlobalLogsPage> {
+	const params = new URLSearchParams();
+	for (const [key, value] of Object.entries(filter)) {
+		if (value !== undefined && value !== '') {
+			params.set(key, String(value));
+		}
+	}
+	const query = params.toString();
+	return get<GlobalLogsPage>(query ? `/logs?${query}` : '/logs');
+}
@@ -1,10 +1,11 @@
 // Tasks API
 
-import { get, post, del } from './client';
+import { get, post, put, patch, del } from './client';
 
 export interface Task {
 	id: number;
 	clickup_task_id: string;
+	clickup_url: string | null;
 	name: string;
 	description?: string;
 	priority?: number;
@@ -14,6 +15,14 @@ export interface Task {
 	started_at?: string;
 	completed_at?: string;
 	is_running: boolean;
+	note?: string;
+	agent_type?: string;
+	/** Percent complete as last reported by the agent */
+	progress?: number | null;
+	/** `.env`-style file relative to the worktree, loaded into the agent */
+	env_file?: string | null;
+	/** Times the task was restarted automatically after failing */
+	restart_count?: number;
 }
 
 export interface TaskStats {
@@ -22,22 +31,136 @@ export interface TaskStats {
 	stopped: number;
 	completed: number;
 	failed: number;
+	needs_review: number;
 	running_processes: number;
+	parallel_limit: number;
+	available_slots: number;
+	running: RunningTask[];
 }
 
-export async function getTasks(status?: string): Promise<Task[]> {
-	const query = status ? `?status=${encodeURIComponent(status)}` : '';
-	return get<Task[]>(`/tasks${query}`);
+export interface RunningTask {
+	id: number;
+	name: string;
+	elapsed_ms: number;
+}
+
+export interface TaskDiffStat {
+	task_id: number;
+	files_changed: number;
+	insertions: number;
+	deletions: number;
+}
+
+export interface TaskLogEntry {
+	id: number;
+	event_type: string;
+	message: string;
+	created_at: string;
+}
+
+export interface TaskLogsResponse {
+	logs: TaskLogEntry[];
+	last_id: number | null;
+}
+
+export interface TaskListFilter {
+	tag?: string;
+	priority?: number;
+	priority_min?: number;
+	priority_max?: number;
+	/** RFC 3339 timestamp or YYYY-MM-DD */
+	created_after?: string;
+	/** RFC 3339 timestamp or YYYY-MM-DD */
+	created_before?: string;
+}
+
+export interface TaskListResponse {
+	tasks: Task[];
+	/** The filters that were applied, unset ones are null */
+	filters: { [K in keyof TaskListFilter]: TaskListFilter[K] | null } & { status: string | null };
+}
+
+export async function getTasks(
+	status?: string,
+	filter: TaskListFilter = {}
+): Promise<TaskListResponse> {
+	const params = new URLSearchParams();
+	for (const [key, value] of Object.entries({ status, ...filter })) {
+		if (value !== undefined && value !== '') {
+			params.set(key, String(value));
+		}
+	}
+	const query = params.toString();
+	return get<TaskListResponse>(query ? `/tasks?${query}` : '/tasks');
 }
 
 export async function getTask(id: number): Promise<Task> {
 	return get<Task>(`/tasks/${id}`);
 }
 
+export async function getTaskLogs(
+	id: number,
+	afterId?: number | null,
+	limit?: number
+): Promise<TaskLogsResponse> {
+	const params = new URLSearchParams();
+	if (afterId != null) params.set('after_id', String(afterId));
+	if (limit != null) params.set('limit', String(limit));
+	const query = params.toString() ? `?${params}` : '';
+	return get<TaskLogsResponse>(`/tasks/${id}/logs${query}`);
+}
+
+/** The last `lines` log rows of a task, oldest first */
+export async function getTaskLogTail(id: number, lines?: number): Promise<TaskLogsResponse> {
+	const query = lines != null ? `?lines=${lines}` : '';
+	return get<TaskLogsResponse>(`/tasks/${id}/logs/tail${query}`);
+}
+
+export interface ProcessSession {
+	id: number;
+	task_id: number;
+	pid: number | null;
+	started_at: string;
+	ended_at: string | null;
+	exit_code: number | null;
+	duration_ms: number | null;
+}
+
+export interface SessionsPage {
+	sessions: ProcessSession[];
+	total: number;
+}
+
+export async function getTaskSessions(id: number): Promise<ProcessSession[]> {
+	return get<ProcessSession[]>(`/tasks/${id}/sessions`);
+}
+
+export async function getSessions(limit?: number, offset?: number): Promise<SessionsPage> {
+	const params = new URLSearchParams();
+	if (limit != null) params.set('limit', String(limit));
+	if (offset != null) params.set('offset', String(offset));
+	const query = params.toString() ? `?${params}` : '';
+	return get<SessionsPage>(`/sessions${query}`);
+}
+
 export async function getTaskStats(): Promise<TaskStats> {
 	return get<TaskStats>('/tasks/stats');
 }
 
+export async function getTaskDiffStats(): Promise<TaskDiffStat[]> {
+	return get<TaskDiffStat[]>('/tasks/diffstat');
+}
+
+export interface KillAllResponse {
+	task_ids: number[];
+	session_ids: string[];
+}
+
+/** Kill every running agent and UI session, whatever the task statuses say */
+export async function killAllProcesses(): Promise<KillAllResponse> {
+	return post<KillAllResponse>('/tasks/kill-all');
+}
+
 export async function stopTask(id: number): Promise<Task> {
 	return post<Task>(`/tasks/${id}/stop`);
 }
@@ -46,6 +169,49 @@ export async function restartTask(id: number): Promise<Task> {
 	return post<Task>(`/tasks/${id}/restart`);
 }
 
+export async function abandonTask(id: number): Promise<Task> {
+	return post<Task>(`/tasks/${id}/abandon`);
+}
+
+/** Replace the task's worktree with a fresh branch off `baseBranch` (default: the task's base branch) */
+export async function rebaseTaskWorktree(id: number, baseBranch?: string): Promise<Task> {
+	return post<Task>(`/tasks/${id}/rebase-worktree`, { base_branch: baseBranch });
+}
+
+export async function updateTaskNote(id: number, note: string | null): Promise<Task> {
+	return put<Task>(`/tasks/${id}/note`, { note });
+}
+
+/** Replace a queued task's description; fails once the task has started */
+export async function updateTaskDescription(id: number, description: string | null): Promise<Task> {
+	return put<Task>(`/tasks/${id}/description`, { description });
+}
+
+/** Set the task's agent type; an empty string restores the default */
+export async function updateTaskAgentType(id: number, agentType: string): Promise<Task> {
+	return patch<Task>(`/tasks/${id}`, { agent_type: agentType });
+}
+
+/** Set the task's env file, relative to its worktree; an empty string clears it */
+export async function updateTaskEnvFile(id: number, envFile: string): Promise<Task> {
+	return patch<Task>(`/tasks/${id}`, { env_file: envFile });
+}
+
 export async function deleteTask(id: number): Promise<{ success: boolean; message: string }> {
 	return del<{ success: boolean; message: string }>(`/tasks/${id}`);
 }
+
+export interface CleanupResult {
+	deleted_tasks: number;
+	deleted_sessions: number;
+	deleted_logs: number;
+	removed_worktrees: number;
+}
+
+/** Delete finished tasks with their sessions, logs and worktrees; in_progress tasks are never removed */
+export async function cleanupTasks(
+	statuses: string[] = ['completed'],
+	olderThanDays?: number
+): Promise<CleanupResult> {
+	return post<CleanupResult>('/tasks/cleanup', { statuses, older_than_days: olderThanDays });
+}
@@ -1,6 +1,6 @@
 // Voice Assistant API
 
-import { post, del } from './client';
+import { get, post, del } from './client';
 
 export type AgentType = 'claude' | 'codex' | 'gemini';
 
@@ -39,6 +39,23 @@ export interface GenerateTasksResponse {
 	session_id?: string;
 }
 
+export interface SessionOutputResponse {
+	lines: string[];
+	next: number;
+	done: boolean;
+	exit_code: number | null;
+	agent_session_id: string | null;
+	/** Lines dropped because the session exceeded the output cap */
+	dropped_lines: number;
+}
+
+export interface SessionStatusResponse {
+	/** Number of BA agent sessions currently running */
+	active: number;
+	/** Maximum number of concurrent sessions, 0 when unlimited */
+	limit: number;
+}
+
 export interface ClearScreenshotsResponse {
 	success: boolean;
 	message: string;
@@ -64,16 +81,22 @@ export async function saveScreenshot(
  * @param transcript The voice transcription text
  * @param screenshots Array of screenshot filepaths (relative to repo)
  * @param agent Which agent to use (claude, codex, gemini)
+ * @param continueSession A previous session to continue, if the agent supports it
+ * @param idempotencyKey Key identifying this submission; repeats reuse the first session
  */
 export async function generateTasks(
 	transcript: string,
 	screenshots: string[],
-	agent: AgentType = 'claude'
+	agent: AgentType = 'claude',
+	continueSession?: string,
+	idempotencyKey?: string
 ): Promise<GenerateTasksResponse> {
 	return post<GenerateTasksResponse>('/voice/generate-tasks', {
 		transcript,
 		screenshots,
-		agent
+		agent,
+		continue_session: continueSession,
+		idempotency_key: idempotencyKey
 	});
 }
 
@@ -83,3 +106,32 @@ export async function generateTasks(
 export async function clearScreenshots(): Promise<ClearScreenshotsResponse> {
 	return del<ClearScreenshotsResponse>('/voice/screenshots');
 }
+
+/**
+ * Cancel a running BA agent session
+ * @param sessionId The session id returned by generateTasks
+ */
+export async function cancelSession(sessionId: string): Promise<ClearScreenshotsResponse> {
+	return post<ClearScreenshotsResponse>(`/voice/cancel/${encodeURIComponent(sessionId)}`, {});
+}
+
+/**
+ * Get the number of running BA agent sessions and the configured limit
+ */
+export async function getSessionStatus(): Promise<SessionStatusResponse> {
+	return get<SessionStatusResponse>('/voice/sessions');
+}
+
+/**
+ * Poll the output of a BA agent session
+ * @param sessionId The session id returned by generateTasks
+ * @param since Number of lines already received
+ */
+export async function getSessionOutput(
+	sessionId: string,
+	since = 0
+): Promise<SessionOutputResponse> {
+	return get<SessionOutputResponse>(
+		`/voice/session/${encodeURIComponent(sessionId)}/output?since=${since}`
+	);
+}
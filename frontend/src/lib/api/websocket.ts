@@ -8,6 +8,11 @@ export interface OutputMessage {
 	is_stderr: boolean;
 }
 
+export interface OutputBatchMessage {
+	type: 'output_batch';
+	lines: { line: string; is_stderr: boolean }[];
+}
+
 export interface ConnectedMessage {
 	type: 'connected';
 	task_id: number;
@@ -19,7 +24,27 @@ export interface ErrorMessage {
 	message: string;
 }
 
-export type WsMessage = OutputMessage | ConnectedMessage | ErrorMessage;
+export interface TaskSnapshotMessage {
+	type: 'task_snapshot';
+	task_id: number;
+	status: string;
+	time_spent_ms: number;
+	pid: number | null;
+	last_exit_code: number | null;
+}
+
+export interface ProgressMessage {
+	type: 'progress';
+	task_id: number;
+	progress: number;
+}
+
+export type WsMessage =
+	| OutputMessage
+	| ConnectedMessage
+	| ErrorMessage
+	| TaskSnapshotMessage
+	| ProgressMessage;
 
 export class TerminalWebSocket {
 	private ws: WebSocket | null = null;
@@ -53,8 +78,15 @@ export class TerminalWebSocket {
 
 		this.ws.onmessage = (event) => {
 			try {
-				const msg = JSON.parse(event.data) as WsMessage;
-				this.onMessage(msg);
+				const msg = JSON.parse(event.data) as WsMessage | OutputBatchMessage;
+				if (msg.type === 'output_batch') {
+					// Unpack coalesced frames into individual output lines
+					for (const entry of msg.lines) {
+						this.onMessage({ type: 'output', ...entry });
+					}
+				} else {
+					this.onMessage(msg);
+				}
 			} catch (e) {
 				console.error('Failed to parse WebSocket message:', e);
 			}
@@ -88,6 +120,12 @@ export class TerminalWebSocket {
 		}
 	}
 
+	requestSnapshot(): void {
+		if (this.ws?.readyState === WebSocket.OPEN) {
+			this.ws.send(JSON.stringify({ type: 'snapshot' }));
+		}
+	}
+
 	disconnect(): void {
 		this.maxReconnectAttempts = 0; // Prevent reconnection
 		this.ws?.close();
@@ -98,3 +136,42 @@ export class TerminalWebSocket {
 		return this.ws?.readyState === WebSocket.OPEN;
 	}
 }
+
+export interface TaskEvent {
+	type: 'created' | 'status_changed' | 'completed' | 'lagged';
+	task_id?: number;
+	status?: string;
+	/** Number of events missed, only set on `lagged` */
+	missed?: number;
+}
+
+/**
+ * Stream task lifecycle events, reconnecting when the connection drops
+ * @returns A function that closes the stream
+ */
+export function subscribeTaskEvents(onEvent: (event: TaskEvent) => void): () => void {
+	let ws: WebSocket | null = null;
+	let closed = false;
+
+	function connect() {
+		ws = new WebSocket(`${WS_BASE}/ws/events`);
+		ws.onmessage = (event) => {
+			try {
+				onEvent(JSON.parse(event.data) as TaskEvent);
+			} catch (e) {
+				console.error('Failed to parse task event:', e);
+			}
+		};
+		ws.onclose = () => {
+			if (!closed) {
+				setTimeout(connect, 5000);
+			}
+		};
+	}
+
+	connect();
+	return () => {
+		closed = true;
+		ws?.close();
+	};
+}
@@ -13,7 +13,7 @@ export function useTasks() {
 		loading = true;
 		error = null;
 		try {
-			tasks = await getTasks();
+			tasks = (await getTasks()).tasks;
 		} catch (e) {
 			error = e instanceof Error ? e.message : 'Failed to load tasks';
 		} finally {
@@ -97,6 +97,9 @@ export function useTasks() {
 		},
 		get failed() {
 			return getTasksByStatus('failed');
+		},
+		get needsReview() {
+			return getTasksByStatus('needs_review');
 		}
 	};
 }